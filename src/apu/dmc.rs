@@ -0,0 +1,195 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
+/// Timer periods of the DMC in CPU cycles, for NTSC.
+const RATE_TABLE: [u16; 16] = [428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54];
+/// Number of cycles the CPU is halted for while the DMC fetches a sample byte.
+pub const DMC_STALL_CYCLES: u16 = 4;
+
+/// The delta modulation channel ($4010-$4013), which plays 1-bit delta encoded samples it reads
+/// straight from CPU memory.
+/// Ref: https://www.nesdev.org/wiki/APU_DMC
+#[derive(Debug, Clone)]
+pub struct Dmc {
+    pub irq_enabled: bool,
+    /// The sample finished playing with IRQs enabled
+    pub irq_pending: bool,
+    looping: bool,
+    timer: u16,
+    timer_period: u16,
+    /// The 7-bit output level
+    level: u8,
+    sample_address: u16,
+    sample_length: u16,
+
+    // Memory reader
+
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+
+    // Output unit
+
+    shift: u8,
+    bits_remaining: u8,
+    silence: bool,
+}
+
+impl Default for Dmc {
+    fn default() -> Self {
+        Dmc {
+            irq_enabled: false,
+            irq_pending: false,
+            looping: false,
+            timer: 0,
+            timer_period: RATE_TABLE[0],
+            level: 0,
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift: 0,
+            bits_remaining: 8,
+            silence: true,
+        }
+    }
+}
+
+impl Dmc {
+    /// Write to one of the channel's 4 registers.
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register & 0x3 {
+            0 => {
+                self.irq_enabled = data & 0x80 != 0;
+                if !self.irq_enabled {
+                    self.irq_pending = false;
+                }
+                self.looping = data & 0x40 != 0;
+                self.timer_period = RATE_TABLE[(data & 0x0F) as usize];
+            },
+            1 => self.level = data & 0x7F,
+            2 => self.sample_address = 0xC000 | ((data as u16) << 6),
+            _ => self.sample_length = ((data as u16) << 4) + 1,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    /// Whether there are still sample bytes left to be read.
+    pub fn active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    /// The address of the next sample byte, if the sample buffer needs to be refilled.
+    pub fn fetch_address(&self) -> Option<u16> {
+        if self.sample_buffer.is_none() && self.bytes_remaining > 0 {
+            Some(self.current_address)
+        } else {
+            None
+        }
+    }
+
+    /// Fill the sample buffer with the byte read from `fetch_address`.
+    pub fn fill_sample_buffer(&mut self, data: u8) {
+        self.sample_buffer = Some(data);
+        // The address wraps around to $8000 instead of $0000
+        self.current_address = self.current_address.checked_add(1).unwrap_or(0x8000);
+        self.bytes_remaining -= 1;
+
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq_pending = true;
+            }
+        }
+    }
+
+    /// Clock the timer, which happens every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.timer_period - 1;
+
+        if !self.silence {
+            // Move the level up or down by 2, unless that would leave the 0-127 range
+            if self.shift & 1 != 0 {
+                if self.level <= 125 {
+                    self.level += 2;
+                }
+            } else if self.level >= 2 {
+                self.level -= 2;
+            }
+        }
+        self.shift >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(sample) => {
+                    self.silence = false;
+                    self.shift = sample;
+                },
+                None => self.silence = true,
+            }
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        self.level
+    }
+}
+
+impl SaveState for Dmc {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.irq_enabled);
+        writer.write_bool(self.irq_pending);
+        writer.write_bool(self.looping);
+        writer.write_u16(self.timer);
+        writer.write_u16(self.timer_period);
+        writer.write_u8(self.level);
+        writer.write_u16(self.sample_address);
+        writer.write_u16(self.sample_length);
+        writer.write_u16(self.current_address);
+        writer.write_u16(self.bytes_remaining);
+        writer.write_bool(self.sample_buffer.is_some());
+        writer.write_u8(self.sample_buffer.unwrap_or(0));
+        writer.write_u8(self.shift);
+        writer.write_u8(self.bits_remaining);
+        writer.write_bool(self.silence);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.irq_enabled = reader.read_bool()?;
+        self.irq_pending = reader.read_bool()?;
+        self.looping = reader.read_bool()?;
+        self.timer = reader.read_u16()?;
+        self.timer_period = reader.read_u16()?;
+        self.level = reader.read_u8()? & 0x7F;
+        self.sample_address = reader.read_u16()?;
+        self.sample_length = reader.read_u16()?;
+        self.current_address = reader.read_u16()?;
+        self.bytes_remaining = reader.read_u16()?;
+        let buffered = reader.read_bool()?;
+        let sample = reader.read_u8()?;
+        self.sample_buffer = buffered.then_some(sample);
+        self.shift = reader.read_u8()?;
+        self.bits_remaining = reader.read_u8()?;
+        self.silence = reader.read_bool()?;
+        Ok(())
+    }
+}
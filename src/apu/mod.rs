@@ -0,0 +1,312 @@
+pub mod dmc;
+pub mod noise;
+pub mod pulse;
+pub mod triangle;
+pub mod units;
+
+use crate::{mapper::Mapper, save_state::{SaveState, SaveStateError, StateReader, StateWriter}};
+use self::{dmc::{Dmc, DMC_STALL_CYCLES}, noise::Noise, pulse::Pulse, triangle::Triangle};
+
+/// Clock rate of the NTSC CPU, and therefore the APU, in Hz.
+pub const CPU_CLOCK_NTSC: f64 = 1_789_773.0;
+/// Clock rate of the PAL CPU in Hz.
+pub const CPU_CLOCK_PAL: f64 = 1_662_607.0;
+/// Clock rate of the Dendy CPU in Hz.
+pub const CPU_CLOCK_DENDY: f64 = 1_773_448.0;
+/// Sample rate of the audio output when none is configured.
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+/// CPU cycles at which the frame counter clocks the envelopes and length counters. The last step
+/// is only used in 5-step mode.
+const FRAME_STEPS: [u32; 5] = [7457, 14913, 22371, 29829, 37281];
+/// Length of a 4-step frame in CPU cycles.
+const FOUR_STEP_PERIOD: u32 = 29830;
+/// Length of a 5-step frame in CPU cycles.
+const FIVE_STEP_PERIOD: u32 = 37282;
+
+/// The 2A03's Audio Processing Unit, clocked once per CPU cycle. The output of the 5 channels is
+/// combined by the same non-linear mixer as the real hardware and resampled to the configured
+/// sample rate.
+/// Ref: https://www.nesdev.org/wiki/APU
+#[derive(Debug, Clone)]
+pub struct Apu {
+    pub pulse_1: Pulse,
+    pub pulse_2: Pulse,
+    pub triangle: Triangle,
+    pub noise: Noise,
+    pub dmc: Dmc,
+
+    // Frame counter ($4017)
+    // Ref: https://www.nesdev.org/wiki/APU_Frame_Counter
+
+    /// Use the 5-step sequence, which doesn't generate IRQs
+    five_step: bool,
+    irq_inhibit: bool,
+    /// The frame counter reached the end of a 4-step sequence with IRQs enabled
+    frame_irq: bool,
+    /// CPU cycles into the current frame counter sequence
+    frame_cycle: u32,
+    /// Whether the current CPU cycle is an odd one, on which the pulse and noise timers are clocked
+    odd_cycle: bool,
+    /// CPU cycles the DMC has stalled the CPU for since the last call to `take_stall_cycles`
+    stall_cycles: u16,
+
+    // Resampling
+
+    cpu_clock_rate: f64,
+    sample_rate: u32,
+    /// How far along the current output sample we are, from 0.0 to 1.0
+    sample_phase: f64,
+    sample_sum: f32,
+    sample_count: u32,
+    samples: Vec<f32>,
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Apu {
+    pub fn new() -> Self {
+        Apu {
+            pulse_1: Pulse::new(true),
+            pulse_2: Pulse::new(false),
+            triangle: Triangle::default(),
+            noise: Noise::default(),
+            dmc: Dmc::default(),
+            five_step: false,
+            irq_inhibit: false,
+            frame_irq: false,
+            frame_cycle: 0,
+            odd_cycle: false,
+            stall_cycles: 0,
+            cpu_clock_rate: CPU_CLOCK_NTSC,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            sample_phase: 0.0,
+            sample_sum: 0.0,
+            sample_count: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Reset the APU, which silences all channels.
+    pub fn reset(&mut self) {
+        self.write(0x4015, 0x00);
+        self.dmc.irq_pending = false;
+        self.frame_irq = false;
+        self.frame_cycle = 0;
+    }
+
+    /// Change the rate at which samples are produced.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Change the rate at which the APU is clocked, e.g. for PAL consoles.
+    pub fn set_cpu_clock_rate(&mut self, cpu_clock_rate: f64) {
+        self.cpu_clock_rate = cpu_clock_rate;
+    }
+
+    /// Take the samples produced since the last call, each in the range 0.0-1.0.
+    pub fn take_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.samples)
+    }
+
+    /// Take the number of cycles the DMC has halted the CPU for since the last call.
+    pub fn take_stall_cycles(&mut self) -> u16 {
+        std::mem::take(&mut self.stall_cycles)
+    }
+
+    /// Whether the frame counter or the DMC is asserting the IRQ line.
+    pub fn irq(&self) -> bool {
+        self.frame_irq || self.dmc.irq_pending
+    }
+
+    /// Read the status register ($4015), which acknowledges the frame counter IRQ.
+    pub fn read_status(&mut self) -> u8 {
+        let status = self.peek_status();
+        self.frame_irq = false;
+        status
+    }
+
+    /// Read the status register ($4015) without any side effects.
+    pub fn peek_status(&self) -> u8 {
+        (self.pulse_1.length.active() as u8)
+            | (self.pulse_2.length.active() as u8) << 1
+            | (self.triangle.length.active() as u8) << 2
+            | (self.noise.length.active() as u8) << 3
+            | (self.dmc.active() as u8) << 4
+            | (self.frame_irq as u8) << 6
+            | (self.dmc.irq_pending as u8) << 7
+    }
+
+    /// Write to one of the registers at $4000-$4013, $4015 or $4017.
+    pub fn write(&mut self, address: u16, data: u8) {
+        match address {
+            0x4000..=0x4003 => self.pulse_1.write(address, data),
+            0x4004..=0x4007 => self.pulse_2.write(address, data),
+            0x4008..=0x400B => self.triangle.write(address, data),
+            0x400C..=0x400F => self.noise.write(address, data),
+            0x4010..=0x4013 => self.dmc.write(address, data),
+            0x4015 => {
+                self.pulse_1.length.set_enabled(data & 0x01 != 0);
+                self.pulse_2.length.set_enabled(data & 0x02 != 0);
+                self.triangle.length.set_enabled(data & 0x04 != 0);
+                self.noise.length.set_enabled(data & 0x08 != 0);
+                self.dmc.set_enabled(data & 0x10 != 0);
+                self.dmc.irq_pending = false;
+            },
+            0x4017 => {
+                self.five_step = data & 0x80 != 0;
+                self.irq_inhibit = data & 0x40 != 0;
+                if self.irq_inhibit {
+                    self.frame_irq = false;
+                }
+
+                // Switching to the 5-step sequence immediately clocks all units
+                self.frame_cycle = 0;
+                if self.five_step {
+                    self.clock_quarter_frame();
+                    self.clock_half_frame();
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Simulates the passing of a single CPU cycle. The DMC reads its samples through the mapper,
+    /// as they are always located in cartridge space.
+    pub fn clock(&mut self, mapper: &Option<Box<dyn Mapper>>) {
+        self.clock_frame_counter();
+
+        self.triangle.clock_timer();
+        self.dmc.clock_timer();
+        if self.odd_cycle {
+            self.pulse_1.clock_timer();
+            self.pulse_2.clock_timer();
+            self.noise.clock_timer();
+        }
+        self.odd_cycle = !self.odd_cycle;
+
+        if let Some(address) = self.dmc.fetch_address() {
+            let data = mapper.as_ref().and_then(|mapper| mapper.cpu_read(address)).unwrap_or(0x0);
+            self.dmc.fill_sample_buffer(data);
+            self.stall_cycles += DMC_STALL_CYCLES;
+        }
+
+        self.sample();
+    }
+
+    fn clock_frame_counter(&mut self) {
+        self.frame_cycle += 1;
+
+        match FRAME_STEPS.iter().position(|&step| step == self.frame_cycle) {
+            Some(0 | 2) => self.clock_quarter_frame(),
+            Some(1) => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            },
+            Some(3) if !self.five_step => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+                if !self.irq_inhibit {
+                    self.frame_irq = true;
+                }
+            },
+            Some(4) => {
+                self.clock_quarter_frame();
+                self.clock_half_frame();
+            },
+            _ => {},
+        }
+
+        let period = if self.five_step { FIVE_STEP_PERIOD } else { FOUR_STEP_PERIOD };
+        if self.frame_cycle >= period {
+            self.frame_cycle = 0;
+        }
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse_1.clock_quarter_frame();
+        self.pulse_2.clock_quarter_frame();
+        self.triangle.clock_quarter_frame();
+        self.noise.clock_quarter_frame();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse_1.clock_half_frame();
+        self.pulse_2.clock_half_frame();
+        self.triangle.clock_half_frame();
+        self.noise.clock_half_frame();
+    }
+
+    /// Mix the output of all channels, in the range 0.0-1.0.
+    /// Ref: https://www.nesdev.org/wiki/APU_Mixer
+    pub fn mix(&self) -> f32 {
+        let pulse = (self.pulse_1.output() + self.pulse_2.output()) as f32;
+        let pulse_out = if pulse == 0.0 { 0.0 } else { 95.88 / (8128.0 / pulse + 100.0) };
+
+        let tnd = self.triangle.output() as f32 / 8227.0 + self.noise.output() as f32 / 12241.0 + self.dmc.output() as f32 / 22638.0;
+        let tnd_out = if tnd == 0.0 { 0.0 } else { 159.79 / (1.0 / tnd + 100.0) };
+
+        pulse_out + tnd_out
+    }
+
+    /// Average the mixer output over the CPU cycles that make up an output sample.
+    fn sample(&mut self) {
+        self.sample_sum += self.mix();
+        self.sample_count += 1;
+
+        self.sample_phase += self.sample_rate as f64 / self.cpu_clock_rate;
+        if self.sample_phase >= 1.0 {
+            self.sample_phase -= 1.0;
+            self.samples.push(self.sample_sum / self.sample_count as f32);
+            self.sample_sum = 0.0;
+            self.sample_count = 0;
+        }
+    }
+}
+
+impl SaveState for Apu {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.pulse_1.save_state(writer);
+        self.pulse_2.save_state(writer);
+        self.triangle.save_state(writer);
+        self.noise.save_state(writer);
+        self.dmc.save_state(writer);
+        writer.write_bool(self.five_step);
+        writer.write_bool(self.irq_inhibit);
+        writer.write_bool(self.frame_irq);
+        writer.write_u32(self.frame_cycle);
+        writer.write_bool(self.odd_cycle);
+        writer.write_u16(self.stall_cycles);
+        writer.write_f64(self.sample_phase);
+        writer.write_f32(self.sample_sum);
+        writer.write_u32(self.sample_count);
+    }
+
+    /// Samples the frontend hasn't taken yet are kept, they were produced before the state was loaded.
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.pulse_1.load_state(reader)?;
+        self.pulse_2.load_state(reader)?;
+        self.triangle.load_state(reader)?;
+        self.noise.load_state(reader)?;
+        self.dmc.load_state(reader)?;
+        self.five_step = reader.read_bool()?;
+        self.irq_inhibit = reader.read_bool()?;
+        self.frame_irq = reader.read_bool()?;
+        self.frame_cycle = reader.read_u32()?;
+        self.odd_cycle = reader.read_bool()?;
+        self.stall_cycles = reader.read_u16()?;
+        self.sample_phase = reader.read_f64()?;
+        self.sample_sum = reader.read_f32()?;
+        self.sample_count = reader.read_u32()?;
+        Ok(())
+    }
+}
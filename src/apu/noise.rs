@@ -0,0 +1,104 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+use super::units::{Envelope, LengthCounter};
+
+/// Timer periods of the noise channel in CPU cycles, for NTSC.
+const PERIOD_TABLE: [u16; 16] = [4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068];
+
+/// The pseudo-random noise channel ($400C-$400F), driven by a 15-bit linear feedback shift
+/// register.
+/// Ref: https://www.nesdev.org/wiki/APU_Noise
+#[derive(Debug, Clone)]
+pub struct Noise {
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+    /// Feed back bit 6 instead of bit 1, producing a short, metallic sounding sequence
+    mode: bool,
+    shift: u16,
+    timer: u16,
+    timer_period: u16,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise {
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+            mode: false,
+            // The shift register is loaded with 1 on power-up
+            shift: 1,
+            timer: 0,
+            timer_period: PERIOD_TABLE[0],
+        }
+    }
+}
+
+impl Noise {
+    /// Write to one of the channel's 4 registers.
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register & 0x3 {
+            0 => {
+                self.envelope.write(data);
+                self.length.halt = data & 0x20 != 0;
+            },
+            1 => {},
+            2 => {
+                self.mode = data & 0x80 != 0;
+                // Periods are in CPU cycles, the timer is clocked every APU cycle
+                self.timer_period = PERIOD_TABLE[(data & 0x0F) as usize] / 2;
+            },
+            _ => {
+                self.length.load(data >> 3);
+                self.envelope.start = true;
+            },
+        }
+    }
+
+    /// Clock the timer, which happens every APU cycle (every other CPU cycle).
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period - 1;
+            let tap = if self.mode { 6 } else { 1 };
+            let feedback = (self.shift ^ (self.shift >> tap)) & 1;
+            self.shift = (self.shift >> 1) | (feedback << 14);
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length.active() || self.shift & 1 != 0 {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
+
+impl SaveState for Noise {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.envelope.save_state(writer);
+        self.length.save_state(writer);
+        writer.write_bool(self.mode);
+        writer.write_u16(self.shift);
+        writer.write_u16(self.timer);
+        writer.write_u16(self.timer_period);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.envelope.load_state(reader)?;
+        self.length.load_state(reader)?;
+        self.mode = reader.read_bool()?;
+        self.shift = reader.read_u16()?;
+        self.timer = reader.read_u16()?;
+        self.timer_period = reader.read_u16()?;
+        Ok(())
+    }
+}
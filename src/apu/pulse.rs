@@ -0,0 +1,163 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+use super::units::{Envelope, LengthCounter};
+
+/// The 4 duty cycles of the pulse channels: 12.5%, 25%, 50% and 25% negated.
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+/// One of the two square wave channels ($4000-$4003 and $4004-$4007).
+/// Ref: https://www.nesdev.org/wiki/APU_Pulse
+#[derive(Debug, Clone)]
+pub struct Pulse {
+    /// The first pulse channel negates its sweep with one's complement instead of two's complement
+    first: bool,
+    pub envelope: Envelope,
+    pub length: LengthCounter,
+    duty: u8,
+    sequence: u8,
+    timer: u16,
+    timer_period: u16,
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_reload: bool,
+    sweep_divider: u8,
+}
+
+impl Pulse {
+    pub fn new(first: bool) -> Self {
+        Pulse {
+            first,
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+            duty: 0,
+            sequence: 0,
+            timer: 0,
+            timer_period: 0,
+            sweep_enabled: false,
+            sweep_period: 0,
+            sweep_negate: false,
+            sweep_shift: 0,
+            sweep_reload: false,
+            sweep_divider: 0,
+        }
+    }
+
+    /// Write to one of the channel's 4 registers.
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register & 0x3 {
+            0 => {
+                self.duty = data >> 6;
+                self.envelope.write(data);
+                self.length.halt = data & 0x20 != 0;
+            },
+            1 => {
+                self.sweep_enabled = data & 0x80 != 0;
+                self.sweep_period = (data >> 4) & 0x07;
+                self.sweep_negate = data & 0x08 != 0;
+                self.sweep_shift = data & 0x07;
+                self.sweep_reload = true;
+            },
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                self.length.load(data >> 3);
+                self.sequence = 0;
+                self.envelope.start = true;
+            },
+        }
+    }
+
+    /// Clock the timer, which happens every APU cycle (every other CPU cycle).
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            self.sequence = (self.sequence + 1) & 0x07;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+
+        // Ref: https://www.nesdev.org/wiki/APU_Sweep
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.sweep_muted() {
+            self.timer_period = self.sweep_target();
+        }
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    /// The period the sweep unit is moving towards.
+    fn sweep_target(&self) -> u16 {
+        let change = self.timer_period >> self.sweep_shift;
+        if !self.sweep_negate {
+            self.timer_period + change
+        } else if self.first {
+            self.timer_period.saturating_sub(change + 1)
+        } else {
+            self.timer_period.saturating_sub(change)
+        }
+    }
+
+    /// The channel is muted when the period is too short, or the sweep would overflow it, even if
+    /// the sweep unit is disabled.
+    fn sweep_muted(&self) -> bool {
+        self.timer_period < 8 || self.sweep_target() > 0x07FF
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.length.active() || self.sweep_muted() || DUTY_TABLE[self.duty as usize][self.sequence as usize] == 0 {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
+
+impl SaveState for Pulse {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.envelope.save_state(writer);
+        self.length.save_state(writer);
+        writer.write_u8(self.duty);
+        writer.write_u8(self.sequence);
+        writer.write_u16(self.timer);
+        writer.write_u16(self.timer_period);
+        writer.write_bool(self.sweep_enabled);
+        writer.write_u8(self.sweep_period);
+        writer.write_bool(self.sweep_negate);
+        writer.write_u8(self.sweep_shift);
+        writer.write_bool(self.sweep_reload);
+        writer.write_u8(self.sweep_divider);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.envelope.load_state(reader)?;
+        self.length.load_state(reader)?;
+        self.duty = reader.read_u8()? & 0x03;
+        self.sequence = reader.read_u8()? & 0x07;
+        self.timer = reader.read_u16()?;
+        self.timer_period = reader.read_u16()?;
+        self.sweep_enabled = reader.read_bool()?;
+        self.sweep_period = reader.read_u8()?;
+        self.sweep_negate = reader.read_bool()?;
+        self.sweep_shift = reader.read_u8()?;
+        self.sweep_reload = reader.read_bool()?;
+        self.sweep_divider = reader.read_u8()?;
+        Ok(())
+    }
+}
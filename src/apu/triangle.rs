@@ -0,0 +1,105 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+use super::units::LengthCounter;
+
+/// The 32 steps of the triangle wave.
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+/// The triangle wave channel ($4008-$400B). Instead of a volume, it has a second, more precise
+/// length counter called the linear counter.
+/// Ref: https://www.nesdev.org/wiki/APU_Triangle
+#[derive(Debug, Default, Clone)]
+pub struct Triangle {
+    pub length: LengthCounter,
+    sequence: u8,
+    timer: u16,
+    timer_period: u16,
+    /// Halts the length counter and keeps reloading the linear counter
+    control: bool,
+    linear_counter: u8,
+    linear_reload_value: u8,
+    linear_reload: bool,
+}
+
+impl Triangle {
+    /// Write to one of the channel's 4 registers.
+    pub fn write(&mut self, register: u16, data: u8) {
+        match register & 0x3 {
+            0 => {
+                self.control = data & 0x80 != 0;
+                self.length.halt = self.control;
+                self.linear_reload_value = data & 0x7F;
+            },
+            1 => {},
+            2 => self.timer_period = (self.timer_period & 0x0700) | data as u16,
+            _ => {
+                self.timer_period = (self.timer_period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                self.length.load(data >> 3);
+                self.linear_reload = true;
+            },
+        }
+    }
+
+    /// Clock the timer, which happens every CPU cycle.
+    pub fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.timer_period;
+            // Very short periods produce ultrasonic frequencies that can't be heard on the real
+            // hardware, freeze the sequencer instead of aliasing them.
+            if self.length.active() && self.linear_counter > 0 && self.timer_period >= 2 {
+                self.sequence = (self.sequence + 1) & 0x1F;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub fn clock_quarter_frame(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.control {
+            self.linear_reload = false;
+        }
+    }
+
+    pub fn clock_half_frame(&mut self) {
+        self.length.clock();
+    }
+
+    /// The current step of the wave. Silencing the channel only stops the sequencer, so the output
+    /// is held at its last value.
+    pub fn output(&self) -> u8 {
+        SEQUENCE[self.sequence as usize]
+    }
+}
+
+impl SaveState for Triangle {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.length.save_state(writer);
+        writer.write_u8(self.sequence);
+        writer.write_u16(self.timer);
+        writer.write_u16(self.timer_period);
+        writer.write_bool(self.control);
+        writer.write_u8(self.linear_counter);
+        writer.write_u8(self.linear_reload_value);
+        writer.write_bool(self.linear_reload);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.length.load_state(reader)?;
+        self.sequence = reader.read_u8()? & 0x1F;
+        self.timer = reader.read_u16()?;
+        self.timer_period = reader.read_u16()?;
+        self.control = reader.read_bool()?;
+        self.linear_counter = reader.read_u8()?;
+        self.linear_reload_value = reader.read_u8()?;
+        self.linear_reload = reader.read_bool()?;
+        Ok(())
+    }
+}
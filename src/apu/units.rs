@@ -0,0 +1,126 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
+/// Lengths loaded into the length counter, indexed by the upper 5 bits of the channel's fourth
+/// register.
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+/// Controls the volume of the pulse and noise channels, either as a constant or as a decaying
+/// saw wave.
+/// Ref: https://www.nesdev.org/wiki/APU_Envelope
+#[derive(Debug, Default, Clone)]
+pub struct Envelope {
+    /// Restart the envelope on the next quarter frame
+    pub start: bool,
+    /// Restart the decay when it reaches 0, shared with the length counter halt flag
+    pub looping: bool,
+    /// Output the volume directly instead of the decay level
+    pub constant: bool,
+    /// The constant volume, or the period of the divider
+    pub volume: u8,
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    /// Load the flags and volume from the lower 6 bits of the channel's first register.
+    pub fn write(&mut self, data: u8) {
+        self.looping = data & 0x20 != 0;
+        self.constant = data & 0x10 != 0;
+        self.volume = data & 0x0F;
+    }
+
+    pub fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+        } else if self.divider == 0 {
+            self.divider = self.volume;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.constant { self.volume } else { self.decay }
+    }
+}
+
+/// Silences a channel after a given number of half frames, unless halted.
+/// Ref: https://www.nesdev.org/wiki/APU_Length_Counter
+#[derive(Debug, Default, Clone)]
+pub struct LengthCounter {
+    pub enabled: bool,
+    pub halt: bool,
+    pub counter: u8,
+}
+
+impl LengthCounter {
+    /// Load the counter from the length table, if the channel is enabled.
+    pub fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(index & 0x1F) as usize];
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    pub fn clock(&mut self) {
+        if !self.halt && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        self.counter > 0
+    }
+}
+
+impl SaveState for Envelope {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.start);
+        writer.write_bool(self.looping);
+        writer.write_bool(self.constant);
+        writer.write_u8(self.volume);
+        writer.write_u8(self.divider);
+        writer.write_u8(self.decay);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.start = reader.read_bool()?;
+        self.looping = reader.read_bool()?;
+        self.constant = reader.read_bool()?;
+        self.volume = reader.read_u8()?;
+        self.divider = reader.read_u8()?;
+        self.decay = reader.read_u8()?;
+        Ok(())
+    }
+}
+
+impl SaveState for LengthCounter {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.enabled);
+        writer.write_bool(self.halt);
+        writer.write_u8(self.counter);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.enabled = reader.read_bool()?;
+        self.halt = reader.read_bool()?;
+        self.counter = reader.read_u8()?;
+        Ok(())
+    }
+}
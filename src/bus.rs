@@ -1,27 +1,276 @@
-const RAM_SIZE: usize = 64 * 1024;
+use crate::{apu::Apu, cartridge::{Cartridge, CartridgeError}, controller::{ButtonState, Controller}, mapper::{self, Mapper}, ppu::Ppu, save_state::{SaveState, SaveStateError, StateReader, StateWriter}};
 
+/// Size of the internal work RAM of the NES.
+pub const RAM_SIZE: usize = 2 * 1024;
+/// Number of memory mapped APU and I/O registers.
+pub const IO_REGISTER_COUNT: usize = 0x20;
+/// Size of the full address space of the 6502.
+pub const ADDRESS_SPACE_SIZE: usize = 64 * 1024;
+/// The upper bits of the controller ports aren't driven and read back as open bus, the last value on
+/// the data bus. That's usually the $40 of `LDA $4016`.
+const CONTROLLER_OPEN_BUS_MASK: u8 = 0xE0;
+/// Number of cycles the CPU is halted for while copying a page into OAM, plus one more if the DMA
+/// is started on an odd CPU cycle.
+pub const OAM_DMA_CYCLES: u16 = 513;
+
+/// A memory system the CPU can be connected to.
+pub trait BusDevice {
+    /// Read a byte from the bus, triggering any side effects the read may have.
+    fn read(&mut self, address: u16) -> u8;
+
+    /// Write a byte to the bus.
+    fn write(&mut self, address: u16, data: u8);
+
+    /// Read a byte from the bus without triggering any side effects, e.g. for debugging.
+    fn peek(&self, address: u16) -> u8;
+
+    /// Called at the start of every CPU cycle, with the number of cycles since power on.
+    fn cpu_clock(&mut self, _cycle: u64) {}
+
+    /// Whether any device on the bus is asserting the IRQ line.
+    fn irq(&self) -> bool {
+        false
+    }
+
+    /// Whether a device on the bus has signalled an NMI since the last call. The NMI line is edge
+    /// triggered, so polling it acknowledges the signal.
+    fn nmi(&mut self) -> bool {
+        false
+    }
+
+    /// The scanline and dot of the PPU on the bus, if any. Only used for tracing.
+    fn ppu_position(&self) -> (u16, u16) {
+        (0, 0)
+    }
+}
+
+/// A bus where every address is backed by plain RAM, useful for running bare 6502 programs.
+#[derive(Debug, Clone)]
+pub struct FlatRam {
+    pub ram: Box<[u8; ADDRESS_SPACE_SIZE]>,
+}
+
+impl Default for FlatRam {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlatRam {
+    pub fn new() -> Self {
+        FlatRam {
+            ram: Box::new([0x0; ADDRESS_SPACE_SIZE]),
+        }
+    }
+}
+
+impl BusDevice for FlatRam {
+    fn read(&mut self, address: u16) -> u8 {
+        self.ram[address as usize]
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.ram[address as usize] = data;
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.ram[address as usize]
+    }
+}
+
+/// The CPU's view of the NES memory map.
+/// Ref: https://www.nesdev.org/wiki/CPU_memory_map
 #[derive(Debug)]
 pub struct Bus {
+    /// 2 KiB of internal RAM, mirrored throughout $0000-$1FFF
     pub ram: [u8; RAM_SIZE],
+    /// The PPU, its registers are mirrored throughout $2000-$3FFF
+    pub ppu: Ppu,
+    /// The APU, whose registers are mapped at $4000-$4017
+    pub apu: Apu,
+    /// The controllers plugged into both ports, read through $4016 and $4017
+    pub controllers: [Controller; 2],
+    /// The APU and I/O registers at $4000-$401F, as last written
+    pub io_registers: [u8; IO_REGISTER_COUNT],
+    /// The mapper of the currently inserted cartridge, if any
+    pub mapper: Option<Box<dyn Mapper>>,
+    /// Number of cycles the CPU still has to be halted for because of DMA
+    stall_cycles: u16,
+    /// The CPU cycle currently in progress
+    cycle: u64,
+    /// The last value read or written on the data bus
+    open_bus: u8,
+}
+
+impl Default for Bus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Bus {
     pub fn new() -> Self {
-        Bus { 
-            ram: [0x0; RAM_SIZE] 
+        Bus {
+            ram: [0x0; RAM_SIZE],
+            ppu: Ppu::new(),
+            apu: Apu::new(),
+            controllers: Default::default(),
+            io_registers: [0x0; IO_REGISTER_COUNT],
+            mapper: None,
+            stall_cycles: 0,
+            cycle: 0,
+            open_bus: 0,
+        }
+    }
+
+    /// Insert a cartridge, mapping it into $4020-$FFFF through the mapper indicated by its header.
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) -> Result<(), CartridgeError> {
+        self.mapper = Some(mapper::from_cartridge(cartridge)?);
+        Ok(())
+    }
+
+    /// Set the buttons held down on the controller in `port` 0 or 1.
+    pub fn set_buttons(&mut self, port: usize, buttons: ButtonState) {
+        self.controllers[port].buttons = buttons;
+    }
+
+    /// Simulate a single dot of the PPU.
+    pub fn clock_ppu(&mut self) {
+        self.ppu.clock(&mut self.mapper);
+    }
+
+    /// Simulate a single CPU cycle of the APU.
+    pub fn clock_apu(&mut self) {
+        self.apu.clock(&self.mapper);
+        self.stall_cycles += self.apu.take_stall_cycles();
+    }
+
+    /// Take the number of cycles the CPU has to be halted for because of DMA transfers started since
+    /// the last call.
+    pub fn take_stall_cycles(&mut self) -> u16 {
+        std::mem::take(&mut self.stall_cycles)
+    }
+
+    /// Copy a page of CPU memory into OAM.
+    /// Ref: https://www.nesdev.org/wiki/PPU_registers#OAMDMA
+    fn oam_dma(&mut self, page: u8) {
+        for offset in 0..=0xFF {
+            let data = self.read(u16::from_le_bytes([offset, page]));
+            self.ppu.write_oam(data);
+        }
+        // The DMA unit has to wait an extra cycle to line up with its read cycles
+        self.stall_cycles += OAM_DMA_CYCLES + (self.cycle & 1) as u16;
+    }
+}
+
+impl BusDevice for Bus {
+    fn cpu_clock(&mut self, cycle: u64) {
+        self.cycle = cycle;
+        if let Some(mapper) = self.mapper.as_mut() {
+            mapper.cpu_clock();
         }
     }
 
-    pub fn read(&self, address: u16) -> u8 {
+    fn read(&mut self, address: u16) -> u8 {
+        let data = match address {
+            0x2000..=0x3FFF => self.ppu.cpu_read(address, &mut self.mapper),
+            0x4015 => self.apu.read_status(),
+            0x4016 | 0x4017 => {
+                (self.open_bus & CONTROLLER_OPEN_BUS_MASK) | self.controllers[address as usize - 0x4016].read()
+            },
+            _ => self.peek(address),
+        };
+        self.open_bus = data;
+        data
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.open_bus = data;
         match address {
-            (0x0000..=0xFFFF) => self.ram[address as usize],
-            _ => 0x0,
+            0x0000..=0x1FFF => self.ram[address as usize & (RAM_SIZE - 1)] = data,
+            0x2000..=0x3FFF => self.ppu.cpu_write(address, data, &mut self.mapper),
+            0x4014 => {
+                self.io_registers[0x14] = data;
+                self.oam_dma(data);
+            },
+            0x4000..=0x401F => {
+                self.io_registers[address as usize - 0x4000] = data;
+                self.apu.write(address, data);
+
+                // Both controllers share the strobe signal
+                if address == 0x4016 {
+                    for controller in self.controllers.iter_mut() {
+                        controller.write(data);
+                    }
+                }
+            },
+            0x4020..=0xFFFF => {
+                if let Some(mapper) = self.mapper.as_mut() {
+                    mapper.cpu_write(address, data);
+                }
+            },
         }
     }
 
-    pub fn write(&mut self, address: u16, data: u8) {
+    fn peek(&self, address: u16) -> u8 {
         match address {
-            (0x0000..=0xFFFF) => self.ram[address as usize] = data,
+            0x0000..=0x1FFF => self.ram[address as usize & (RAM_SIZE - 1)],
+            0x2000..=0x3FFF => self.ppu.peek_register(address),
+            0x4015 => self.apu.peek_status(),
+            0x4016 | 0x4017 => {
+                (self.open_bus & CONTROLLER_OPEN_BUS_MASK) | self.controllers[address as usize - 0x4016].peek()
+            },
+            0x4000..=0x401F => self.io_registers[address as usize - 0x4000],
+            0x4020..=0xFFFF => self.mapper.as_ref().and_then(|mapper| mapper.cpu_read(address)).unwrap_or(0x0),
         }
     }
-}
\ No newline at end of file
+
+    fn irq(&self) -> bool {
+        self.apu.irq() || self.mapper.as_ref().is_some_and(|mapper| mapper.irq())
+    }
+
+    fn nmi(&mut self) -> bool {
+        self.ppu.poll_nmi()
+    }
+
+    fn ppu_position(&self) -> (u16, u16) {
+        (self.ppu.scanline(), self.ppu.dot())
+    }
+}
+
+impl SaveState for Bus {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.ram);
+        self.ppu.save_state(writer);
+        self.apu.save_state(writer);
+        for controller in &self.controllers {
+            controller.save_state(writer);
+        }
+        writer.write_bytes(&self.io_registers);
+        writer.write_u16(self.stall_cycles);
+        writer.write_u8(self.open_bus);
+
+        writer.write_bool(self.mapper.is_some());
+        if let Some(mapper) = &self.mapper {
+            mapper.save_state(writer);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        reader.read_bytes(&mut self.ram)?;
+        self.ppu.load_state(reader)?;
+        self.apu.load_state(reader)?;
+        for controller in &mut self.controllers {
+            controller.load_state(reader)?;
+        }
+        reader.read_bytes(&mut self.io_registers)?;
+        self.stall_cycles = reader.read_u16()?;
+        self.open_bus = reader.read_u8()?;
+
+        match (reader.read_bool()?, &mut self.mapper) {
+            (true, Some(mapper)) => mapper.load_state(reader),
+            (false, None) => Ok(()),
+            _ => Err(SaveStateError::WrongCartridge),
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
+/// Every iNES file starts with these four bytes: "NES" followed by an MS-DOS end-of-file.
+pub const INES_MAGIC: [u8; 4] = [b'N', b'E', b'S', 0x1A];
+/// Size of the header preceding the actual contents of the file.
+pub const HEADER_SIZE: usize = 16;
+/// Size of the optional trainer, which is located between the header and PRG-ROM.
+pub const TRAINER_SIZE: usize = 512;
+/// PRG-ROM sizes in the header are specified in units of 16 KiB.
+pub const PRG_ROM_UNIT: usize = 16 * 1024;
+/// CHR-ROM sizes in the header are specified in units of 8 KiB.
+pub const CHR_ROM_UNIT: usize = 8 * 1024;
+/// iNES 1.0 specifies PRG-RAM sizes in units of 8 KiB.
+pub const PRG_RAM_UNIT: usize = 8 * 1024;
+
+/// The layout of the nametables as wired up by the cartridge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirroring {
+    /// Nametables are mirrored horizontally, used by vertically scrolling games.
+    Horizontal,
+    /// Nametables are mirrored vertically, used by horizontally scrolling games.
+    Vertical,
+    /// The cartridge provides its own VRAM for four individual nametables.
+    FourScreen,
+    /// All nametables point to the first page of VRAM.
+    SingleScreenLower,
+    /// All nametables point to the second page of VRAM.
+    SingleScreenUpper,
+}
+
+/// The TV system (and thus CPU/PPU timing) the cartridge was designed for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Ntsc,
+    Pal,
+    /// The game runs on both NTSC and PAL machines.
+    Multi,
+    Dendy,
+}
+
+/// Revision of the header format the file was stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderFormat {
+    INes,
+    Nes2,
+}
+
+/// Errors that can occur while parsing a cartridge.
+#[derive(Debug)]
+pub enum CartridgeError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file does not start with the "NES<EOF>" magic bytes.
+    InvalidMagic,
+    /// The file is shorter than what the header claims it should contain.
+    Truncated { expected: usize, found: usize },
+    /// The header describes a cartridge that cannot exist.
+    Malformed(&'static str),
+    /// The cartridge uses a mapper that hasn't been implemented.
+    UnsupportedMapper(u16),
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CartridgeError::Io(err) => write!(f, "could not read cartridge: {}", err),
+            CartridgeError::InvalidMagic => write!(f, "not an iNES file (invalid magic bytes)"),
+            CartridgeError::Truncated { expected, found } => {
+                write!(f, "cartridge is truncated: expected {} bytes, found {}", expected, found)
+            },
+            CartridgeError::Malformed(reason) => write!(f, "malformed cartridge header: {}", reason),
+            CartridgeError::UnsupportedMapper(mapper) => write!(f, "mapper {} is not supported", mapper),
+        }
+    }
+}
+
+impl Error for CartridgeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CartridgeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CartridgeError {
+    fn from(err: io::Error) -> Self {
+        CartridgeError::Io(err)
+    }
+}
+
+/// The parsed contents of an iNES or NES 2.0 header.
+/// Ref: https://www.nesdev.org/wiki/NES_2.0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub format: HeaderFormat,
+    /// Size of the PRG-ROM in bytes
+    pub prg_rom_size: usize,
+    /// Size of the CHR-ROM in bytes, 0 means the board uses CHR-RAM instead
+    pub chr_rom_size: usize,
+    /// The iNES mapper number
+    pub mapper: u16,
+    /// The NES 2.0 submapper number, always 0 for iNES files
+    pub submapper: u8,
+    pub mirroring: Mirroring,
+    /// The cartridge contains battery-backed (or otherwise non-volatile) memory
+    pub battery: bool,
+    /// A 512 byte trainer is present between the header and the PRG-ROM
+    pub trainer: bool,
+    pub region: Region,
+    /// Size of the volatile PRG-RAM in bytes
+    pub prg_ram_size: usize,
+    /// Size of the non-volatile PRG-RAM in bytes
+    pub prg_nvram_size: usize,
+    /// Size of the volatile CHR-RAM in bytes
+    pub chr_ram_size: usize,
+    /// Size of the non-volatile CHR-RAM in bytes
+    pub chr_nvram_size: usize,
+}
+
+impl Header {
+    /// Parse the first 16 bytes of an iNES or NES 2.0 file.
+    pub fn parse(bytes: &[u8]) -> Result<Self, CartridgeError> {
+        if !bytes.starts_with(&INES_MAGIC) && bytes.len() >= INES_MAGIC.len() {
+            return Err(CartridgeError::InvalidMagic);
+        }
+        if bytes.len() < HEADER_SIZE {
+            return Err(CartridgeError::Truncated { expected: HEADER_SIZE, found: bytes.len() });
+        }
+
+        let flags6 = bytes[6];
+        let flags7 = bytes[7];
+
+        let mirroring = if flags6 & 0b1000 != 0 {
+            Mirroring::FourScreen
+        } else if flags6 & 0b0001 != 0 {
+            Mirroring::Vertical
+        } else {
+            Mirroring::Horizontal
+        };
+        let battery = flags6 & 0b0010 != 0;
+        let trainer = flags6 & 0b0100 != 0;
+
+        if flags7 & 0b0000_1100 == 0b0000_1000 {
+            Self::parse_nes2(bytes, mirroring, battery, trainer)
+        } else {
+            Ok(Self::parse_ines(bytes, mirroring, battery, trainer))
+        }
+    }
+
+    fn parse_ines(bytes: &[u8], mirroring: Mirroring, battery: bool, trainer: bool) -> Self {
+        // Old dumping tools liked to write their signature (e.g. "DiskDude!") in the padding
+        // bytes at the end of the header. If that padding isn't empty, the upper nibble of the
+        // mapper number can't be trusted either.
+        let dirty = bytes[12..16].iter().any(|&b| b != 0);
+        let mapper_hi = if dirty { 0 } else { bytes[7] & 0xF0 };
+        let mapper = (mapper_hi | (bytes[6] >> 4)) as u16;
+
+        let chr_rom_size = bytes[5] as usize * CHR_ROM_UNIT;
+
+        Header {
+            format: HeaderFormat::INes,
+            prg_rom_size: bytes[4] as usize * PRG_ROM_UNIT,
+            chr_rom_size,
+            mapper,
+            submapper: 0,
+            mirroring,
+            battery,
+            trainer,
+            region: if !dirty && bytes[9] & 0x01 != 0 { Region::Pal } else { Region::Ntsc },
+            // A value of 0 here infers 8 KiB for compatibility
+            prg_ram_size: if battery { 0 } else { (bytes[8].max(1)) as usize * PRG_RAM_UNIT },
+            prg_nvram_size: if battery { (bytes[8].max(1)) as usize * PRG_RAM_UNIT } else { 0 },
+            // Boards without CHR-ROM always come with 8 KiB of CHR-RAM
+            chr_ram_size: if chr_rom_size == 0 { CHR_ROM_UNIT } else { 0 },
+            chr_nvram_size: 0,
+        }
+    }
+
+    fn parse_nes2(bytes: &[u8], mirroring: Mirroring, battery: bool, trainer: bool) -> Result<Self, CartridgeError> {
+        let mapper = (bytes[6] >> 4) as u16 | (bytes[7] & 0xF0) as u16 | ((bytes[8] & 0x0F) as u16) << 8;
+        let submapper = bytes[8] >> 4;
+
+        let prg_rom_size = nes2_rom_size(bytes[4], bytes[9] & 0x0F, PRG_ROM_UNIT)?;
+        let chr_rom_size = nes2_rom_size(bytes[5], bytes[9] >> 4, CHR_ROM_UNIT)?;
+
+        let region = match bytes[12] & 0b11 {
+            0 => Region::Ntsc,
+            1 => Region::Pal,
+            2 => Region::Multi,
+            _ => Region::Dendy,
+        };
+
+        Ok(Header {
+            format: HeaderFormat::Nes2,
+            prg_rom_size,
+            chr_rom_size,
+            mapper,
+            submapper,
+            mirroring,
+            battery,
+            trainer,
+            region,
+            prg_ram_size: nes2_ram_size(bytes[10] & 0x0F),
+            prg_nvram_size: nes2_ram_size(bytes[10] >> 4),
+            chr_ram_size: nes2_ram_size(bytes[11] & 0x0F),
+            chr_nvram_size: nes2_ram_size(bytes[11] >> 4),
+        })
+    }
+}
+
+/// NES 2.0 ROM sizes are either a 12-bit multiple of `unit`, or, when the upper nibble
+/// is 0xF, an exponent-multiplier pair stored in the lower byte.
+fn nes2_rom_size(lsb: u8, msb: u8, unit: usize) -> Result<usize, CartridgeError> {
+    if msb == 0x0F {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0b11) as usize * 2 + 1;
+        1usize.checked_shl(exponent)
+            .and_then(|size| size.checked_mul(multiplier))
+            .ok_or(CartridgeError::Malformed("ROM size exponent is too large"))
+    } else {
+        Ok((((msb as usize) << 8) | lsb as usize) * unit)
+    }
+}
+
+/// NES 2.0 RAM sizes are stored as a shift count, where 0 means no RAM at all.
+fn nes2_ram_size(shift: u8) -> usize {
+    if shift == 0 { 0 } else { 64 << shift }
+}
+
+/// A cartridge (or rather, the dump of one) that can be inserted into the system.
+#[derive(Debug, Clone)]
+pub struct Cartridge {
+    pub header: Header,
+    /// Contents of the optional trainer, mapped at $7000 by some mappers
+    pub trainer: Option<Vec<u8>>,
+    /// The program memory, mapped into the CPU's address space
+    pub prg_rom: Vec<u8>,
+    /// The character memory, mapped into the PPU's address space. Empty if the board uses CHR-RAM.
+    pub chr_rom: Vec<u8>,
+    /// Work RAM on the cartridge, mapped at $6000-$7FFF
+    pub prg_ram: Vec<u8>,
+    /// Character RAM for boards that don't come with CHR-ROM
+    pub chr_ram: Vec<u8>,
+}
+
+impl Cartridge {
+    /// Load a cartridge from an iNES or NES 2.0 file on disk.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CartridgeError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Parse a cartridge from the full contents of an iNES or NES 2.0 file.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CartridgeError> {
+        let header = Header::parse(bytes)?;
+
+        if header.prg_rom_size == 0 {
+            return Err(CartridgeError::Malformed("cartridge contains no PRG-ROM"));
+        }
+
+        let trainer_size = if header.trainer { TRAINER_SIZE } else { 0 };
+        let prg_start = HEADER_SIZE + trainer_size;
+        // NES 2.0 exponent sizes can add up to more than fits in memory
+        let (chr_start, expected) = prg_start.checked_add(header.prg_rom_size)
+            .and_then(|chr_start| Some((chr_start, chr_start.checked_add(header.chr_rom_size)?)))
+            .ok_or(CartridgeError::Malformed("ROM sizes are too large"))?;
+        if bytes.len() < expected {
+            return Err(CartridgeError::Truncated { expected, found: bytes.len() });
+        }
+
+        Ok(Cartridge {
+            trainer: if header.trainer { Some(bytes[HEADER_SIZE..prg_start].to_vec()) } else { None },
+            prg_rom: bytes[prg_start..chr_start].to_vec(),
+            chr_rom: bytes[chr_start..expected].to_vec(),
+            prg_ram: vec![0x0; header.prg_ram_size + header.prg_nvram_size],
+            chr_ram: vec![0x0; header.chr_ram_size + header.chr_nvram_size],
+            header,
+        })
+    }
+
+    /// Read from PRG-ROM as seen through a bank of `bank_size` bytes. Bank numbers that exceed
+    /// the size of the ROM wrap around, like they would on an actual board.
+    pub fn read_prg_rom(&self, bank: usize, bank_size: usize, address: u16) -> u8 {
+        self.prg_rom[bank_offset(bank, bank_size, address, self.prg_rom.len())]
+    }
+
+    /// Read from CHR-ROM or CHR-RAM as seen through a bank of `bank_size` bytes.
+    pub fn read_chr(&self, bank: usize, bank_size: usize, address: u16) -> u8 {
+        let chr = if self.chr_rom.is_empty() { &self.chr_ram } else { &self.chr_rom };
+        if chr.is_empty() {
+            return 0x0;
+        }
+        chr[bank_offset(bank, bank_size, address, chr.len())]
+    }
+
+    /// Write to CHR-RAM as seen through a bank of `bank_size` bytes. Writes to CHR-ROM have no effect.
+    pub fn write_chr(&mut self, bank: usize, bank_size: usize, address: u16, data: u8) {
+        if self.chr_rom.is_empty() && !self.chr_ram.is_empty() {
+            let offset = bank_offset(bank, bank_size, address, self.chr_ram.len());
+            self.chr_ram[offset] = data;
+        }
+    }
+
+    /// Read from the PRG-RAM at $6000-$7FFF. Returns `None` if the cartridge has no PRG-RAM.
+    pub fn read_prg_ram(&self, address: u16) -> Option<u8> {
+        if self.prg_ram.is_empty() {
+            None
+        } else {
+            Some(self.prg_ram[(address as usize & 0x1FFF) % self.prg_ram.len()])
+        }
+    }
+
+    /// Write to the PRG-RAM at $6000-$7FFF, if the cartridge has any.
+    pub fn write_prg_ram(&mut self, address: u16, data: u8) {
+        if !self.prg_ram.is_empty() {
+            let len = self.prg_ram.len();
+            self.prg_ram[(address as usize & 0x1FFF) % len] = data;
+        }
+    }
+}
+
+/// Translate an address within a bank of `bank_size` bytes to an offset into memory of `len` bytes.
+fn bank_offset(bank: usize, bank_size: usize, address: u16, len: usize) -> usize {
+    let banks = (len / bank_size).max(1);
+    ((bank % banks) * bank_size + (address as usize & (bank_size - 1))) % len
+}
+
+/// Only the cartridge's RAM is saved, the ROM is identified by the save state's header.
+impl SaveState for Cartridge {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_vec(&self.prg_ram);
+        writer.write_vec(&self.chr_ram);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        reader.read_vec_into(&mut self.prg_ram)?;
+        reader.read_vec_into(&mut self.chr_ram)
+    }
+}
@@ -0,0 +1,79 @@
+use bitflags::bitflags;
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
+bitflags! {
+    /// The buttons of a standard controller, in the order they are reported.
+    pub struct ButtonState: u8 {
+        const A = 1;
+        const B = 1 << 1;
+        const SELECT = 1 << 2;
+        const START = 1 << 3;
+        const UP = 1 << 4;
+        const DOWN = 1 << 5;
+        const LEFT = 1 << 6;
+        const RIGHT = 1 << 7;
+    }
+}
+
+impl Default for ButtonState {
+    fn default() -> Self {
+        ButtonState::empty()
+    }
+}
+
+/// The standard NES controller. While the strobe is high its shift register keeps getting reloaded
+/// with the state of the buttons, afterwards every read shifts out the next button.
+/// Ref: https://www.nesdev.org/wiki/Standard_controller
+#[derive(Debug, Default, Clone)]
+pub struct Controller {
+    /// The buttons currently held down
+    pub buttons: ButtonState,
+    shift: u8,
+    strobe: bool,
+}
+
+impl Controller {
+    /// Set the strobe, from bit 0 of a write to $4016.
+    pub fn write(&mut self, data: u8) {
+        // The register is reloaded continuously while the strobe is high, so it holds the buttons
+        // at the moment the strobe goes low.
+        if self.strobe || data & 1 != 0 {
+            self.shift = self.buttons.bits();
+        }
+        self.strobe = data & 1 != 0;
+    }
+
+    /// Read the next button, returned in bit 0.
+    pub fn read(&mut self) -> u8 {
+        let data = self.peek();
+        if !self.strobe {
+            // Official controllers shift in 1s once all buttons have been read
+            self.shift = (self.shift >> 1) | 0x80;
+        }
+        data
+    }
+
+    /// Read the next button without shifting the register.
+    pub fn peek(&self) -> u8 {
+        if self.strobe {
+            self.buttons.bits() & 1
+        } else {
+            self.shift & 1
+        }
+    }
+}
+
+impl SaveState for Controller {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.buttons.bits());
+        writer.write_u8(self.shift);
+        writer.write_bool(self.strobe);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.buttons = ButtonState::from_bits_truncate(reader.read_u8()?);
+        self.shift = reader.read_u8()?;
+        self.strobe = reader.read_bool()?;
+        Ok(())
+    }
+}
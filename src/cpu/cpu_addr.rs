@@ -1,21 +1,106 @@
-use super::CPU;
+use crate::bus::BusDevice;
+use super::{CPU, CpuVariant, cpu_instr, instructions::{Access, AddressingMode}};
+
+/// Run the current cycle of the instruction, `cpu.step`, for the given addressing mode. Like the
+/// real CPU, every cycle performs exactly one bus access, including the dummy reads and writes.
+/// Returns true once the instruction has finished.
+/// Ref: https://www.nesdev.org/6502_cpu.txt
+pub fn exec<B: BusDevice>(cpu: &mut CPU<B>, mode: AddressingMode) -> bool {
+    match mode {
+        AddressingMode::IMP | AddressingMode::ACC => imp(cpu),
+        AddressingMode::IMM => imm(cpu),
+        AddressingMode::ZP0 => zp0(cpu),
+        AddressingMode::ZPX => zpx(cpu),
+        AddressingMode::ZPY => zpy(cpu),
+        AddressingMode::REL => rel(cpu),
+        AddressingMode::ABS => abs(cpu),
+        AddressingMode::ABX => abx(cpu),
+        AddressingMode::ABY => aby(cpu),
+        AddressingMode::IND => ind(cpu),
+        AddressingMode::IZX => izx(cpu),
+        AddressingMode::IZY => izy(cpu),
+        AddressingMode::IZP => izp(cpu),
+        AddressingMode::IAX => iax(cpu),
+        AddressingMode::ZPR => zpr(cpu),
+    }
+}
+
+/// Access the effective address in `addr_abs`, `cycle` cycles after it has been calculated, and
+/// perform the operation once the operand is known. Returns true once the instruction has finished.
+fn access<B: BusDevice>(cpu: &mut CPU<B>, cycle: u8) -> bool {
+    let mnemonic = cpu.instruction().mnemonic;
+
+    match (mnemonic.access(), cycle) {
+        // The operation does the write itself
+        (Access::Write, _) => {
+            cpu_instr::exec(cpu, mnemonic);
+            true
+        },
+        (Access::ReadModifyWrite, 0) => {
+            cpu.fetched = cpu.read(cpu.addr_abs);
+            false
+        },
+        // The unmodified value is written back while the operation is performed on it
+        (Access::ReadModifyWrite, 1) => {
+            cpu.write(cpu.addr_abs, cpu.fetched);
+            false
+        },
+        (Access::ReadModifyWrite, _) => {
+            cpu_instr::exec(cpu, mnemonic);
+            true
+        },
+        _ => {
+            cpu.fetched = cpu.read(cpu.addr_abs);
+            cpu_instr::exec(cpu, mnemonic);
+            true
+        },
+    }
+}
+
+/// Add `index` to the base address in `addr_abs`. The CPU only adds it to the low byte at first,
+/// and fixes the high byte on the next cycle if a page was crossed. That first, possibly wrong,
+/// address is kept in `addr_ptr`.
+fn index<B: BusDevice>(cpu: &mut CPU<B>, index: u8) {
+    let address = cpu.addr_abs.wrapping_add(index as u16);
+    cpu.addr_ptr = (cpu.addr_abs & 0xFF00) | (address & 0x00FF);
+    cpu.addr_abs = address;
+}
+
+/// Access an indexed address, `cycle` cycles after the index was added. The first cycle reads
+/// from the address before its high byte was fixed. Read instructions that didn't cross a page are
+/// happy with that, all others access the correct address on the next cycle.
+fn indexed_access<B: BusDevice>(cpu: &mut CPU<B>, cycle: u8) -> bool {
+    let read = cpu.instruction().mnemonic.access() == Access::Read;
+
+    match cycle {
+        0 if read && cpu.addr_ptr == cpu.addr_abs => access(cpu, 0),
+        0 => {
+            cpu.read(cpu.addr_ptr);
+            false
+        },
+        _ => access(cpu, cycle - 1),
+    }
+}
 
 /// Implied addressiong. No data is fetched with this addressing mode as it
 /// is part of the actual instruction instead. Some implied instruction act
 /// upon the accumulator value though, so we set `fetched` to that value.
+/// The CPU still reads the byte after the opcode, and throws it away.
 #[inline]
-pub fn imp(cpu: &mut CPU) -> u8 {
+pub fn imp<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    cpu.read(cpu.pc);
     cpu.fetched = cpu.a;
-    0
+    cpu_instr::exec(cpu, cpu.instruction().mnemonic);
+    true
 }
 
 /// Immediate mode addressing. This means the data is supplied as part of the
 /// instruction (in other words, the next byte).
 #[inline]
-pub fn imm(cpu: &mut CPU) -> u8 {
+pub fn imm<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
     cpu.addr_abs = cpu.pc;
     cpu.pc = cpu.pc.wrapping_add(1);
-    0
+    access(cpu, 0)
 }
 
 /// Zero page addressing. This means we're reading from page zero (address with high
@@ -23,158 +108,325 @@ pub fn imm(cpu: &mut CPU) -> u8 {
 /// page zero. Thus we can interact with working memory with instructions that require
 /// less bytes (in other words, shorter instructions).
 #[inline]
-pub fn zp0(cpu: &mut CPU) -> u8 {
-    cpu.addr_abs = cpu.read(cpu.pc).into();
-    cpu.addr_abs = cpu.addr_abs & 0x00FF;
-    cpu.pc = cpu.pc.wrapping_add(1);
-    0
+pub fn zp0<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    match cpu.step {
+        2 => {
+            cpu.addr_abs = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        cycle => access(cpu, cycle - 3),
+    }
 }
 
 /// Zero page addressing with the offset of the X register added to it. Useful for iterating
 /// through regions of working memory.
 #[inline]
-pub fn zpx(cpu: &mut CPU) -> u8 {
-    cpu.addr_abs = (cpu.read(cpu.pc) + cpu.x).into();
-    cpu.addr_abs = cpu.addr_abs & 0x00FF;
-    cpu.pc = cpu.pc.wrapping_add(1);
-    0
+pub fn zpx<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    let x = cpu.x;
+    zero_page_indexed(cpu, x)
 }
 
 /// Zero page addressing with the offset of the Y register added to it. Useful for iterating
 /// through regions of working memory.
 #[inline]
-pub fn zpy(cpu: &mut CPU) -> u8 {
-    cpu.addr_abs = (cpu.read(cpu.pc) + cpu.y).into();
-    cpu.addr_abs = cpu.addr_abs & 0x00FF;
-    cpu.pc = cpu.pc.wrapping_add(1);
-    0
+pub fn zpy<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    let y = cpu.y;
+    zero_page_indexed(cpu, y)
+}
+
+fn zero_page_indexed<B: BusDevice>(cpu: &mut CPU<B>, index: u8) -> bool {
+    match cpu.step {
+        2 => {
+            cpu.addr_abs = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        // The unindexed address is read while the index is added, which never leaves page zero
+        3 => {
+            cpu.read(cpu.addr_abs);
+            cpu.addr_abs = (cpu.addr_abs as u8).wrapping_add(index).into();
+            false
+        },
+        cycle => access(cpu, cycle - 4),
+    }
 }
 
-/// Relative addressing. Only used in branching instructions.
+/// Relative addressing. Only used in branching instructions. Taking the branch costs an extra
+/// cycle, and crossing a page while doing so another one.
 #[inline]
-pub fn rel(cpu: &mut CPU) -> u8 {
-    cpu.addr_rel = cpu.read(cpu.pc).into();
-    cpu.pc = cpu.pc.wrapping_add(1);
+pub fn rel<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    let cycle = cpu.step - 2;
+    branch(cpu, cycle)
+}
 
-    // In order to use this address to jump back, it needs to be signed. Therefore,
-    // we check if the first bit is set to 1.
-    if cpu.addr_rel & 0b1000_0000 != 0 {
-        // Set the high byte of the relative address to all 1s.
-        cpu.addr_rel |= 0xFF00;
-    }
+/// Fetch the branch offset and take the branch, `cycle` cycles after the offset fetch.
+fn branch<B: BusDevice>(cpu: &mut CPU<B>, cycle: u8) -> bool {
+    match cycle {
+        0 => {
+            cpu.addr_rel = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+
+            // In order to use this address to jump back, it needs to be signed. Therefore,
+            // we check if the first bit is set to 1.
+            if cpu.addr_rel & 0b1000_0000 != 0 {
+                // Set the high byte of the relative address to all 1s.
+                cpu.addr_rel |= 0xFF00;
+            }
 
-    0
+            // The branch puts its target in `addr_abs` if it's taken
+            cpu_instr::exec(cpu, cpu.instruction().mnemonic) == 0
+        },
+        // Only the low byte of the program counter is updated at first. If that's all it takes,
+        // an IRQ or NMI that came in during the operand fetch isn't polled until the next
+        // instruction.
+        1 => {
+            if cpu.irq_pending && !cpu.irq_ready {
+                cpu.irq_pending = false;
+            }
+            if cpu.nmi_pending && !cpu.nmi_ready {
+                cpu.nmi_poll_skipped = true;
+            }
+
+            cpu.read(cpu.pc);
+            cpu.pc = (cpu.pc & 0xFF00) | (cpu.addr_abs & 0x00FF);
+            cpu.pc == cpu.addr_abs
+        },
+        _ => {
+            cpu.read(cpu.pc);
+            cpu.pc = cpu.addr_abs;
+            true
+        },
+    }
 }
 
 /// Absolute addressing. The entire address we need is located in the next two bytes from the
 /// instruction.
 #[inline]
-pub fn abs(cpu: &mut CPU) -> u8 {
-    let lo = cpu.read(cpu.pc);
-    let hi = cpu.read(cpu.pc.wrapping_add(1));
-    
-    cpu.pc = cpu.pc.wrapping_add(2);
-    cpu.addr_abs = u16::from_le_bytes([lo, hi]);
+pub fn abs<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    match cpu.step {
+        2 => {
+            cpu.addr_abs = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        3 => {
+            cpu.addr_abs |= (cpu.read(cpu.pc) as u16) << 8;
+            cpu.pc = cpu.pc.wrapping_add(1);
+
+            // Jumps are done as soon as they know where to
+            let mnemonic = cpu.instruction().mnemonic;
+            if mnemonic.access() == Access::Jump {
+                cpu_instr::exec(cpu, mnemonic);
+                return true;
+            }
 
-    0
+            false
+        },
+        cycle => access(cpu, cycle - 4),
+    }
 }
 
 /// Absolute addressing with the offset in the X register added to it. An extra cycle must be
 /// elapsed if during the adding of the X register, a page is crossed.
 #[inline]
-pub fn abx(cpu: &mut CPU) -> u8 {
-    let lo = cpu.read(cpu.pc);
-    let hi = cpu.read(cpu.pc.wrapping_add(1));
-    
-    cpu.pc = cpu.pc.wrapping_add(2);
-    cpu.addr_abs = u16::from_le_bytes([lo, hi]);
-    cpu.addr_abs = cpu.addr_abs + cpu.x as u16;
-
-    // If by incrementing the absolute address with the X register the whole
-    // address has changed to a different page, we need to count an extra cycle.
-    // We can do this by checking if the high byte has changed.
-    if (cpu.addr_abs & 0xFF00) != (hi as u16) << 8 {
-        1
-    } else {
-        0
-    }
+pub fn abx<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    let x = cpu.x;
+    absolute_indexed(cpu, x)
 }
 
 /// Absolute addressing with the offset in the Y register added to it. An extra cycle must be
 /// elapsed if during the adding of the Y register, a page is crossed.
 #[inline]
-pub fn aby(cpu: &mut CPU) -> u8 {
-    let lo = cpu.read(cpu.pc);
-    let hi = cpu.read(cpu.pc.wrapping_add(1));
-    
-    cpu.pc = cpu.pc.wrapping_add(2);
-    cpu.addr_abs = u16::from_le_bytes([lo, hi]);
-    cpu.addr_abs = cpu.addr_abs + cpu.y as u16;
-
-    // If by incrementing the absolute address with the Y register the whole
-    // address has changed to a different page, we need to count an extra cycle.
-    // We can do this by checking if the high byte has changed.
-    if (cpu.addr_abs & 0xFF00) != (hi as u16) << 8 {
-        1
-    } else {
-        0
+pub fn aby<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    let y = cpu.y;
+    absolute_indexed(cpu, y)
+}
+
+fn absolute_indexed<B: BusDevice>(cpu: &mut CPU<B>, offset: u8) -> bool {
+    match cpu.step {
+        2 => {
+            cpu.addr_abs = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        3 => {
+            cpu.addr_abs |= (cpu.read(cpu.pc) as u16) << 8;
+            cpu.pc = cpu.pc.wrapping_add(1);
+            index(cpu, offset);
+            false
+        },
+        cycle => indexed_access(cpu, cycle - 4),
     }
 }
 
 /// Indirect addressing. This is an assembly-level technique to implement pointer-like addressing, as
 /// this reads from the address defined by the the value read through absolute addressing.
 #[inline]
-pub fn ind(cpu: &mut CPU) -> u8 {
-    // First construct the "pointer"
-    let ptr_lo = cpu.read(cpu.pc);
-    let ptr_hi = cpu.read(cpu.pc.wrapping_add(1));
-    let ptr = u16::from_le_bytes([ptr_lo, ptr_hi]);
-    cpu.pc = cpu.pc.wrapping_add(2);
-
-    // This simulates a hardware bug. If the lo byte is 0x00FF (aka, a page cross will occur), the 6502 
-    // will not read from the correct memory location by reading from `ptr` twice, instead of 
-    // `ptr + 1` and `ptr`. 
-    if ptr_lo == 0x00FF {
-        cpu.addr_abs = u16::from_le_bytes([cpu.read(ptr & 0xFF00), cpu.read(ptr + 1)]);
-    } else {
-        cpu.addr_abs = u16::from_le_bytes([cpu.read(ptr), cpu.read(ptr + 1)]);
-    }
+pub fn ind<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    // The 65C02 fixed the page wrapping bug below, which costs it an extra cycle
+    let fixed = cpu.variant == CpuVariant::Wdc65C02;
 
-    0
+    match (cpu.step, fixed) {
+        // First construct the "pointer"
+        (2, _) => {
+            cpu.addr_ptr = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        (3, _) => {
+            cpu.addr_ptr |= (cpu.read(cpu.pc) as u16) << 8;
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        (4, true) => {
+            cpu.read(cpu.pc.wrapping_sub(1));
+            false
+        },
+        (4, false) | (5, true) => {
+            cpu.addr_abs = cpu.read(cpu.addr_ptr).into();
+            false
+        },
+        // This simulates a hardware bug. If the lo byte is 0x00FF (aka, a page cross will occur), the 6502
+        // does not carry into the high byte of the pointer, so the high byte of the target is read from
+        // the start of the same page instead of from `ptr + 1`.
+        _ => {
+            let hi_ptr = if fixed {
+                cpu.addr_ptr.wrapping_add(1)
+            } else {
+                (cpu.addr_ptr & 0xFF00) | (cpu.addr_ptr.wrapping_add(1) & 0x00FF)
+            };
+            cpu.addr_abs |= (cpu.read(hi_ptr) as u16) << 8;
+            cpu_instr::exec(cpu, cpu.instruction().mnemonic);
+            true
+        },
+    }
 }
 
 /// Indirect addressing of the zero page with X offset.
 #[inline]
-pub fn izx(cpu: &mut CPU) -> u8 {
-    let t: u16 = cpu.read(cpu.pc).into();
-    let lo = cpu.read((t + cpu.x as u16) as u16 & 0x00FF);
-    let hi = cpu.read((t + cpu.x as u16 + 1) as u16 & 0x00FF);
-    
-    cpu.pc = cpu.pc.wrapping_add(1);
-    cpu.addr_abs = u16::from_be_bytes([lo, hi]);
-    
-    0
+pub fn izx<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    match cpu.step {
+        2 => {
+            cpu.addr_ptr = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        // The pointer is read while X is added to it
+        3 => {
+            cpu.read(cpu.addr_ptr);
+            cpu.addr_ptr = (cpu.addr_ptr as u8).wrapping_add(cpu.x).into();
+            false
+        },
+        4 => {
+            cpu.addr_abs = cpu.read(cpu.addr_ptr).into();
+            false
+        },
+        5 => {
+            let hi_ptr = (cpu.addr_ptr as u8).wrapping_add(1);
+            cpu.addr_abs |= (cpu.read(hi_ptr.into()) as u16) << 8;
+            false
+        },
+        cycle => access(cpu, cycle - 6),
+    }
 }
 
-
 /// Indirect addressing of the zero page with Y offset after reading.
 #[inline]
-pub fn izy(cpu: &mut CPU) -> u8 {
-    let t: u16 = cpu.read(cpu.pc).into();
-    let lo = cpu.read(t & 0x00FF);
-    let hi = cpu.read((t + 1)  & 0x00FF);
-    
-    cpu.pc = cpu.pc.wrapping_add(1);
-    cpu.addr_abs = u16::from_le_bytes([lo, hi]);
-    cpu.addr_abs = cpu.addr_abs + cpu.y as u16;
+pub fn izy<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    match cpu.step {
+        2 => {
+            cpu.addr_ptr = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        3 => {
+            cpu.addr_abs = cpu.read(cpu.addr_ptr).into();
+            false
+        },
+        4 => {
+            let hi_ptr = (cpu.addr_ptr as u8).wrapping_add(1);
+            cpu.addr_abs |= (cpu.read(hi_ptr.into()) as u16) << 8;
+            let y = cpu.y;
+            index(cpu, y);
+            false
+        },
+        cycle => indexed_access(cpu, cycle - 5),
+    }
+}
+
+/// Indirect addressing of the zero page, without an offset (65C02).
+#[inline]
+pub fn izp<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    match cpu.step {
+        2 => {
+            cpu.addr_ptr = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        3 => {
+            cpu.addr_abs = cpu.read(cpu.addr_ptr).into();
+            false
+        },
+        4 => {
+            let hi_ptr = (cpu.addr_ptr as u8).wrapping_add(1);
+            cpu.addr_abs |= (cpu.read(hi_ptr.into()) as u16) << 8;
+            false
+        },
+        cycle => access(cpu, cycle - 5),
+    }
+}
 
-    // If by incrementing the indirect with the Y register the whole
-    // address has changed to a different page, we need to count an extra cycle.
-    // We can do this by checking if the high byte has changed.
+/// Indirect addressing with the X register added to the pointer, only used by JMP (65C02).
+#[inline]
+pub fn iax<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    match cpu.step {
+        2 => {
+            cpu.addr_ptr = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        3 => {
+            cpu.addr_ptr |= (cpu.read(cpu.pc) as u16) << 8;
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        // The last operand byte is read again while X is added to the pointer
+        4 => {
+            cpu.read(cpu.pc.wrapping_sub(1));
+            cpu.addr_ptr = cpu.addr_ptr.wrapping_add(cpu.x as u16);
+            false
+        },
+        5 => {
+            cpu.addr_abs = cpu.read(cpu.addr_ptr).into();
+            false
+        },
+        _ => {
+            cpu.addr_abs |= (cpu.read(cpu.addr_ptr.wrapping_add(1)) as u16) << 8;
+            cpu_instr::exec(cpu, cpu.instruction().mnemonic);
+            true
+        },
+    }
+}
 
-    if (cpu.addr_abs & 0xFF00) != (hi as u16) << 8 {
-        1
-    } else {
-        0
+/// Zero page addressing followed by a relative branch, used by BBR and BBS to test a bit of the
+/// zero page value (65C02).
+#[inline]
+pub fn zpr<B: BusDevice>(cpu: &mut CPU<B>) -> bool {
+    match cpu.step {
+        2 => {
+            cpu.addr_abs = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+            false
+        },
+        3 => {
+            cpu.fetched = cpu.read(cpu.addr_abs);
+            false
+        },
+        4 => {
+            cpu.read(cpu.addr_abs);
+            false
+        },
+        cycle => branch(cpu, cycle - 5),
     }
-}
\ No newline at end of file
+}
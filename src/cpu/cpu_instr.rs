@@ -1,28 +1,62 @@
-use std::net::AddrParseError;
-
-use super::{CPU, STACK_BASE, StatusFlags, instructions::{AddressingMode, Instruction}};
+use crate::bus::BusDevice;
+use super::{CPU, CpuState, CpuVariant, IRQ_POINTER, Interrupt, NMI_POINTER, PC_POINTER, STACK_BASE, StatusFlags, instructions::{AddressingMode, Mnemonic}};
+
+/// Bits of the accumulator that leak into the result of the unstable XAA and LAX #imm. The real
+/// value differs between chips, and even with temperature, but 0xEE is the most common one.
+/// Ref: https://www.nesdev.org/wiki/Visual6502wiki/6502_Opcode_8B_(XAA,_ANE)
+const UNSTABLE_MAGIC: u8 = 0xEE;
+
+/// Execute the operation for the given mnemonic, returning 1 if it requires an additional cycle.
+/// Most operations are performed on the last cycle of the instruction, once the addressing mode
+/// has fetched the operand. Instructions that use the stack run their own sequence instead, and
+/// are called for every cycle.
+pub fn exec<B: BusDevice>(cpu: &mut CPU<B>, mnemonic: Mnemonic) -> u8 {
+    match mnemonic {
+        Mnemonic::LDA => lda(cpu), Mnemonic::LDX => ldx(cpu), Mnemonic::LDY => ldy(cpu),
+        Mnemonic::STA => sta(cpu), Mnemonic::STX => stx(cpu), Mnemonic::STY => sty(cpu),
+        Mnemonic::TAX => tax(cpu), Mnemonic::TAY => tay(cpu), Mnemonic::TSX => tsx(cpu),
+        Mnemonic::TXA => txa(cpu), Mnemonic::TXS => txs(cpu), Mnemonic::TYA => tya(cpu),
+        Mnemonic::ADC => adc(cpu), Mnemonic::DEC => dec(cpu), Mnemonic::DEX => dex(cpu),
+        Mnemonic::DEY => dey(cpu), Mnemonic::INC => inc(cpu), Mnemonic::INX => inx(cpu),
+        Mnemonic::INY => iny(cpu), Mnemonic::SBC => sbc(cpu),
+        Mnemonic::AND => and(cpu), Mnemonic::ASL => asl(cpu), Mnemonic::BIT => bit(cpu),
+        Mnemonic::EOR => eor(cpu), Mnemonic::LSR => lsr(cpu), Mnemonic::ORA => ora(cpu),
+        Mnemonic::ROL => rol(cpu), Mnemonic::ROR => ror(cpu),
+        Mnemonic::BCC => bcc(cpu), Mnemonic::BCS => bcs(cpu), Mnemonic::BEQ => beq(cpu),
+        Mnemonic::BMI => bmi(cpu), Mnemonic::BNE => bne(cpu), Mnemonic::BPL => bpl(cpu),
+        Mnemonic::BVC => bvc(cpu), Mnemonic::BVS => bvs(cpu),
+        Mnemonic::JMP => jmp(cpu), Mnemonic::JSR => jsr(cpu), Mnemonic::RTI => rti(cpu),
+        Mnemonic::RTS => rts(cpu),
+        Mnemonic::CLC => clc(cpu), Mnemonic::CLD => cld(cpu), Mnemonic::CLI => cli(cpu),
+        Mnemonic::CLV => clv(cpu), Mnemonic::CMP => cmp(cpu), Mnemonic::CPX => cpx(cpu),
+        Mnemonic::CPY => cpy(cpu), Mnemonic::SEC => sec(cpu), Mnemonic::SED => sed(cpu),
+        Mnemonic::SEI => sei(cpu),
+        Mnemonic::PHA => pha(cpu), Mnemonic::PHP => php(cpu), Mnemonic::PLA => pla(cpu),
+        Mnemonic::PLP => plp(cpu),
+        Mnemonic::BRK => brk(cpu), Mnemonic::NOP => nop(cpu),
+        Mnemonic::LAX => lax(cpu), Mnemonic::SAX => sax(cpu), Mnemonic::DCP => dcp(cpu),
+        Mnemonic::ISC => isc(cpu), Mnemonic::SLO => slo(cpu), Mnemonic::RLA => rla(cpu),
+        Mnemonic::SRE => sre(cpu), Mnemonic::RRA => rra(cpu),
+        Mnemonic::ANC => anc(cpu), Mnemonic::ALR => alr(cpu), Mnemonic::ARR => arr(cpu),
+        Mnemonic::AXS => axs(cpu),
+        Mnemonic::SHA => sha(cpu), Mnemonic::SHX => shx(cpu), Mnemonic::SHY => shy(cpu),
+        Mnemonic::TAS => tas(cpu), Mnemonic::LAS => las(cpu), Mnemonic::XAA => xaa(cpu),
+        Mnemonic::JAM => jam(cpu),
+        Mnemonic::BRA => bra(cpu), Mnemonic::PHX => phx(cpu), Mnemonic::PHY => phy(cpu),
+        Mnemonic::PLX => plx(cpu), Mnemonic::PLY => ply(cpu), Mnemonic::STZ => stz(cpu),
+        Mnemonic::TRB => trb(cpu), Mnemonic::TSB => tsb(cpu),
+        Mnemonic::RMB => rmb(cpu), Mnemonic::SMB => smb(cpu), Mnemonic::BBR => bbr(cpu),
+        Mnemonic::BBS => bbs(cpu),
+        Mnemonic::WAI => wai(cpu), Mnemonic::STP => stp(cpu),
+    }
+}
 
 /// Add with carry in. Allows us to add a value to the accumulator and a carry bit. 
 /// If the result is > 255 there is an overflow setting the carry bit. Ths allows you 
 /// to chain together ADC instructions to add numbers larger than 8-bits. 
-pub fn adc(cpu: &mut CPU) -> u8 {
-    let fetched: u16 = cpu.fetch().into();
-
-    // Add is performed in 16-bit domain for emulation to capture any carry bit, 
-    // which will exist in bit 8 of the 16-bit word
-    let result = fetched + cpu.a as u16 + cpu.status.contains(StatusFlags::C) as u16;
-
-    // We need to determine the signed overflow flag using the following fomula
-    let v = !((cpu.a as u16) ^ fetched) & ((cpu.a as u16) ^ result) & 0x0080;
-
-    // Set all the required
-    cpu.status.set(StatusFlags::C, result > 255);
-    cpu.status.set(StatusFlags::Z, result & 0x00FF == 0);
-    cpu.status.set(StatusFlags::N, result & 0b1000_0000 != 0);
-    cpu.status.set(StatusFlags::V, v != 0);
-
-    // Load the result back into the accumulator, but as a u8 of course!
-    cpu.a = (result & 0x00FF) as u8;
+pub fn adc<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    add_with_carry(cpu, fetched);
 
     1
 }
@@ -30,32 +64,16 @@ pub fn adc(cpu: &mut CPU) -> u8 {
 /// Subtraction with Borrow In. Given the explanation for ADC above, we can reorganise our data
 /// to use the same computation for addition, for subtraction by multiplying the data by -1, 
 /// i.e. make it negative.
-pub fn sbc(cpu: &mut CPU) -> u8 {
-    // Fetch the datea and invert the lo bits (this is a u8 stored in a u16, so this is all of them) 
-    let fetched: u16 = (cpu.fetch() as u16) ^ 0x00FF;
-
-    // Add is performed in 16-bit domain for emulation to capture any carry bit, 
-    // which will exist in bit 8 of the 16-bit word
-    let result = fetched + cpu.a as u16 + cpu.status.contains(StatusFlags::C) as u16;
-
-    // We need to determine the signed overflow flag using the following fomula
-    let v = !((cpu.a as u16) ^ fetched) & ((cpu.a as u16) ^ result) & 0x0080;
-
-    // Set all the required
-    cpu.status.set(StatusFlags::C, result > 255);
-    cpu.status.set(StatusFlags::Z, result & 0x00FF == 0);
-    cpu.status.set(StatusFlags::N, result & 0b1000_0000 != 0);
-    cpu.status.set(StatusFlags::V, v != 0);
-
-    // Load the result back into the accumulator, but as a u8 of course!
-    cpu.a = (result & 0x00FF) as u8;
+pub fn sbc<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    subtract_with_borrow(cpu, fetched);
 
     1
 }
 
 /// Logical AND on the value in the accumulator.
-pub fn and(cpu: &mut CPU) -> u8 {
-    cpu.a = cpu.a & cpu.fetch();
+pub fn and<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    cpu.a &= cpu.fetch();
     cpu.status.set(StatusFlags::Z, cpu.a == 0x00);
     cpu.status.set(StatusFlags::N, cpu.a & 0b1000_0000 != 0);
 
@@ -63,7 +81,7 @@ pub fn and(cpu: &mut CPU) -> u8 {
 }
 
 /// Arithmetic Shift Left.
-pub fn asl(cpu: &mut CPU) -> u8 {
+pub fn asl<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched = cpu.fetch() as u16;
     let shifted = fetched << 1;
 
@@ -73,7 +91,7 @@ pub fn asl(cpu: &mut CPU) -> u8 {
     cpu.status.set(StatusFlags::N, (shifted & 0x80) != 0);
 
     // Write the result based on the addressing mode
-    if Instruction::decode(cpu.opcode).mode == AddressingMode::IMP  {
+    if cpu.instruction().mode == AddressingMode::IMP  {
         cpu.a = (shifted & 0x00FF) as u8;
     } else {
         cpu.write(cpu.addr_abs, (shifted & 0x00FF) as u8);
@@ -83,42 +101,33 @@ pub fn asl(cpu: &mut CPU) -> u8 {
 }
 
 /// Branch if carry bit is clear.
-pub fn bcc(cpu: &mut CPU) -> u8 {
+pub fn bcc<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     // Check if the carry flag is clear
-    if !cpu.status.contains(StatusFlags::C) {
-        branch(cpu);
-    }
-
-    0
+    branch(cpu, !cpu.status.contains(StatusFlags::C))
 }
 
 /// Branch if the carry bit has been set.
-pub fn bcs(cpu: &mut CPU) -> u8 {
+pub fn bcs<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     // Check if the carry flag has been set
-    if cpu.status.contains(StatusFlags::C) {
-        branch(cpu);
-    }
-
-    0
+    branch(cpu, cpu.status.contains(StatusFlags::C))
 }
 
 /// Branch if equal.
-pub fn beq(cpu: &mut CPU) -> u8 {
+pub fn beq<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     // Check if the zero flag has been set
-    if cpu.status.contains(StatusFlags::Z) {
-        branch(cpu);
-    }
-
-    0
+    branch(cpu, cpu.status.contains(StatusFlags::Z))
 }
 
 /// Test bits in memory with sccumulator
-pub fn bit(cpu: &mut CPU) -> u8 {
+pub fn bit<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched = cpu.fetch();
 	let tested = cpu.a & fetched;
 
-    // Set the flags
-    cpu.status.set(StatusFlags::Z, (tested & 0x00FF) == 0);
+    // Set the flags, the immediate variant of the 65C02 only sets Z
+    cpu.status.set(StatusFlags::Z, tested == 0);
+    if cpu.instruction().mode == AddressingMode::IMM {
+        return 0;
+    }
     cpu.status.set(StatusFlags::N, fetched & (1 << 7) != 0);
     cpu.status.set(StatusFlags::V, fetched & (1 << 6) != 0);
 
@@ -126,108 +135,113 @@ pub fn bit(cpu: &mut CPU) -> u8 {
 }
 
 /// Branch if negative.
-pub fn bmi(cpu: &mut CPU) -> u8 {
+pub fn bmi<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     // Check if the negative flag is clear
-    if cpu.status.contains(StatusFlags::N) {
-        branch(cpu);
-    }
-
-    0
+    branch(cpu, cpu.status.contains(StatusFlags::N))
 }
 
 /// Branch if not equal.
-pub fn bne(cpu: &mut CPU) -> u8 {
+pub fn bne<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     // Check if the zero flag is clear
-    if !cpu.status.contains(StatusFlags::Z) {
-        branch(cpu);
-    }
-
-    0
+    branch(cpu, !cpu.status.contains(StatusFlags::Z))
 }
 
 /// Branch if positive.
-pub fn bpl(cpu: &mut CPU) -> u8 {
+pub fn bpl<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     // Check if the negative flag is clear
-    if !cpu.status.contains(StatusFlags::N) {
-        branch(cpu);
-    }
-
-    0
+    branch(cpu, !cpu.status.contains(StatusFlags::N))
 }
 
-/// Break.
-pub fn brk(cpu: &mut CPU) -> u8 {
-    cpu.pc = cpu.pc.wrapping_add(1);
-	
-    cpu.status.set(StatusFlags::I, true);
-    cpu.write(STACK_BASE + cpu.sp as u16, ((cpu.pc >> 8) & 0x00FF) as u8);
-    cpu.write(STACK_BASE + (cpu.sp - 1) as u16, (cpu.pc & 0x00FF) as u8);
-    cpu.sp = cpu.sp.wrapping_sub(2);
-
-    // TODO: Why do we toggle the B flag here?
-    cpu.status.set(StatusFlags::B, true);
-    cpu.write(STACK_BASE + cpu.sp as u16, cpu.status.bits);
-    cpu.sp = cpu.sp.wrapping_sub(1);
-    cpu.status.set(StatusFlags::B, false);
-
-    let lo = cpu.read(0xFFFE);
-    let hi = cpu.read(0xFFFF);
-
-    cpu.pc = u16::from_le_bytes([lo, hi]);
+/// Break. Hardware interrupts and the reset run the same sequence, but the interrupts don't skip
+/// the padding byte after the opcode or set the B flag, and the reset only reads from the stack.
+/// Called once for each cycle after the opcode fetch, returning 1 as long as it needs another cycle.
+pub fn brk<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let interrupt = cpu.interrupt;
+
+    match cpu.step {
+        2 => {
+            cpu.read(cpu.pc);
+            if interrupt.is_none() {
+                cpu.pc = cpu.pc.wrapping_add(1);
+            }
+        },
+        3 => push_unless_reset(cpu, (cpu.pc >> 8) as u8),
+        4 => push_unless_reset(cpu, cpu.pc as u8),
+        5 => {
+            // The B flag only exists on the stack, it signals that the interrupt came from a BRK
+            let b = if interrupt.is_none() { StatusFlags::B } else { StatusFlags::empty() };
+            push_unless_reset(cpu, (cpu.status | b | StatusFlags::U).bits);
+        },
+        // An NMI that comes in before the vector is read hijacks the sequence, even for a BRK or
+        // an IRQ, which then run the NMI handler instead
+        6 => {
+            if cpu.nmi_pending && interrupt != Some(Interrupt::Reset) {
+                cpu.nmi_pending = false;
+                cpu.interrupt = Some(Interrupt::Nmi);
+            }
+
+            cpu.addr_abs = cpu.read(interrupt_vector(cpu.interrupt)).into();
+            cpu.status.set(StatusFlags::I, true);
+
+            // The 65C02 leaves decimal mode for the handler
+            if cpu.variant == CpuVariant::Wdc65C02 {
+                cpu.status.set(StatusFlags::D, false);
+            }
+        },
+        _ => {
+            cpu.addr_abs |= (cpu.read(interrupt_vector(cpu.interrupt) + 1) as u16) << 8;
+            cpu.pc = cpu.addr_abs;
+            cpu.interrupt = None;
+            return 0;
+        },
+    }
 
-	0
+    1
 }
 
 /// Branch if overflow.
-pub fn bvc(cpu: &mut CPU) -> u8 {
+pub fn bvc<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     // Check if the overflow flag is clear
-    if !cpu.status.contains(StatusFlags::V) {
-        branch(cpu);
-    }
-
-    0
+    branch(cpu, !cpu.status.contains(StatusFlags::V))
 }
 
 /// Branch if not overflowed.
-pub fn bvs(cpu: &mut CPU) -> u8 {
+pub fn bvs<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     // Check if the carry flag has been set
-    if cpu.status.contains(StatusFlags::V) {
-        branch(cpu);
-    }
-
-    0
+    branch(cpu, cpu.status.contains(StatusFlags::V))
 }
 
 /// Clear the "carry" flag.
-pub fn clc(cpu: &mut CPU) -> u8 {
+pub fn clc<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.status.set(StatusFlags::C, false);
 
     0
 }
 
 /// Clear the "decimal" flag.
-pub fn cld(cpu: &mut CPU) -> u8 {
+pub fn cld<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.status.set(StatusFlags::D, false);
 
     0
 }
 
-/// Clear the "disable interrupt" flag.
-pub fn cli(cpu: &mut CPU) -> u8 {
+/// Clear the "disable interrupt" flag. Interrupts were already polled by the time it's cleared,
+/// so a pending IRQ is only serviced after the next instruction.
+pub fn cli<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.status.set(StatusFlags::I, false);
 
     0
 }
 
 /// Clear the "overflow" flag.
-pub fn clv(cpu: &mut CPU) -> u8 {
+pub fn clv<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.status.set(StatusFlags::V, false);
 
     0
 }
 
 /// Compare Accumulator.
-pub fn cmp(cpu: &mut CPU) -> u8 {
+pub fn cmp<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched = cpu.fetch();
     let compared = (cpu.a as u16).wrapping_sub(fetched as u16);
 
@@ -240,25 +254,25 @@ pub fn cmp(cpu: &mut CPU) -> u8 {
 }
 
 /// Compare X Register
-pub fn cpx(cpu: &mut CPU) -> u8 {
+pub fn cpx<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched = cpu.fetch();
     let compared = (cpu.x as u16).wrapping_sub(fetched as u16);
 
     // Set flags
-    cpu.status.set(StatusFlags::C, cpu.a >= fetched);
+    cpu.status.set(StatusFlags::C, cpu.x >= fetched);
     cpu.status.set(StatusFlags::N, (compared & 0x0080) != 0);
     cpu.status.set(StatusFlags::Z, (compared & 0x00FF) == 0);
 
     0
 }
 
-/// Compare X Register
-pub fn cpy(cpu: &mut CPU) -> u8 {
+/// Compare Y Register
+pub fn cpy<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched = cpu.fetch();
     let compared = (cpu.y as u16).wrapping_sub(fetched as u16);
 
     // Set flags
-    cpu.status.set(StatusFlags::C, cpu.a >= fetched);
+    cpu.status.set(StatusFlags::C, cpu.y >= fetched);
     cpu.status.set(StatusFlags::N, (compared & 0x0080) != 0);
     cpu.status.set(StatusFlags::Z, (compared & 0x00FF) == 0);
 
@@ -266,21 +280,27 @@ pub fn cpy(cpu: &mut CPU) -> u8 {
 }
 
 /// Decrement value at memory location.
-pub fn dec(cpu: &mut CPU) -> u8 {
+pub fn dec<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched = cpu.fetch();
     let decrement = fetched.wrapping_sub(1);
-    cpu.write(cpu.addr_abs, decrement & 0x00FF);
+
+    // The 65C02 can decrement the accumulator too
+    if cpu.instruction().mode == AddressingMode::IMP {
+        cpu.a = decrement;
+    } else {
+        cpu.write(cpu.addr_abs, decrement);
+    }
 
     // Set flags
     cpu.status.set(StatusFlags::N, (decrement & 0x0080) != 0);
-    cpu.status.set(StatusFlags::Z, (decrement & 0x00FF) == 0);
+    cpu.status.set(StatusFlags::Z, decrement == 0);
 
     0
 }
 
 /// Decrement X register.
-pub fn dex(cpu: &mut CPU) -> u8 {
-    cpu.x = cpu.x.wrapping_sub(cpu.x);
+pub fn dex<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    cpu.x = cpu.x.wrapping_sub(1);
 
     // Set flags
     cpu.status.set(StatusFlags::N, (cpu.x & 0x0080) != 0);
@@ -290,8 +310,8 @@ pub fn dex(cpu: &mut CPU) -> u8 {
 }
 
 /// Decrement Y register.
-pub fn dey(cpu: &mut CPU) -> u8 {
-    cpu.y = cpu.y.wrapping_sub(cpu.y);
+pub fn dey<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    cpu.y = cpu.y.wrapping_sub(1);
 
     // Set flags
     cpu.status.set(StatusFlags::N, (cpu.y & 0x0080) != 0);
@@ -301,9 +321,9 @@ pub fn dey(cpu: &mut CPU) -> u8 {
 }
 
 /// Bitwise logic XOR.
-pub fn eor(cpu: &mut CPU) -> u8 {
+pub fn eor<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched = cpu.fetch();
-    cpu.a = cpu.a ^ fetched;
+    cpu.a ^= fetched;
     
     // Set flags
     cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
@@ -313,20 +333,26 @@ pub fn eor(cpu: &mut CPU) -> u8 {
 }
 
 /// Increment Value at memory location.
-pub fn inc(cpu: &mut CPU) -> u8 {
+pub fn inc<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched = cpu.fetch();
-    let increment = fetched + 1;
-    cpu.write(cpu.addr_abs, increment & 0x00FF);
+    let increment = fetched.wrapping_add(1);
+
+    // The 65C02 can increment the accumulator too
+    if cpu.instruction().mode == AddressingMode::IMP {
+        cpu.a = increment;
+    } else {
+        cpu.write(cpu.addr_abs, increment);
+    }
 
     // Set flags
     cpu.status.set(StatusFlags::N, (increment & 0x0080) != 0);
-    cpu.status.set(StatusFlags::Z, (increment & 0x00FF) == 0);
+    cpu.status.set(StatusFlags::Z, increment == 0);
 
     0
 }
 
 /// Increment X Register.
-pub fn inx(cpu: &mut CPU) -> u8 {
+pub fn inx<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.x = cpu.x.wrapping_add(1);
     cpu.status.set(StatusFlags::N, (cpu.x & 0x0080) != 0);
     cpu.status.set(StatusFlags::Z, cpu.x == 0);
@@ -335,7 +361,7 @@ pub fn inx(cpu: &mut CPU) -> u8 {
 }
 
 /// Increment Y Register.
-pub fn iny(cpu: &mut CPU) -> u8 {
+pub fn iny<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.y = cpu.y.wrapping_add(1);
     cpu.status.set(StatusFlags::N, (cpu.y & 0x0080) != 0);
     cpu.status.set(StatusFlags::Z, cpu.y == 0);
@@ -344,25 +370,37 @@ pub fn iny(cpu: &mut CPU) -> u8 {
 }
 
 /// Jump to location.
-pub fn jmp(cpu: &mut CPU) -> u8 {
+pub fn jmp<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.pc = cpu.addr_abs;
     
     0
 }
 
-/// Jump to subroutine.
-pub fn jsr(cpu: &mut CPU) -> u8 {
-    cpu.pc = cpu.pc.wrapping_sub(1);
-    cpu.write(STACK_BASE + cpu.sp as u16, ((cpu.pc >> 8) & 0x00FF) as u8);
-    cpu.write(STACK_BASE + (cpu.sp - 1) as u16, (cpu.pc & 0x00FF) as u8);
-    cpu.sp = cpu.sp.wrapping_sub(2);
-    cpu.pc = cpu.addr_abs;
+/// Jump to subroutine. The high byte of the target is only read after the return address has been
+/// pushed. Called once for each cycle after the opcode fetch, returning 1 as long as it needs
+/// another cycle.
+pub fn jsr<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    match cpu.step {
+        2 => {
+            cpu.addr_abs = cpu.read(cpu.pc).into();
+            cpu.pc = cpu.pc.wrapping_add(1);
+        },
+        3 => read_stack(cpu),
+        // The pushed return address points to the last byte of the JSR instruction
+        4 => cpu.push((cpu.pc >> 8) as u8),
+        5 => cpu.push(cpu.pc as u8),
+        _ => {
+            cpu.addr_abs |= (cpu.read(cpu.pc) as u16) << 8;
+            cpu.pc = cpu.addr_abs;
+            return 0;
+        },
+    }
 
-    0
+    1
 }
 
 /// Load the accumulator.
-pub fn lda(cpu: &mut CPU) -> u8 {
+pub fn lda<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.a = cpu.fetch();
     
     // Set flags
@@ -373,7 +411,7 @@ pub fn lda(cpu: &mut CPU) -> u8 {
 }
 
 /// Load the X register.
-pub fn ldx(cpu: &mut CPU) -> u8 {
+pub fn ldx<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.x = cpu.fetch();
     
     // Set flags
@@ -384,7 +422,7 @@ pub fn ldx(cpu: &mut CPU) -> u8 {
 }
 
 /// Load the Y register.
-pub fn ldy(cpu: &mut CPU) -> u8 {
+pub fn ldy<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.y = cpu.fetch();
     
     // Set flags
@@ -395,39 +433,36 @@ pub fn ldy(cpu: &mut CPU) -> u8 {
 }
 
 /// Shift one bit right (memory or accumulator).
-pub fn lsr(cpu: &mut CPU) -> u8 {
+pub fn lsr<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched = cpu.fetch();
-    let shifted = fetched >> 1 as u16;
+    let shifted = fetched >> 1;
 
     // Set flags
     cpu.status.set(StatusFlags::C, fetched & 0x0001 != 0);
     cpu.status.set(StatusFlags::N, (shifted & 0x0080) != 0);
-    cpu.status.set(StatusFlags::Z, (shifted & 0x00FF) == 0);
+    cpu.status.set(StatusFlags::Z, shifted == 0);
 
     // Write the result based on the addressing mode
-    if Instruction::decode(cpu.opcode).mode == AddressingMode::IMP {
-        cpu.a = shifted & 0x00FF;
+    if cpu.instruction().mode == AddressingMode::IMP {
+        cpu.a = shifted;
     } else {
-        cpu.write(cpu.addr_abs, shifted & 0x00FF);
+        cpu.write(cpu.addr_abs, shifted);
     }
 
     0
 }
 
 /// No operation.
-pub fn nop(cpu: &mut CPU) -> u8 {
-    // Not all NOPs are actually the same, see 
-    // https://wiki.nesdev.com/w/index.php/CPU_unofficial_opcodes
-    match cpu.opcode {
-        0x1C | 0x3C | 0x5C | 0x7C | 0xDC | 0xFC => 1,
-        _ => 0,
-    }
+pub fn nop<B: BusDevice>(_cpu: &mut CPU<B>) -> u8 {
+    // Not all NOPs are actually the same, the unofficial ones still read their operand through
+    // their addressing mode, see https://wiki.nesdev.com/w/index.php/CPU_unofficial_opcodes
+    0
 }
 
 /// Bitwise logic OR.
-pub fn ora(cpu: &mut CPU) -> u8 {
+pub fn ora<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched = cpu.fetch();
-    cpu.a = cpu.a | fetched;
+    cpu.a |= fetched;
 
     // Set flags
     cpu.status.set(StatusFlags::Z, cpu.a == 0);
@@ -437,51 +472,62 @@ pub fn ora(cpu: &mut CPU) -> u8 {
 }
 
 /// Push Accumulator to Stack.
-pub fn pha(cpu: &mut CPU) -> u8 {
-    cpu.write(STACK_BASE + cpu.sp as u16, cpu.a);
-    cpu.sp -= 1;
-
-    0
+pub fn pha<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    push_register(cpu, cpu.a)
 }
 
 /// Push status register to stack.
-pub fn php(cpu: &mut CPU) -> u8 {
-    // TODO: Does the status flag manipulation here work?
-    cpu.write(STACK_BASE + cpu.sp as u16, cpu.status.bits | StatusFlags::B.bits | StatusFlags::U.bits);
-    cpu.sp = cpu.sp.wrapping_sub(1);
-
-    // Set flags
-    cpu.status.set(StatusFlags::B, false);
-    cpu.status.set(StatusFlags::U, false);
-
-    0
+pub fn php<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    match cpu.step {
+        2 => {
+            cpu.read(cpu.pc);
+            1
+        },
+        _ => {
+            // The B and U flags are always set on the copy that is pushed, see
+            // https://www.nesdev.org/wiki/Status_flags#The_B_flag
+            cpu.push((cpu.status | StatusFlags::B | StatusFlags::U).bits);
+            0
+        },
+    }
 }
 
 /// Pop Accumulator off Stack.
-pub fn pla(cpu: &mut CPU) -> u8 {
-    cpu.sp += 1;
-    cpu.a = cpu.read(STACK_BASE + cpu.sp as u16);
-
-    // Set flags
-    cpu.status.set(StatusFlags::Z, cpu.a == 0);
-    cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
-
-    0
+pub fn pla<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    match pull_register(cpu) {
+        Some(value) => {
+            cpu.a = value;
+            0
+        },
+        None => 1,
+    }
 }
 
-/// Pop status register off stack.
-pub fn plp(cpu: &mut CPU) -> u8 {
-    cpu.sp = cpu.sp.wrapping_add(1);
-    cpu.status.bits = cpu.read(STACK_BASE + cpu.sp as u16);
-    
-    // Set flags
-    cpu.status.set(StatusFlags::U, true);
-
-    0
+/// Pop status register off stack. Like CLI and SEI, a change of the "disable interrupt" flag only
+/// affects interrupt polling after the next instruction.
+pub fn plp<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    match cpu.step {
+        2 => {
+            cpu.read(cpu.pc);
+            1
+        },
+        3 => {
+            read_stack(cpu);
+            1
+        },
+        _ => {
+            cpu.status.bits = cpu.pop();
+
+            // The B flag doesn't actually exist in the register, and U always reads back as set
+            cpu.status.set(StatusFlags::B, false);
+            cpu.status.set(StatusFlags::U, true);
+            0
+        },
+    }
 }
 
 /// Rotate one bit left (memory or accumulator).
-pub fn rol(cpu: &mut CPU) -> u8 {
+pub fn rol<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched: u16 = cpu.fetch().into();
     let rotated: u16 = (fetched << 1) | (cpu.status.contains(StatusFlags::C) as u16);
 
@@ -491,7 +537,7 @@ pub fn rol(cpu: &mut CPU) -> u8 {
     cpu.status.set(StatusFlags::Z, (rotated & 0x00FF) == 0);
 
     // Write the result based on the addressing mode
-    if Instruction::decode(cpu.opcode).mode == AddressingMode::IMP {
+    if cpu.instruction().mode == AddressingMode::IMP {
         cpu.a = (rotated & 0x00FF) as u8;
     } else {
         cpu.write(cpu.addr_abs, (rotated & 0x00FF) as u8);
@@ -501,7 +547,7 @@ pub fn rol(cpu: &mut CPU) -> u8 {
 }
 
 /// Rotate one bit right (memory or accumulator).
-pub fn ror(cpu: &mut CPU) -> u8 {
+pub fn ror<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     let fetched: u16 = cpu.fetch().into();
     let rotated: u16 = ((cpu.status.contains(StatusFlags::C) as u16) << 7) | (fetched >> 1);
 
@@ -511,7 +557,7 @@ pub fn ror(cpu: &mut CPU) -> u8 {
     cpu.status.set(StatusFlags::Z, (rotated & 0x00FF) == 0);
 
     // Write the result based on the addressing mode
-    if Instruction::decode(cpu.opcode).mode == AddressingMode::IMP {
+    if cpu.instruction().mode == AddressingMode::IMP {
         cpu.a = (rotated & 0x00FF) as u8;
     } else {
         cpu.write(cpu.addr_abs, (rotated & 0x00FF) as u8);
@@ -520,73 +566,91 @@ pub fn ror(cpu: &mut CPU) -> u8 {
     0
 }
 
-/// Returns from a BRK, IRQ or NMI.
-pub fn rti(cpu: &mut CPU) -> u8 {
-    // Restore the status register value from the stack
-    let status_bits = cpu.read(STACK_BASE + cpu.sp as u16 + 1);
-    cpu.status = StatusFlags::from_bits(status_bits).unwrap();
-    cpu.status.set(StatusFlags::B, false);
-    cpu.status.set(StatusFlags::U, false);
-    cpu.sp += 1;
-
-    let hi = cpu.read(STACK_BASE + cpu.sp as u16 + 1); 
-    let lo = cpu.read(STACK_BASE + cpu.sp as u16 + 2);
-    cpu.pc = u16::from_be_bytes([lo, hi]);
-    cpu.sp += 2;
+/// Returns from a BRK, IRQ or NMI. Called once for each cycle after the opcode fetch, returning 1
+/// as long as it needs another cycle.
+pub fn rti<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    match cpu.step {
+        2 => {
+            cpu.read(cpu.pc);
+        },
+        3 => read_stack(cpu),
+        // Restore the status register value from the stack
+        4 => {
+            cpu.status.bits = cpu.pop();
+            cpu.status.set(StatusFlags::B, false);
+            cpu.status.set(StatusFlags::U, true);
+        },
+        5 => cpu.addr_abs = cpu.pop().into(),
+        _ => {
+            cpu.addr_abs |= (cpu.pop() as u16) << 8;
+            cpu.pc = cpu.addr_abs;
+            return 0;
+        },
+    }
 
-    0
+    1
 }
 
-/// Return from subroutine.
-// TODO: Check this implementation
-pub fn rts(cpu: &mut CPU) -> u8 {
-    let hi = cpu.read(STACK_BASE + cpu.sp as u16 - 1); 
-    let lo = cpu.read(STACK_BASE + cpu.sp as u16 - 2);
-    cpu.pc = u16::from_be_bytes([lo, hi]);
-    cpu.pc += 1;
-    cpu.sp -= 2;
+/// Return from subroutine. Called once for each cycle after the opcode fetch, returning 1 as long
+/// as it needs another cycle.
+pub fn rts<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    match cpu.step {
+        2 => {
+            cpu.read(cpu.pc);
+        },
+        3 => read_stack(cpu),
+        4 => cpu.addr_abs = cpu.pop().into(),
+        5 => cpu.addr_abs |= (cpu.pop() as u16) << 8,
+        // JSR pushed the address of its last byte, so we need to skip ahead one
+        _ => {
+            cpu.read(cpu.addr_abs);
+            cpu.pc = cpu.addr_abs.wrapping_add(1);
+            return 0;
+        },
+    }
 
-    0
+    1
 }
 
 /// Set carry flag.
-pub fn sec(cpu: &mut CPU) -> u8 {
+pub fn sec<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.status.set(StatusFlags::C, true);
     0
 }
 
 /// Set decimal flag.
-pub fn sed(cpu: &mut CPU) -> u8 {
+pub fn sed<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.status.set(StatusFlags::D, true);
     0
 }
 
-/// Set interrupt flag (aka enable interrupts).
-pub fn sei(cpu: &mut CPU) -> u8 {
+/// Set interrupt flag (aka disable interrupts). An IRQ that was already pending is still serviced
+/// right after it.
+pub fn sei<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.status.set(StatusFlags::I, true);
     0
 }
 
 /// Store accumulator at address.
-pub fn sta(cpu: &mut CPU) -> u8 {
+pub fn sta<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.write(cpu.addr_abs, cpu.a);
     0
 }
 
 /// Store x register at address.
-pub fn stx(cpu: &mut CPU) -> u8 {
+pub fn stx<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.write(cpu.addr_abs, cpu.x);
     0
 }
 
 /// Store y register at address.
-pub fn sty(cpu: &mut CPU) -> u8 {
+pub fn sty<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.write(cpu.addr_abs, cpu.y);
     0
 }
 
 /// Transfer accumulator to x register.
-pub fn tax(cpu: &mut CPU) -> u8 {
+pub fn tax<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.x = cpu.a;
     cpu.status.set(StatusFlags::Z, cpu.x == 0);
     cpu.status.set(StatusFlags::N, cpu.x & 0x80 != 0);
@@ -595,7 +659,7 @@ pub fn tax(cpu: &mut CPU) -> u8 {
 }
 
 /// Transfer accumlator to y register.
-pub fn tay(cpu: &mut CPU) -> u8 {
+pub fn tay<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.y = cpu.a;
     cpu.status.set(StatusFlags::Z, cpu.y == 0);
     cpu.status.set(StatusFlags::N, cpu.y & 0x80 != 0);
@@ -604,7 +668,7 @@ pub fn tay(cpu: &mut CPU) -> u8 {
 }
 
 /// Transfer stack pointer to x register.
-pub fn tsx(cpu: &mut CPU) -> u8 {
+pub fn tsx<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.x = cpu.sp;
     cpu.status.set(StatusFlags::Z, cpu.x == 0);
     cpu.status.set(StatusFlags::N, cpu.x & 0x80 != 0);
@@ -613,7 +677,7 @@ pub fn tsx(cpu: &mut CPU) -> u8 {
 }
 
 /// Transfer x register to accumulator.
-pub fn txa(cpu: &mut CPU) -> u8 {
+pub fn txa<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.a = cpu.x;
     cpu.status.set(StatusFlags::Z, cpu.a == 0);
     cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
@@ -622,13 +686,13 @@ pub fn txa(cpu: &mut CPU) -> u8 {
 }
 
 /// Transfer x register to stack pointer.
-pub fn txs(cpu: &mut CPU) -> u8 {
+pub fn txs<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.sp = cpu.x;
     0
 }
 
 /// Transfer y register to accumulator.
-pub fn tya(cpu: &mut CPU) -> u8 {
+pub fn tya<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
     cpu.a = cpu.y;
     cpu.status.set(StatusFlags::Z, cpu.a == 0);
     cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
@@ -636,23 +700,537 @@ pub fn tya(cpu: &mut CPU) -> u8 {
     0
 }
 
-/// Unimplemented illegal opcode. Since some games actually use illegal opcodes, 
-/// we don't panic here, we just continue as if nothing happened. Specific illegal
-/// opcodes will need to be implemented on a need-to basis.
-pub fn xxx(cpu: &mut CPU) -> u8 {
+/// AND the immediate value with the accumulator, then copy the negative flag to the carry flag.
+pub fn anc<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    cpu.a &= cpu.fetch();
+    cpu.status.set(StatusFlags::Z, cpu.a == 0);
+    cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
+    cpu.status.set(StatusFlags::C, cpu.a & 0x80 != 0);
+
     0
 }
 
-/// Generic branch instruction
-fn branch(cpu: &mut CPU) {
-    cpu.cycles_remaining += 1;
-    cpu.addr_abs = cpu.pc + cpu.addr_rel;
+/// AND the immediate value with the accumulator, then shift the accumulator one bit right.
+pub fn alr<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let anded = cpu.a & cpu.fetch();
+    cpu.a = anded >> 1;
 
-    // If this instruction crossed the page boundary, we
-    // need to perform an additional clock cycle
-    if (cpu.addr_abs & 0xFF00) != (cpu.pc & 0xFF00) {
-        cpu.cycles_remaining += 1;
+    // Set flags
+    cpu.status.set(StatusFlags::C, anded & 0x01 != 0);
+    cpu.status.set(StatusFlags::Z, cpu.a == 0);
+    cpu.status.set(StatusFlags::N, false);
+
+    0
+}
+
+/// AND the immediate value with the accumulator, then rotate the accumulator one bit right. The
+/// carry and overflow flags come from bits 6 and 5 of the result, rather than from the rotation.
+pub fn arr<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let anded = cpu.a & cpu.fetch();
+    cpu.a = ((cpu.status.contains(StatusFlags::C) as u8) << 7) | (anded >> 1);
+
+    // Set flags
+    cpu.status.set(StatusFlags::Z, cpu.a == 0);
+    cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
+
+    if decimal_mode(cpu) {
+        // In decimal mode, both digits of the result are adjusted like after a BCD addition
+        // Ref: https://www.nesdev.org/6502_cpu.txt
+        cpu.status.set(StatusFlags::V, (cpu.a ^ anded) & 0x40 != 0);
+        if (anded & 0x0F) + (anded & 0x01) > 0x05 {
+            cpu.a = (cpu.a & 0xF0) | (cpu.a.wrapping_add(0x06) & 0x0F);
+        }
+        let carry = (anded as u16 + (anded & 0x10) as u16) & 0x1F0 > 0x50;
+        cpu.status.set(StatusFlags::C, carry);
+        if carry {
+            cpu.a = cpu.a.wrapping_add(0x60);
+        }
+    } else {
+        cpu.status.set(StatusFlags::C, cpu.a & 0x40 != 0);
+        cpu.status.set(StatusFlags::V, ((cpu.a >> 6) ^ (cpu.a >> 5)) & 0x01 != 0);
     }
 
-    cpu.pc = cpu.addr_abs;
+    0
+}
+
+/// Subtract the immediate value from the accumulator ANDed with the X register, and store the
+/// result in the X register. Sets the flags like CMP, ignoring the carry flag on the way in.
+pub fn axs<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    let anded = cpu.a & cpu.x;
+    cpu.x = anded.wrapping_sub(fetched);
+
+    // Set flags
+    cpu.status.set(StatusFlags::C, anded >= fetched);
+    cpu.status.set(StatusFlags::Z, cpu.x == 0);
+    cpu.status.set(StatusFlags::N, cpu.x & 0x80 != 0);
+
+    0
+}
+
+/// Decrement value at memory location, then compare it with the accumulator.
+pub fn dcp<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let decrement = cpu.fetch().wrapping_sub(1);
+    cpu.write(cpu.addr_abs, decrement);
+
+    // Set flags
+    let compared = cpu.a.wrapping_sub(decrement);
+    cpu.status.set(StatusFlags::C, cpu.a >= decrement);
+    cpu.status.set(StatusFlags::Z, compared == 0);
+    cpu.status.set(StatusFlags::N, compared & 0x80 != 0);
+
+    0
+}
+
+/// Increment value at memory location, then subtract it from the accumulator.
+pub fn isc<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let increment = cpu.fetch().wrapping_add(1);
+    cpu.write(cpu.addr_abs, increment);
+    subtract_with_borrow(cpu, increment);
+
+    0
+}
+
+/// Load both the accumulator and the X register. The immediate variant is unstable, and mixes in
+/// some bits of the accumulator.
+pub fn lax<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    cpu.a = if cpu.instruction().mode == AddressingMode::IMM {
+        (cpu.a | UNSTABLE_MAGIC) & fetched
+    } else {
+        fetched
+    };
+    cpu.x = cpu.a;
+
+    // Set flags
+    cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
+    cpu.status.set(StatusFlags::Z, cpu.a == 0);
+
+    1
+}
+
+/// AND the value at memory location with the stack pointer, and load the result into the
+/// accumulator, the X register and the stack pointer.
+pub fn las<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    cpu.sp &= cpu.fetch();
+    cpu.a = cpu.sp;
+    cpu.x = cpu.sp;
+
+    // Set flags
+    cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
+    cpu.status.set(StatusFlags::Z, cpu.a == 0);
+
+    1
+}
+
+/// Rotate value at memory location one bit left, then AND it with the accumulator.
+pub fn rla<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    let rotated = (fetched << 1) | (cpu.status.contains(StatusFlags::C) as u8);
+    cpu.write(cpu.addr_abs, rotated);
+    cpu.a &= rotated;
+
+    // Set flags
+    cpu.status.set(StatusFlags::C, fetched & 0x80 != 0);
+    cpu.status.set(StatusFlags::Z, cpu.a == 0);
+    cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
+
+    0
+}
+
+/// Rotate value at memory location one bit right, then add it to the accumulator.
+pub fn rra<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    let rotated = ((cpu.status.contains(StatusFlags::C) as u8) << 7) | (fetched >> 1);
+    cpu.write(cpu.addr_abs, rotated);
+
+    // The carry out of the rotation is the carry into the addition
+    cpu.status.set(StatusFlags::C, fetched & 0x01 != 0);
+    add_with_carry(cpu, rotated);
+
+    0
+}
+
+/// Store the accumulator ANDed with the X register at address.
+pub fn sax<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    cpu.write(cpu.addr_abs, cpu.a & cpu.x);
+    0
+}
+
+/// Store the accumulator ANDed with the X register at address, unstable.
+pub fn sha<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    unstable_store(cpu, cpu.a & cpu.x);
+    0
+}
+
+/// Store the X register at address, unstable.
+pub fn shx<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    unstable_store(cpu, cpu.x);
+    0
+}
+
+/// Store the Y register at address, unstable.
+pub fn shy<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    unstable_store(cpu, cpu.y);
+    0
+}
+
+/// Shift value at memory location one bit left, then OR it with the accumulator.
+pub fn slo<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    let shifted = fetched << 1;
+    cpu.write(cpu.addr_abs, shifted);
+    cpu.a |= shifted;
+
+    // Set flags
+    cpu.status.set(StatusFlags::C, fetched & 0x80 != 0);
+    cpu.status.set(StatusFlags::Z, cpu.a == 0);
+    cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
+
+    0
+}
+
+/// Shift value at memory location one bit right, then XOR it with the accumulator.
+pub fn sre<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    let shifted = fetched >> 1;
+    cpu.write(cpu.addr_abs, shifted);
+    cpu.a ^= shifted;
+
+    // Set flags
+    cpu.status.set(StatusFlags::C, fetched & 0x01 != 0);
+    cpu.status.set(StatusFlags::Z, cpu.a == 0);
+    cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
+
+    0
+}
+
+/// Transfer the accumulator ANDed with the X register to the stack pointer, then store it at
+/// address, unstable.
+pub fn tas<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    cpu.sp = cpu.a & cpu.x;
+    unstable_store(cpu, cpu.sp);
+    0
+}
+
+/// Transfer the X register ANDed with the immediate value to the accumulator. Unstable, as some
+/// bits of the accumulator are mixed in.
+pub fn xaa<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    cpu.a = (cpu.a | UNSTABLE_MAGIC) & cpu.x & cpu.fetch();
+    cpu.status.set(StatusFlags::Z, cpu.a == 0);
+    cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
+
+    0
+}
+
+/// Lock up the CPU. It stops fetching instructions and ignores interrupts, until it is reset.
+pub fn jam<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    cpu.jam(cpu.opcode, cpu.pc.wrapping_sub(1));
+    0
+}
+
+/// Branch always (65C02).
+pub fn bra<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    branch(cpu, true)
+}
+
+/// Push X register to stack (65C02).
+pub fn phx<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    push_register(cpu, cpu.x)
+}
+
+/// Push Y register to stack (65C02).
+pub fn phy<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    push_register(cpu, cpu.y)
+}
+
+/// Pop X register off stack (65C02).
+pub fn plx<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    match pull_register(cpu) {
+        Some(value) => {
+            cpu.x = value;
+            0
+        },
+        None => 1,
+    }
+}
+
+/// Pop Y register off stack (65C02).
+pub fn ply<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    match pull_register(cpu) {
+        Some(value) => {
+            cpu.y = value;
+            0
+        },
+        None => 1,
+    }
+}
+
+/// Store zero at address (65C02).
+pub fn stz<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    cpu.write(cpu.addr_abs, 0x00);
+    0
+}
+
+/// Test and reset the bits of the accumulator in memory (65C02). Sets Z like BIT.
+pub fn trb<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    cpu.write(cpu.addr_abs, fetched & !cpu.a);
+    cpu.status.set(StatusFlags::Z, fetched & cpu.a == 0);
+
+    0
+}
+
+/// Test and set the bits of the accumulator in memory (65C02). Sets Z like BIT.
+pub fn tsb<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    cpu.write(cpu.addr_abs, fetched | cpu.a);
+    cpu.status.set(StatusFlags::Z, fetched & cpu.a == 0);
+
+    0
+}
+
+/// Reset a bit of a zero page value (65C02).
+pub fn rmb<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    cpu.write(cpu.addr_abs, fetched & !opcode_bit(cpu));
+    0
+}
+
+/// Set a bit of a zero page value (65C02).
+pub fn smb<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let fetched = cpu.fetch();
+    cpu.write(cpu.addr_abs, fetched | opcode_bit(cpu));
+    0
+}
+
+/// Branch if a bit of a zero page value is reset (65C02).
+pub fn bbr<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let taken = cpu.fetch() & opcode_bit(cpu) == 0;
+    branch(cpu, taken)
+}
+
+/// Branch if a bit of a zero page value is set (65C02).
+pub fn bbs<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    let taken = cpu.fetch() & opcode_bit(cpu) != 0;
+    branch(cpu, taken)
+}
+
+/// Wait for an interrupt (65C02). The CPU pauses until the IRQ or NMI line is asserted.
+pub fn wai<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    cpu.state = CpuState::Waiting;
+    0
+}
+
+/// Stop the clock until a reset (65C02). Behaves like a JAM.
+pub fn stp<B: BusDevice>(cpu: &mut CPU<B>) -> u8 {
+    jam(cpu)
+}
+
+/// Push a register to the stack, shared by PHA, PHX and PHY. Called once for each cycle after the
+/// opcode fetch, returning 1 as long as it needs another cycle.
+fn push_register<B: BusDevice>(cpu: &mut CPU<B>, value: u8) -> u8 {
+    match cpu.step {
+        2 => {
+            cpu.read(cpu.pc);
+            1
+        },
+        _ => {
+            cpu.push(value);
+            0
+        },
+    }
+}
+
+/// Pull a register from the stack, shared by PLA, PLX and PLY. Called once for each cycle after
+/// the opcode fetch, returning the value on the last one.
+fn pull_register<B: BusDevice>(cpu: &mut CPU<B>) -> Option<u8> {
+    match cpu.step {
+        2 => {
+            cpu.read(cpu.pc);
+            None
+        },
+        3 => {
+            read_stack(cpu);
+            None
+        },
+        _ => {
+            let value = cpu.pop();
+
+            // Set flags
+            cpu.status.set(StatusFlags::Z, value == 0);
+            cpu.status.set(StatusFlags::N, value & 0x80 != 0);
+            Some(value)
+        },
+    }
+}
+
+/// The bit that RMB, SMB, BBR and BBS work on, encoded in the high nibble of the opcode.
+fn opcode_bit<B: BusDevice>(cpu: &CPU<B>) -> u8 {
+    1 << ((cpu.opcode >> 4) & 0x07)
+}
+
+/// Generic branch instruction. If the branch is taken, its target is put in `addr_abs` and 1 is
+/// returned, as that requires an additional cycle.
+fn branch<B: BusDevice>(cpu: &mut CPU<B>, taken: bool) -> u8 {
+    if !taken {
+        return 0;
+    }
+
+    cpu.addr_abs = cpu.pc.wrapping_add(cpu.addr_rel);
+    1
+}
+
+/// Read from the top of the stack without pulling, which the CPU does while it increments the
+/// stack pointer.
+fn read_stack<B: BusDevice>(cpu: &mut CPU<B>) {
+    cpu.read(STACK_BASE + cpu.sp as u16);
+}
+
+/// Push to the stack, except during a reset, which goes through the motions without writing.
+fn push_unless_reset<B: BusDevice>(cpu: &mut CPU<B>, data: u8) {
+    if cpu.interrupt == Some(Interrupt::Reset) {
+        read_stack(cpu);
+        cpu.sp = cpu.sp.wrapping_sub(1);
+    } else {
+        cpu.push(data);
+    }
+}
+
+/// Location of the address to jump to for BRK or the given hardware interrupt.
+fn interrupt_vector(interrupt: Option<Interrupt>) -> u16 {
+    match interrupt {
+        Some(Interrupt::Reset) => PC_POINTER,
+        Some(Interrupt::Nmi) => NMI_POINTER,
+        Some(Interrupt::Irq) | None => IRQ_POINTER,
+    }
+}
+
+/// Add a value and the carry bit to the accumulator, shared by ADC and RRA.
+fn add_with_carry<B: BusDevice>(cpu: &mut CPU<B>, value: u8) {
+    if decimal_mode(cpu) {
+        return add_decimal(cpu, value);
+    }
+
+    add_binary(cpu, value);
+}
+
+/// Subtract a value and the inverted carry bit from the accumulator, shared by SBC and ISC.
+fn subtract_with_borrow<B: BusDevice>(cpu: &mut CPU<B>, value: u8) {
+    let (a, carry) = (cpu.a, cpu.status.contains(StatusFlags::C));
+
+    // Invert the bits of the operand, the carry bit completes the two's complement. The flags
+    // are those of the binary subtraction, even in decimal mode.
+    add_binary(cpu, value ^ 0xFF);
+
+    if decimal_mode(cpu) {
+        subtract_decimal(cpu, a, value, carry);
+    }
+}
+
+fn add_binary<B: BusDevice>(cpu: &mut CPU<B>, value: u8) {
+    let value = value as u16;
+
+    // Add is performed in 16-bit domain for emulation to capture any carry bit, 
+    // which will exist in bit 8 of the 16-bit word
+    let result = value + cpu.a as u16 + cpu.status.contains(StatusFlags::C) as u16;
+
+    // We need to determine the signed overflow flag using the following fomula
+    let v = !((cpu.a as u16) ^ value) & ((cpu.a as u16) ^ result) & 0x0080;
+
+    // Set all the required
+    cpu.status.set(StatusFlags::C, result > 255);
+    cpu.status.set(StatusFlags::Z, result & 0x00FF == 0);
+    cpu.status.set(StatusFlags::N, result & 0b1000_0000 != 0);
+    cpu.status.set(StatusFlags::V, v != 0);
+
+    // Load the result back into the accumulator, but as a u8 of course!
+    cpu.a = (result & 0x00FF) as u8;
+}
+
+/// Whether the arithmetic instructions work on binary coded decimals.
+fn decimal_mode<B: BusDevice>(cpu: &CPU<B>) -> bool {
+    cpu.variant.has_decimal_mode() && cpu.status.contains(StatusFlags::D)
+}
+
+/// BCD addition. The NMOS 6502 sets the Z flag as if it did a binary addition, and the N and V
+/// flags before it adjusts the high digit. The 65C02 sets N and Z on the actual result.
+/// Ref: http://www.6502.org/tutorials/decimal_mode.html#A
+fn add_decimal<B: BusDevice>(cpu: &mut CPU<B>, value: u8) {
+    let (a, value) = (cpu.a as u16, value as u16);
+    let carry = cpu.status.contains(StatusFlags::C) as u16;
+
+    let mut lo = (a & 0x0F) + (value & 0x0F) + carry;
+    if lo >= 0x0A {
+        lo = ((lo + 0x06) & 0x0F) + 0x10;
+    }
+
+    let mut result = (a & 0xF0) + (value & 0xF0) + lo;
+    cpu.status.set(StatusFlags::Z, (a + value + carry) & 0x00FF == 0);
+    cpu.status.set(StatusFlags::N, result & 0x0080 != 0);
+    cpu.status.set(StatusFlags::V, !(a ^ value) & (a ^ result) & 0x0080 != 0);
+
+    if result >= 0xA0 {
+        result += 0x60;
+    }
+    cpu.status.set(StatusFlags::C, result >= 0x100);
+    cpu.a = (result & 0x00FF) as u8;
+
+    if cpu.variant == CpuVariant::Wdc65C02 {
+        cpu.status.set(StatusFlags::Z, cpu.a == 0);
+        cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
+    }
+}
+
+/// BCD subtraction of `value` from `a`. Only the accumulator differs from the binary subtraction,
+/// except on the 65C02 which sets N and Z on the actual result.
+/// Ref: http://www.6502.org/tutorials/decimal_mode.html#A
+fn subtract_decimal<B: BusDevice>(cpu: &mut CPU<B>, a: u8, value: u8, carry: bool) {
+    let (a, value) = (a as i16, value as i16);
+    let borrow = !carry as i16;
+    let mut lo = (a & 0x0F) - (value & 0x0F) - borrow;
+
+    let result = if cpu.variant == CpuVariant::Wdc65C02 {
+        let mut result = a - value - borrow;
+        if result < 0 {
+            result -= 0x60;
+        }
+        if lo < 0 {
+            result -= 0x06;
+        }
+        result
+    } else {
+        if lo < 0 {
+            lo = ((lo - 0x06) & 0x0F) - 0x10;
+        }
+        let mut result = (a & 0xF0) - (value & 0xF0) + lo;
+        if result < 0 {
+            result -= 0x60;
+        }
+        result
+    };
+    cpu.a = (result & 0x00FF) as u8;
+
+    if cpu.variant == CpuVariant::Wdc65C02 {
+        cpu.status.set(StatusFlags::Z, cpu.a == 0);
+        cpu.status.set(StatusFlags::N, cpu.a & 0x80 != 0);
+    }
+}
+
+/// The unstable stores AND the value with the high byte of the base address plus one. When the
+/// index crosses a page, the high byte of the target address is replaced by that value too.
+/// Ref: https://www.nesdev.org/wiki/CPU_unofficial_opcodes
+fn unstable_store<B: BusDevice>(cpu: &mut CPU<B>, value: u8) {
+    let index = match cpu.instruction().mode {
+        AddressingMode::ABX => cpu.x,
+        _ => cpu.y,
+    };
+    let base = cpu.addr_abs.wrapping_sub(index as u16);
+    let data = value & ((base >> 8) as u8).wrapping_add(1);
+
+    if (base & 0xFF00) != (cpu.addr_abs & 0xFF00) {
+        cpu.addr_abs = (cpu.addr_abs & 0x00FF) | ((data as u16) << 8);
+    }
+
+    cpu.write(cpu.addr_abs, data);
 }
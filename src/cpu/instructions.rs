@@ -1,9 +1,11 @@
 use once_cell::sync::Lazy;
-use super::{CPU, cpu_addr, cpu_instr};
+use super::CpuVariant;
 
-// Mnemonics for all 6502 CPU instructions
+// Mnemonics for all 6502 CPU instructions, including the undocumented ones and those added by the 65C02
 // Ref: http://www.thealmightyguru.com/Games/Hacking/Wiki/index.php/6502_Opcodes
-#[derive(Debug)]
+// Ref: https://www.nesdev.org/wiki/CPU_unofficial_opcodes
+// Ref: http://www.6502.org/tutorials/65c02opcodes.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mnemonic {
     LDA, LDX, LDY, STA, STX, STY, TAX, TAY, TSX, TXA, TXS, TYA,     // Storage
     ADC, DEC, DEX, DEY, INC, INX, INY, SBC,                         // Math
@@ -13,13 +15,72 @@ pub enum Mnemonic {
     CLC, CLD, CLI, CLV, CMP, CPX, CPY, SEC, SED, SEI,               // Registers
     PHA, PHP, PLA, PLP,                                             // Stack
     BRK, NOP,                                                       // System
-    XXX,
+    LAX, SAX, DCP, ISC, SLO, RLA, SRE, RRA,                         // Illegal, stable
+    ANC, ALR, ARR, AXS,                                             // Illegal, immediate
+    SHA, SHX, SHY, TAS, LAS, XAA,                                   // Illegal, unstable
+    JAM,                                                            // Illegal, halts the CPU
+    BRA, PHX, PHY, PLX, PLY, STZ, TRB, TSB,                         // 65C02
+    RMB, SMB, BBR, BBS,                                             // 65C02, bit number in the opcode
+    WAI, STP,                                                       // 65C02, WDC only
+}
+
+/// How an instruction accesses memory, which determines what happens on each of its cycles.
+/// Ref: https://www.nesdev.org/6502_cpu.txt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Reads its operand, or only uses registers
+    Read,
+    /// Writes to the effective address
+    Write,
+    /// Reads from the effective address, writes the value back unmodified, then writes the result
+    ReadModifyWrite,
+    /// Uses the effective address itself, without accessing it
+    Jump,
+    /// Pushes to or pulls from the stack, in a sequence of its own
+    Stack,
+}
+
+impl Mnemonic {
+    /// How the instruction accesses memory.
+    pub fn access(self) -> Access {
+        match self {
+            Mnemonic::STA | Mnemonic::STX | Mnemonic::STY | Mnemonic::SAX | Mnemonic::SHA | Mnemonic::SHX |
+            Mnemonic::SHY | Mnemonic::TAS | Mnemonic::STZ => Access::Write,
+            Mnemonic::ASL | Mnemonic::LSR | Mnemonic::ROL | Mnemonic::ROR | Mnemonic::INC | Mnemonic::DEC |
+            Mnemonic::SLO | Mnemonic::RLA | Mnemonic::SRE | Mnemonic::RRA | Mnemonic::ISC | Mnemonic::DCP |
+            Mnemonic::TRB | Mnemonic::TSB | Mnemonic::RMB | Mnemonic::SMB => Access::ReadModifyWrite,
+            Mnemonic::JMP => Access::Jump,
+            Mnemonic::BRK | Mnemonic::JSR | Mnemonic::RTI | Mnemonic::RTS | Mnemonic::PHA | Mnemonic::PHP |
+            Mnemonic::PLA | Mnemonic::PLP | Mnemonic::PHX | Mnemonic::PHY | Mnemonic::PLX |
+            Mnemonic::PLY => Access::Stack,
+            _ => Access::Read,
+        }
+    }
+
+    /// Whether the mnemonic is one of the 56 documented by MOS.
+    pub fn is_official(self) -> bool {
+        !matches!(self,
+            Mnemonic::LAX | Mnemonic::SAX | Mnemonic::DCP | Mnemonic::ISC | Mnemonic::SLO | Mnemonic::RLA |
+            Mnemonic::SRE | Mnemonic::RRA | Mnemonic::ANC | Mnemonic::ALR | Mnemonic::ARR | Mnemonic::AXS |
+            Mnemonic::SHA | Mnemonic::SHX | Mnemonic::SHY | Mnemonic::TAS | Mnemonic::LAS | Mnemonic::XAA |
+            Mnemonic::JAM
+        ) && !self.is_cmos()
+    }
+
+    /// Whether the mnemonic was added by the 65C02.
+    pub fn is_cmos(self) -> bool {
+        matches!(self,
+            Mnemonic::BRA | Mnemonic::PHX | Mnemonic::PHY | Mnemonic::PLX | Mnemonic::PLY | Mnemonic::STZ |
+            Mnemonic::TRB | Mnemonic::TSB | Mnemonic::RMB | Mnemonic::SMB | Mnemonic::BBR | Mnemonic::BBS |
+            Mnemonic::WAI | Mnemonic::STP
+        )
+    }
 }
 
 // All possible 6502 addressing modes
 // Addressing modes define how the CPU fetched the required operands for an instructions
 // Ref: http://www.thealmightyguru.com/Games/Hacking/Wiki/index.php?title=Addressing_Modes
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AddressingMode {
     ZP0,        // ZeroPage             Operand is an address and only the low byte is used,         ex: LDA $EE
     ZPX,        // Indexed ZeroPage X   Operand is 1-byte address, X register is added to it         eg: STA $00,X
@@ -34,15 +95,17 @@ pub enum AddressingMode {
     REL,        // Relative             1-byte signed operand is added to the program counter        eg: BEQ $04
     IZX,        // Indexed Indirect     2-byte pointer from 1-byte address and adding X register     eg: LDA ($40, X)
     IZY,        // Indirect Indexed     2-byte pointer from 1-byte address and adding Y after read   eg: LDA ($46), Y
+    IZP,        // ZeroPage Indirect    2-byte pointer from 1-byte address (65C02)                   eg: LDA ($46)
+    IAX,        // Absolute Indirect X  2-byte pointer from 2-byte address and adding X (65C02)      eg: JMP ($1000, X)
+    ZPR,        // ZeroPage Relative    1-byte address to test, then a relative branch (65C02)       eg: BBR0 $12, $04
 }
 
 pub type OpCode = u8;
 
+#[derive(Debug, Clone, Copy)]
 pub struct Instruction {
     pub mnemonic: Mnemonic,
-    pub op_exec: fn(&mut CPU) -> u8,
     pub mode: AddressingMode,
-    pub mode_exec: fn(&mut CPU) -> u8,
     pub cycles: u8,
 }
 
@@ -50,263 +113,336 @@ impl Instruction {
     pub fn decode(opcode: OpCode) -> &'static Instruction {
         &INSTRUCTION_MAP[opcode as usize]
     }
+
+    /// Decode the opcode for the given 6502 variant. Only the 65C02 decodes differently.
+    pub fn decode_for(opcode: OpCode, variant: CpuVariant) -> &'static Instruction {
+        match variant {
+            CpuVariant::Wdc65C02 => &WDC_65C02_MAP[opcode as usize],
+            CpuVariant::Ricoh2A03 | CpuVariant::Nmos6502 => Instruction::decode(opcode),
+        }
+    }
+
+    /// Whether the opcode is one of the 151 documented by MOS.
+    pub fn is_official(opcode: OpCode) -> bool {
+        Instruction::decode(opcode).mnemonic.is_official() && !UNOFFICIAL_ALIASES.contains(&opcode)
+    }
 }
 
+/// Undocumented opcodes that behave like, and decode to, an official instruction.
+const UNOFFICIAL_ALIASES: [OpCode; 28] = [
+    0x04, 0x0C, 0x14, 0x1A, 0x1C, 0x34, 0x3A, 0x3C, 0x44, 0x54, 0x5A, 0x5C, 0x64, 0x74,
+    0x7A, 0x7C, 0x80, 0x82, 0x89, 0xC2, 0xD4, 0xDA, 0xDC, 0xE2, 0xEB, 0xF4, 0xFA, 0xFC,
+];
+
 static INSTRUCTION_MAP: Lazy<[Instruction; 256]> = Lazy::new(|| {[
-    Instruction { mnemonic: Mnemonic::BRK, op_exec: cpu_instr::brk, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::ORA, op_exec: cpu_instr::ora, mode: AddressingMode::IZX, mode_exec: cpu_addr::izx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::ORA, op_exec: cpu_instr::ora, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::ASL, op_exec: cpu_instr::asl, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::PHP, op_exec: cpu_instr::php, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::ORA, op_exec: cpu_instr::ora, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::ASL, op_exec: cpu_instr::asl, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ORA, op_exec: cpu_instr::ora, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ASL, op_exec: cpu_instr::asl, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::BPL, op_exec: cpu_instr::bpl, mode: AddressingMode::REL, mode_exec: cpu_addr::rel, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::ORA, op_exec: cpu_instr::ora, mode: AddressingMode::IZY, mode_exec: cpu_addr::izy, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ORA, op_exec: cpu_instr::ora, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ASL, op_exec: cpu_instr::asl, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::CLC, op_exec: cpu_instr::clc, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::ORA, op_exec: cpu_instr::ora, mode: AddressingMode::ABY, mode_exec: cpu_addr::aby, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ORA, op_exec: cpu_instr::ora, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ASL, op_exec: cpu_instr::asl, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::JSR, op_exec: cpu_instr::jsr, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::AND, op_exec: cpu_instr::and, mode: AddressingMode::IZX, mode_exec: cpu_addr::izx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::BIT, op_exec: cpu_instr::bit, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::AND, op_exec: cpu_instr::and, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::ROL, op_exec: cpu_instr::rol, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::PLP, op_exec: cpu_instr::plp, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::AND, op_exec: cpu_instr::and, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::ROL, op_exec: cpu_instr::rol, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::BIT, op_exec: cpu_instr::bit, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::AND, op_exec: cpu_instr::and, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ROL, op_exec: cpu_instr::rol, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::BMI, op_exec: cpu_instr::bmi, mode: AddressingMode::REL, mode_exec: cpu_addr::rel, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::AND, op_exec: cpu_instr::and, mode: AddressingMode::IZY, mode_exec: cpu_addr::izy, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::AND, op_exec: cpu_instr::and, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ROL, op_exec: cpu_instr::rol, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::SEC, op_exec: cpu_instr::sec, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::AND, op_exec: cpu_instr::and, mode: AddressingMode::ABY, mode_exec: cpu_addr::aby, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::AND, op_exec: cpu_instr::and, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ROL, op_exec: cpu_instr::rol, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::RTI, op_exec: cpu_instr::rti, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::EOR, op_exec: cpu_instr::eor, mode: AddressingMode::IZX, mode_exec: cpu_addr::izx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::EOR, op_exec: cpu_instr::eor, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::LSR, op_exec: cpu_instr::lsr, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::PHA, op_exec: cpu_instr::pha, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::EOR, op_exec: cpu_instr::eor, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::LSR, op_exec: cpu_instr::lsr, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::JMP, op_exec: cpu_instr::jmp, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::EOR, op_exec: cpu_instr::eor, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::LSR, op_exec: cpu_instr::lsr, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::BVC, op_exec: cpu_instr::bvc, mode: AddressingMode::REL, mode_exec: cpu_addr::rel, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::EOR, op_exec: cpu_instr::eor, mode: AddressingMode::IZY, mode_exec: cpu_addr::izy, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::EOR, op_exec: cpu_instr::eor, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::LSR, op_exec: cpu_instr::lsr, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::CLI, op_exec: cpu_instr::cli, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::EOR, op_exec: cpu_instr::eor, mode: AddressingMode::ABY, mode_exec: cpu_addr::aby, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::EOR, op_exec: cpu_instr::eor, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::LSR, op_exec: cpu_instr::lsr, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::RTS, op_exec: cpu_instr::rts, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::ADC, op_exec: cpu_instr::adc, mode: AddressingMode::IZX, mode_exec: cpu_addr::izx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::ADC, op_exec: cpu_instr::adc, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::ROR, op_exec: cpu_instr::ror, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::PLA, op_exec: cpu_instr::pla, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ADC, op_exec: cpu_instr::adc, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::ROR, op_exec: cpu_instr::ror, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::JMP, op_exec: cpu_instr::jmp, mode: AddressingMode::IND, mode_exec: cpu_addr::ind, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::ADC, op_exec: cpu_instr::adc, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ROR, op_exec: cpu_instr::ror, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::BVS, op_exec: cpu_instr::bvs, mode: AddressingMode::REL, mode_exec: cpu_addr::rel, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::ADC, op_exec: cpu_instr::adc, mode: AddressingMode::IZY, mode_exec: cpu_addr::izy, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ADC, op_exec: cpu_instr::adc, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ROR, op_exec: cpu_instr::ror, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::SEI, op_exec: cpu_instr::sei, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::ADC, op_exec: cpu_instr::adc, mode: AddressingMode::ABY, mode_exec: cpu_addr::aby, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ADC, op_exec: cpu_instr::adc, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::ROR, op_exec: cpu_instr::ror, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::STA, op_exec: cpu_instr::sta, mode: AddressingMode::IZX, mode_exec: cpu_addr::izx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::STY, op_exec: cpu_instr::sty, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::STA, op_exec: cpu_instr::sta, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::STX, op_exec: cpu_instr::stx, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::DEY, op_exec: cpu_instr::dey, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::TXA, op_exec: cpu_instr::txa, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::STY, op_exec: cpu_instr::sty, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::STA, op_exec: cpu_instr::sta, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::STX, op_exec: cpu_instr::stx, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::BCC, op_exec: cpu_instr::bcc, mode: AddressingMode::REL, mode_exec: cpu_addr::rel, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::STA, op_exec: cpu_instr::sta, mode: AddressingMode::IZY, mode_exec: cpu_addr::izy, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::STY, op_exec: cpu_instr::sty, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::STA, op_exec: cpu_instr::sta, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::STX, op_exec: cpu_instr::stx, mode: AddressingMode::ZPY, mode_exec: cpu_addr::zpy, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::TYA, op_exec: cpu_instr::tya, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::STA, op_exec: cpu_instr::sta, mode: AddressingMode::ABY, mode_exec: cpu_addr::aby, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::TXS, op_exec: cpu_instr::txs, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::STA, op_exec: cpu_instr::sta, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::LDY, op_exec: cpu_instr::ldy, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::LDA, op_exec: cpu_instr::lda, mode: AddressingMode::IZX, mode_exec: cpu_addr::izx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::LDX, op_exec: cpu_instr::ldx, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::LDY, op_exec: cpu_instr::ldy, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::LDA, op_exec: cpu_instr::lda, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::LDX, op_exec: cpu_instr::ldx, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::TAY, op_exec: cpu_instr::tay, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::LDA, op_exec: cpu_instr::lda, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::TAX, op_exec: cpu_instr::tax, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::LDY, op_exec: cpu_instr::ldy, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::LDA, op_exec: cpu_instr::lda, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::LDX, op_exec: cpu_instr::ldx, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::BCS, op_exec: cpu_instr::bcs, mode: AddressingMode::REL, mode_exec: cpu_addr::rel, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::LDA, op_exec: cpu_instr::lda, mode: AddressingMode::IZY, mode_exec: cpu_addr::izy, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::LDY, op_exec: cpu_instr::ldy, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::LDA, op_exec: cpu_instr::lda, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::LDX, op_exec: cpu_instr::ldx, mode: AddressingMode::ZPY, mode_exec: cpu_addr::zpy, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::CLV, op_exec: cpu_instr::clv, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::LDA, op_exec: cpu_instr::lda, mode: AddressingMode::ABY, mode_exec: cpu_addr::aby, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::TSX, op_exec: cpu_instr::tsx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::LDY, op_exec: cpu_instr::ldy, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::LDA, op_exec: cpu_instr::lda, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::LDX, op_exec: cpu_instr::ldx, mode: AddressingMode::ABY, mode_exec: cpu_addr::aby, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::CPY, op_exec: cpu_instr::cpy, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::CMP, op_exec: cpu_instr::cmp, mode: AddressingMode::IZX, mode_exec: cpu_addr::izx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::CPY, op_exec: cpu_instr::cpy, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::CMP, op_exec: cpu_instr::cmp, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::DEC, op_exec: cpu_instr::dec, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::INY, op_exec: cpu_instr::iny, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::CMP, op_exec: cpu_instr::cmp, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::DEX, op_exec: cpu_instr::dex, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::CPY, op_exec: cpu_instr::cpy, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::CMP, op_exec: cpu_instr::cmp, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::DEC, op_exec: cpu_instr::dec, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::BNE, op_exec: cpu_instr::bne, mode: AddressingMode::REL, mode_exec: cpu_addr::rel, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::CMP, op_exec: cpu_instr::cmp, mode: AddressingMode::IZY, mode_exec: cpu_addr::izy, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::CMP, op_exec: cpu_instr::cmp, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::DEC, op_exec: cpu_instr::dec, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::CLD, op_exec: cpu_instr::cld, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::CMP, op_exec: cpu_instr::cmp, mode: AddressingMode::ABY, mode_exec: cpu_addr::aby, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::CMP, op_exec: cpu_instr::cmp, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::DEC, op_exec: cpu_instr::dec, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::CPX, op_exec: cpu_instr::cpx, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::SBC, op_exec: cpu_instr::sbc, mode: AddressingMode::IZX, mode_exec: cpu_addr::izx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::CPX, op_exec: cpu_instr::cpx, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::SBC, op_exec: cpu_instr::sbc, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 3 },
-    Instruction { mnemonic: Mnemonic::INC, op_exec: cpu_instr::inc, mode: AddressingMode::ZP0, mode_exec: cpu_addr::zp0, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::INX, op_exec: cpu_instr::inx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::SBC, op_exec: cpu_instr::sbc, mode: AddressingMode::IMM, mode_exec: cpu_addr::imm, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::SBC, op_exec: cpu_instr::sbc, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::CPX, op_exec: cpu_instr::cpx, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::SBC, op_exec: cpu_instr::sbc, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::INC, op_exec: cpu_instr::inc, mode: AddressingMode::ABS, mode_exec: cpu_addr::abs, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::BEQ, op_exec: cpu_instr::beq, mode: AddressingMode::REL, mode_exec: cpu_addr::rel, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::SBC, op_exec: cpu_instr::sbc, mode: AddressingMode::IZY, mode_exec: cpu_addr::izy, cycles: 5 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 8 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::SBC, op_exec: cpu_instr::sbc, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::INC, op_exec: cpu_instr::inc, mode: AddressingMode::ZPX, mode_exec: cpu_addr::zpx, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 6 },
-    Instruction { mnemonic: Mnemonic::SED, op_exec: cpu_instr::sed, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::SBC, op_exec: cpu_instr::sbc, mode: AddressingMode::ABY, mode_exec: cpu_addr::aby, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 2 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::NOP, op_exec: cpu_instr::nop, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::SBC, op_exec: cpu_instr::sbc, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 4 },
-    Instruction { mnemonic: Mnemonic::INC, op_exec: cpu_instr::inc, mode: AddressingMode::ABX, mode_exec: cpu_addr::abx, cycles: 7 },
-    Instruction { mnemonic: Mnemonic::XXX, op_exec: cpu_instr::xxx, mode: AddressingMode::IMP, mode_exec: cpu_addr::imp, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::BRK, mode: AddressingMode::IMM, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::ORA, mode: AddressingMode::IZX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SLO, mode: AddressingMode::IZX, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::ORA, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::ASL, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::SLO, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::PHP, mode: AddressingMode::IMP, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::ORA, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ASL, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ANC, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ORA, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ASL, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::SLO, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::BPL, mode: AddressingMode::REL, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ORA, mode: AddressingMode::IZY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SLO, mode: AddressingMode::IZY, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ORA, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ASL, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::SLO, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::CLC, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ORA, mode: AddressingMode::ABY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SLO, mode: AddressingMode::ABY, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ORA, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ASL, mode: AddressingMode::ABX, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::SLO, mode: AddressingMode::ABX, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::JSR, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::AND, mode: AddressingMode::IZX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::RLA, mode: AddressingMode::IZX, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::BIT, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::AND, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::ROL, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::RLA, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::PLP, mode: AddressingMode::IMP, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::AND, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ROL, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ANC, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::BIT, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::AND, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ROL, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::RLA, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::BMI, mode: AddressingMode::REL, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::AND, mode: AddressingMode::IZY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::RLA, mode: AddressingMode::IZY, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::AND, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ROL, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::RLA, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::SEC, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::AND, mode: AddressingMode::ABY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::RLA, mode: AddressingMode::ABY, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::AND, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ROL, mode: AddressingMode::ABX, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::RLA, mode: AddressingMode::ABX, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::RTI, mode: AddressingMode::IMP, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::EOR, mode: AddressingMode::IZX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SRE, mode: AddressingMode::IZX, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::EOR, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::LSR, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::SRE, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::PHA, mode: AddressingMode::IMP, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::EOR, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::LSR, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ALR, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::JMP, mode: AddressingMode::ABS, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::EOR, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LSR, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::SRE, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::BVC, mode: AddressingMode::REL, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::EOR, mode: AddressingMode::IZY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SRE, mode: AddressingMode::IZY, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::EOR, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LSR, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::SRE, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::CLI, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::EOR, mode: AddressingMode::ABY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SRE, mode: AddressingMode::ABY, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::EOR, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LSR, mode: AddressingMode::ABX, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::SRE, mode: AddressingMode::ABX, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::RTS, mode: AddressingMode::IMP, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::ADC, mode: AddressingMode::IZX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::RRA, mode: AddressingMode::IZX, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::ADC, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::ROR, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::RRA, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::PLA, mode: AddressingMode::IMP, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ADC, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ROR, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ARR, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::JMP, mode: AddressingMode::IND, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::ADC, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ROR, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::RRA, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::BVS, mode: AddressingMode::REL, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ADC, mode: AddressingMode::IZY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::RRA, mode: AddressingMode::IZY, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ADC, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ROR, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::RRA, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::SEI, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ADC, mode: AddressingMode::ABY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::RRA, mode: AddressingMode::ABY, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ADC, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::ROR, mode: AddressingMode::ABX, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::RRA, mode: AddressingMode::ABX, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::STA, mode: AddressingMode::IZX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SAX, mode: AddressingMode::IZX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::STY, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::STA, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::STX, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::SAX, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::DEY, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::TXA, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::XAA, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::STY, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::STA, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::STX, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::SAX, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::BCC, mode: AddressingMode::REL, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::STA, mode: AddressingMode::IZY, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SHA, mode: AddressingMode::IZY, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::STY, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::STA, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::STX, mode: AddressingMode::ZPY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::SAX, mode: AddressingMode::ZPY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::TYA, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::STA, mode: AddressingMode::ABY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::TXS, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::TAS, mode: AddressingMode::ABY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::SHY, mode: AddressingMode::ABX, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::STA, mode: AddressingMode::ABX, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::SHX, mode: AddressingMode::ABY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::SHA, mode: AddressingMode::ABY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::LDY, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::LDA, mode: AddressingMode::IZX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::LDX, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::LAX, mode: AddressingMode::IZX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::LDY, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::LDA, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::LDX, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::LAX, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::TAY, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::LDA, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::TAX, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::LAX, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::LDY, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LDA, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LDX, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LAX, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::BCS, mode: AddressingMode::REL, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::LDA, mode: AddressingMode::IZY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::LAX, mode: AddressingMode::IZY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::LDY, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LDA, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LDX, mode: AddressingMode::ZPY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LAX, mode: AddressingMode::ZPY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::CLV, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::LDA, mode: AddressingMode::ABY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::TSX, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::LAS, mode: AddressingMode::ABY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LDY, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LDA, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LDX, mode: AddressingMode::ABY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::LAX, mode: AddressingMode::ABY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::CPY, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::CMP, mode: AddressingMode::IZX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::DCP, mode: AddressingMode::IZX, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::CPY, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::CMP, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::DEC, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::DCP, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::INY, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::CMP, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::DEX, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::AXS, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::CPY, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::CMP, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::DEC, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::DCP, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::BNE, mode: AddressingMode::REL, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::CMP, mode: AddressingMode::IZY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::DCP, mode: AddressingMode::IZY, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::CMP, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::DEC, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::DCP, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::CLD, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::CMP, mode: AddressingMode::ABY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::DCP, mode: AddressingMode::ABY, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::CMP, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::DEC, mode: AddressingMode::ABX, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::DCP, mode: AddressingMode::ABX, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::CPX, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SBC, mode: AddressingMode::IZX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ISC, mode: AddressingMode::IZX, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::CPX, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::SBC, mode: AddressingMode::ZP0, cycles: 3 },
+    Instruction { mnemonic: Mnemonic::INC, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::ISC, mode: AddressingMode::ZP0, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::INX, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SBC, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SBC, mode: AddressingMode::IMM, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::CPX, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::SBC, mode: AddressingMode::ABS, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::INC, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::ISC, mode: AddressingMode::ABS, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::BEQ, mode: AddressingMode::REL, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SBC, mode: AddressingMode::IZY, cycles: 5 },
+    Instruction { mnemonic: Mnemonic::JAM, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ISC, mode: AddressingMode::IZY, cycles: 8 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::SBC, mode: AddressingMode::ZPX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::INC, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::ISC, mode: AddressingMode::ZPX, cycles: 6 },
+    Instruction { mnemonic: Mnemonic::SED, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::SBC, mode: AddressingMode::ABY, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::IMP, cycles: 2 },
+    Instruction { mnemonic: Mnemonic::ISC, mode: AddressingMode::ABY, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::NOP, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::SBC, mode: AddressingMode::ABX, cycles: 4 },
+    Instruction { mnemonic: Mnemonic::INC, mode: AddressingMode::ABX, cycles: 7 },
+    Instruction { mnemonic: Mnemonic::ISC, mode: AddressingMode::ABX, cycles: 7 },
 ]});
+
+/// The opcodes of the WDC 65C02 that differ from the NMOS 6502, not counting the RMB, SMB, BBR and
+/// BBS columns. All its other undefined opcodes are single cycle NOPs.
+/// Ref: http://www.6502.org/tutorials/65c02opcodes.html
+const WDC_65C02_OPCODES: [(OpCode, Mnemonic, AddressingMode, u8); 44] = [
+    (0x02, Mnemonic::NOP, AddressingMode::IMM, 2), (0x22, Mnemonic::NOP, AddressingMode::IMM, 2),
+    (0x42, Mnemonic::NOP, AddressingMode::IMM, 2), (0x62, Mnemonic::NOP, AddressingMode::IMM, 2),
+    (0x82, Mnemonic::NOP, AddressingMode::IMM, 2), (0xC2, Mnemonic::NOP, AddressingMode::IMM, 2),
+    (0xE2, Mnemonic::NOP, AddressingMode::IMM, 2), (0x44, Mnemonic::NOP, AddressingMode::ZP0, 3),
+    (0x54, Mnemonic::NOP, AddressingMode::ZPX, 4), (0xD4, Mnemonic::NOP, AddressingMode::ZPX, 4),
+    (0xF4, Mnemonic::NOP, AddressingMode::ZPX, 4), (0xDC, Mnemonic::NOP, AddressingMode::ABS, 4),
+    (0xFC, Mnemonic::NOP, AddressingMode::ABS, 4),
+    // Takes 8 cycles on the real chip
+    (0x5C, Mnemonic::NOP, AddressingMode::ABS, 4),
+    (0x12, Mnemonic::ORA, AddressingMode::IZP, 5), (0x32, Mnemonic::AND, AddressingMode::IZP, 5),
+    (0x52, Mnemonic::EOR, AddressingMode::IZP, 5), (0x72, Mnemonic::ADC, AddressingMode::IZP, 5),
+    (0x92, Mnemonic::STA, AddressingMode::IZP, 5), (0xB2, Mnemonic::LDA, AddressingMode::IZP, 5),
+    (0xD2, Mnemonic::CMP, AddressingMode::IZP, 5), (0xF2, Mnemonic::SBC, AddressingMode::IZP, 5),
+    (0x04, Mnemonic::TSB, AddressingMode::ZP0, 5), (0x0C, Mnemonic::TSB, AddressingMode::ABS, 6),
+    (0x14, Mnemonic::TRB, AddressingMode::ZP0, 5), (0x1C, Mnemonic::TRB, AddressingMode::ABS, 6),
+    (0x1A, Mnemonic::INC, AddressingMode::IMP, 2), (0x3A, Mnemonic::DEC, AddressingMode::IMP, 2),
+    (0x34, Mnemonic::BIT, AddressingMode::ZPX, 4), (0x3C, Mnemonic::BIT, AddressingMode::ABX, 4),
+    (0x89, Mnemonic::BIT, AddressingMode::IMM, 2),
+    (0x5A, Mnemonic::PHY, AddressingMode::IMP, 3), (0x7A, Mnemonic::PLY, AddressingMode::IMP, 4),
+    (0xDA, Mnemonic::PHX, AddressingMode::IMP, 3), (0xFA, Mnemonic::PLX, AddressingMode::IMP, 4),
+    (0x64, Mnemonic::STZ, AddressingMode::ZP0, 3), (0x74, Mnemonic::STZ, AddressingMode::ZPX, 4),
+    (0x9C, Mnemonic::STZ, AddressingMode::ABS, 4), (0x9E, Mnemonic::STZ, AddressingMode::ABX, 5),
+    (0x6C, Mnemonic::JMP, AddressingMode::IND, 6), (0x7C, Mnemonic::JMP, AddressingMode::IAX, 6),
+    (0x80, Mnemonic::BRA, AddressingMode::REL, 3),
+    (0xCB, Mnemonic::WAI, AddressingMode::IMP, 3), (0xDB, Mnemonic::STP, AddressingMode::IMP, 3),
+];
+
+static WDC_65C02_MAP: Lazy<[Instruction; 256]> = Lazy::new(|| {
+    let mut map = *INSTRUCTION_MAP;
+
+    for (opcode, instruction) in map.iter_mut().enumerate() {
+        let (row, column) = (opcode >> 4, opcode & 0x0F);
+        let (mnemonic, mode, cycles) = match column {
+            0x07 if row < 0x08 => (Mnemonic::RMB, AddressingMode::ZP0, 5),
+            0x07 => (Mnemonic::SMB, AddressingMode::ZP0, 5),
+            0x0F if row < 0x08 => (Mnemonic::BBR, AddressingMode::ZPR, 5),
+            0x0F => (Mnemonic::BBS, AddressingMode::ZPR, 5),
+            _ if !Instruction::is_official(opcode as OpCode) => (Mnemonic::NOP, AddressingMode::IMP, 1),
+            _ => continue,
+        };
+        *instruction = Instruction { mnemonic, mode, cycles };
+    }
+
+    for (opcode, mnemonic, mode, cycles) in WDC_65C02_OPCODES {
+        map[opcode as usize] = Instruction { mnemonic, mode, cycles };
+    }
+
+    map
+});
@@ -2,10 +2,12 @@ pub mod cpu_addr;
 pub mod cpu_instr;
 pub mod disassemble;
 pub mod instructions;
+pub mod trace;
 
+use std::fmt;
 use bitflags::bitflags;
-use crate::bus::Bus;
-use self::instructions::{AddressingMode, Instruction};
+use crate::{bus::{Bus, BusDevice}, save_state::{SaveState, SaveStateError, StateReader, StateWriter}};
+use self::{instructions::{Access, Instruction}, trace::Tracer};
 
 /// Base location of the stack to which we can add the stack pointer offset.
 pub const STACK_BASE: u16 = 0x0100;
@@ -27,7 +29,7 @@ bitflags! {
         const Z = 1 << 1;
         /// Disable interrupts flag
         const I = 1 << 2;
-        /// Decimal mode flag (Unused, as the NES's version of the 6502 does NOT support hardware decimal mode)
+        /// Decimal mode flag (Ignored by the 2A03, the NES's version of the 6502 does NOT support hardware decimal mode)
         const D = 1 << 3;
         /// Break flag
         const B = 1 << 4;
@@ -40,10 +42,65 @@ bitflags! {
     }
 }
 
+/// The flavours of the 6502 the CPU can emulate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CpuVariant {
+    /// The Ricoh 2A03 of the NES, an NMOS 6502 without decimal mode
+    #[default]
+    Ricoh2A03,
+    /// The original NMOS 6502, which does BCD arithmetic in ADC and SBC when the D flag is set
+    Nmos6502,
+    /// The WDC 65C02, with decimal mode, the new CMOS instructions and the JMP indirect bug fixed.
+    /// Its undefined opcodes are NOPs. Bus accesses are still those of the NMOS 6502, and the
+    /// extra cycle of decimal ADC and SBC isn't emulated.
+    Wdc65C02,
+}
+
+impl CpuVariant {
+    /// Whether ADC and SBC do BCD arithmetic when the D flag is set.
+    pub fn has_decimal_mode(self) -> bool {
+        self != CpuVariant::Ricoh2A03
+    }
+}
+
+/// Whether the CPU is executing instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuState {
+    Running,
+    /// A WAI instruction paused the CPU until an interrupt comes in (65C02 only)
+    Waiting,
+    /// A JAM opcode locked up the CPU. Only a reset brings it back.
+    Jammed {
+        /// The JAM opcode that was executed
+        opcode: u8,
+        /// Location of the JAM opcode
+        pc: u16,
+    },
+}
+
+/// The hardware signals that make the CPU run the BRK sequence instead of the next instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    Reset,
+    Nmi,
+    Irq,
+}
+
+/// Called with the opcode and its location when the CPU jams.
+pub struct JamHandler(pub Box<dyn FnMut(u8, u16)>);
+
+impl fmt::Debug for JamHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("JamHandler")
+    }
+}
+
 #[derive(Debug)]
-pub struct CPU {
+pub struct CPU<B: BusDevice = Bus> {
     /// The memory bus
-    pub bus: Bus,
+    pub bus: B,
+    /// Which 6502 is emulated, the 2A03 by default
+    pub variant: CpuVariant,
 
     // Registers
 
@@ -68,16 +125,58 @@ pub struct CPU {
     pub addr_abs: u16,
     /// Represents absolute address following a branch
     pub addr_rel: u16,   
-    /// The amount of cycles remaining for the current instruction
-    pub cycles_remaining: u8,
+    /// Pointer read by the indirect addressing modes, or the address accessed before the page
+    /// crossing of indexed addressing was fixed
+    pub addr_ptr: u16,
+    /// The cycle of the current instruction that was executed last, 0 in between instructions
+    pub step: u8,
     /// The opcode that's currently being executed
     pub opcode: u8,
+    /// The hardware interrupt that's being serviced instead of an instruction
+    pub interrupt: Option<Interrupt>,
+    /// Whether an external device holds the IRQ line low, on top of the devices on the bus
+    pub irq_line: bool,
+    /// An NMI edge was detected and hasn't been serviced yet
+    pub nmi_pending: bool,
+    /// `nmi_pending` as polled at the end of the previous cycle
+    pub nmi_ready: bool,
+    /// The current cycle doesn't poll `nmi_pending`, set and cleared within the same cycle
+    nmi_poll_skipped: bool,
+    /// The IRQ line was asserted while interrupts were enabled at the end of the last cycle
+    pub irq_pending: bool,
+    /// `irq_pending` as polled at the end of the previous cycle
+    pub irq_ready: bool,
+    /// The number of cycles since power on
+    pub cycles: u64,
+    /// Whether the CPU is running or jammed
+    pub state: CpuState,
+    /// Where to log executed instructions, if anywhere
+    tracer: Option<Tracer>,
+    /// Why writing the trace failed, until it's taken
+    trace_error: Option<std::io::Error>,
+    /// Who to notify when the CPU jams, if anyone
+    jam_handler: Option<JamHandler>,
+}
+
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CPU {
+    /// Create a CPU connected to the NES memory map
     pub fn new() -> Self {
+        Self::with_bus(Bus::new())
+    }
+}
+
+impl<B: BusDevice> CPU<B> {
+    /// Create a CPU connected to an arbitrary memory system
+    pub fn with_bus(bus: B) -> Self {
         CPU { 
-            bus: Bus::new(),
+            bus,
+            variant: CpuVariant::default(),
             status: StatusFlags::empty(),
             a: 0,
             x: 0,
@@ -87,131 +186,296 @@ impl CPU {
             fetched: 0,
             addr_abs: 0,
             addr_rel: 0,
-            cycles_remaining: 0,
+            addr_ptr: 0,
+            step: 0,
             opcode: 0,
+            interrupt: None,
+            irq_line: false,
+            nmi_pending: false,
+            nmi_ready: false,
+            nmi_poll_skipped: false,
+            irq_pending: false,
+            irq_ready: false,
+            cycles: 0,
+            state: CpuState::Running,
+            tracer: None,
+            trace_error: None,
+            jam_handler: None,
         }
     }
 
-    pub fn read(&self, address: u16) -> u8 {
+    pub fn read(&mut self, address: u16) -> u8 {
         self.bus.read(address)
     }
 
+    /// Read from the bus without triggering any side effects
+    pub fn peek(&self, address: u16) -> u8 {
+        self.bus.peek(address)
+    }
+
     pub fn write(&mut self, address: u16, data: u8) {
         self.bus.write(address, data);
     }
 
-    /// Reset the CPU to its initial boot state
+    /// Push a byte onto the stack
+    pub fn push(&mut self, data: u8) {
+        self.write(STACK_BASE + self.sp as u16, data);
+        self.sp = self.sp.wrapping_sub(1);
+    }
+
+    /// Push a word onto the stack, high byte first
+    pub fn push_word(&mut self, data: u16) {
+        let [lo, hi] = data.to_le_bytes();
+        self.push(hi);
+        self.push(lo);
+    }
+
+    /// Pop a byte off the stack
+    pub fn pop(&mut self) -> u8 {
+        self.sp = self.sp.wrapping_add(1);
+        self.read(STACK_BASE + self.sp as u16)
+    }
+
+    /// Pop a word off the stack, low byte first
+    pub fn pop_word(&mut self) -> u16 {
+        let lo = self.pop();
+        let hi = self.pop();
+        u16::from_le_bytes([lo, hi])
+    }
+
+    /// Reset the CPU to its initial boot state. The reset sequence runs over the next 7 cycles,
+    /// after which the program counter is loaded from the reset vector.
     pub fn reset(&mut self) {
         self.a = 0;
         self.x = 0;
         self.y = 0;
 
-        self.sp = 0xFD;
-        self.status = StatusFlags::U;
-
-        // Locating set by the programmer pointing to the location of the 
-        // program counter on reset.
-        let lo = self.read(PC_POINTER);
-        let hi = self.read(PC_POINTER + 1);
-        self.pc = u16::from_le_bytes([lo, hi]);
+        // The reset sequence moves the stack pointer down three times, without writing to it,
+        // which leaves it at 0xFD.
+        self.sp = 0x00;
+        self.status = StatusFlags::U | StatusFlags::I;
 
         self.addr_rel = 0x0000;
         self.addr_abs = 0x0000;
+        self.addr_ptr = 0x0000;
         self.fetched = 0x00;
 
-        // Resets and interrupts actually consume cycles
-        self.cycles_remaining = 8;
+        // Resets and interrupts actually consume cycles, they run the same sequence as BRK
+        self.interrupt = Some(Interrupt::Reset);
+        self.step = 0;
+        self.nmi_pending = false;
+        self.nmi_ready = false;
+        self.irq_pending = false;
+        self.irq_ready = false;
+
+        // A reset is the only way out of a jam
+        self.state = CpuState::Running;
     }
 
-    fn fetch(&mut self) -> u8 {
-        let instr = Instruction::decode(self.opcode);
+    /// Call `handler` with the opcode and its location whenever the CPU jams. Passing `None`
+    /// removes the handler.
+    pub fn set_jam_handler(&mut self, handler: Option<Box<dyn FnMut(u8, u16)>>) {
+        self.jam_handler = handler.map(JamHandler);
+    }
 
-        // Use the absolute address to fetch from memory unless we're in implied
-        // addressing mode.
-        if instr.mode != AddressingMode::IMP {
-            self.fetched = self.read(self.addr_abs);
+    /// Lock up the CPU after executing the JAM `opcode` at `pc`.
+    pub(crate) fn jam(&mut self, opcode: u8, pc: u16) {
+        self.state = CpuState::Jammed { opcode, pc };
+        if let Some(JamHandler(handler)) = self.jam_handler.as_mut() {
+            handler(opcode, pc);
         }
+    }
 
+    /// The current instruction, as decoded by the emulated variant.
+    pub fn instruction(&self) -> &'static Instruction {
+        Instruction::decode_for(self.opcode, self.variant)
+    }
+
+    /// The operand of the current instruction, as read by its addressing mode. For implied
+    /// addressing this is the accumulator.
+    fn fetch(&mut self) -> u8 {
         self.fetched
     }
 
-    /// Simulates the passing of a single clock cycle
+    /// Simulates the passing of a single clock cycle, in which the CPU performs a single access
+    /// of the bus.
     pub fn clock(&mut self) {
-        // No more cycles are remaining in the currently executing instruction
-        if self.cycles_remaining == 0 {
-            // Set the next opcode to execute
-            self.opcode = self.read(self.pc);
-            // DEBUG
-            println!("{:#6X}:   {:?}, A: {:#04x}, X: {:#04x}, Y: {:#04x}, SP: {:#06x}", self.pc, Instruction::decode(self.opcode).mnemonic, self.a, self.x, self.y, self.sp);
-            self.pc = self.pc.wrapping_add(1);
-            
-            // Set how many clock cycles we need to execute
-            self.cycles_remaining = Instruction::decode(self.opcode).cycles;
-            
-
-            // Fetch operands with the correct addressing mode and execute the instruction
-            let more_cycles1 = (Instruction::decode(self.opcode).mode_exec)(self);
-            let more_cycles2 = (Instruction::decode(self.opcode).op_exec)(self);
-
-            // If the previous two actions indicated that they both require additional cycles
-            // we add those to the total need to complete for this instruction.
-            self.cycles_remaining += more_cycles1 & more_cycles2;
+        self.bus.cpu_clock(self.cycles);
+
+        // A jammed CPU doesn't do anything, not even service interrupts, but time goes on
+        if let CpuState::Jammed { .. } = self.state {
+            self.step = 0;
+            self.cycles += 1;
+            return;
         }
 
-        // Each call of the `clock` function, we decrement a single one of our remaining cycles
-        self.cycles_remaining -= 1;
-    }
-
-    /// Simulate an interrupt request signal 
-    pub fn irq(&mut self) {
-        // Only run the interrupt if the interrupt disable flag is not set
-        if !self.status.contains(StatusFlags::I) {
-            // On interrupt, we write data to the stack so we can resume out program later. First
-            // is the current program counter.
-            self.write(STACK_BASE + self.sp as u16, ((self.pc >> 8) & 0x00FF) as u8);
-            self.write(STACK_BASE + self.sp as u16 - 1, (self.pc & 0x00FF) as u8);
-            self.sp -= 2;
-
-            // Next we set the correct status flags and push those unto the stack as well
-            self.status.set(StatusFlags::B, false); // Set to 0 when pushing to the stack during IRQ/NMI, 1 during PHP/BRK
-            self.status.set(StatusFlags::U, true);  // Always set to 1 when pushed to the stack during IRQ
-            self.status.set(StatusFlags::I, true);  // Disable interrupts during an interrupt
-            self.write(STACK_BASE + self.sp as u16, self.status.bits);
-            self.sp -= 1;
-
-            // We look up the value of the interrupt handler we're supposed to execute at `IRQ_POINTER` and set the
-            // program counter there.
-            let lo = self.read(IRQ_POINTER);
-            let hi = self.read(IRQ_POINTER + 1);
-            self.pc = u16::from_be_bytes([lo, hi]);
-
-            // Resets and interrupts actually consume cycles
-            self.cycles_remaining = 7;
+        // A waiting CPU wakes up when an interrupt line is asserted, even if interrupts are disabled
+        if let CpuState::Waiting = self.state {
+            self.poll_interrupts();
+            if self.nmi_pending || self.irq_line || self.bus.irq() {
+                self.state = CpuState::Running;
+            }
+            self.cycles += 1;
+            return;
+        }
+
+        if self.step == 0 {
+            // Interrupts are serviced in between instructions, if they were polled before the last
+            // cycle of the instruction that just finished. The first instruction of an interrupt
+            // handler always runs before the next interrupt.
+            if self.interrupt.is_none() && self.opcode != 0x00 {
+                if self.nmi_ready {
+                    self.interrupt = Some(Interrupt::Nmi);
+                } else if self.irq_ready {
+                    self.interrupt = Some(Interrupt::Irq);
+                }
+            }
+
+            if self.interrupt.is_some() {
+                // The opcode is still fetched, but replaced by a BRK which runs the interrupt sequence
+                self.read(self.pc);
+                self.opcode = 0x00;
+            } else {
+                self.trace();
+
+                // Set the next opcode to execute
+                self.opcode = self.read(self.pc);
+                self.pc = self.pc.wrapping_add(1);
+            }
+
+            // Some NOPs of the 65C02 are done after the opcode fetch
+            self.step = if self.instruction().cycles == 1 { 0 } else { 1 };
+        } else {
+            self.step += 1;
+            if self.execute() {
+                self.step = 0;
+            }
+        }
+
+        self.poll_interrupts();
+        self.cycles += 1;
+    }
+
+    /// Sample the interrupt lines at the end of a cycle. The NMI line is edge triggered, so a
+    /// detected NMI stays pending until it's serviced. The IRQ line is level triggered, and only
+    /// counts while interrupts are enabled.
+    fn poll_interrupts(&mut self) {
+        self.nmi_ready = self.nmi_pending && !std::mem::take(&mut self.nmi_poll_skipped);
+        self.irq_ready = self.irq_pending;
+
+        if self.bus.nmi() {
+            self.nmi_pending = true;
+        }
+        self.irq_pending = (self.irq_line || self.bus.irq()) && !self.status.contains(StatusFlags::I);
+    }
+
+    /// Run the current cycle of the current instruction, returning true if it was the last one.
+    fn execute(&mut self) -> bool {
+        let instr = self.instruction();
+
+        match instr.mnemonic.access() {
+            // Instructions that use the stack have a sequence of their own
+            Access::Stack => cpu_instr::exec(self, instr.mnemonic) == 0,
+            // All others fetch their operands with the correct addressing mode, then execute
+            _ => cpu_addr::exec(self, instr.mode),
         }
     }
 
-    /// Simulate a non-maskable interrupt request signal. Cannot be stopped from ocurring.
+    /// Spend a cycle halted, e.g. while DMA has taken over the bus.
+    pub fn stall(&mut self) {
+        self.bus.cpu_clock(self.cycles);
+        self.cycles += 1;
+    }
+
+    /// Assert or release the interrupt request line. As long as it is asserted and interrupts
+    /// are enabled, an interrupt is serviced after the current instruction.
+    pub fn set_irq_line(&mut self, asserted: bool) {
+        self.irq_line = asserted;
+    }
+
+    /// Signal a non-maskable interrupt, as if the NMI line went low. Cannot be stopped from
+    /// ocurring, and is serviced after the current instruction.
     pub fn nmi(&mut self) {
-        // On interrupt, we write data to the stack so we can resume out program later. First
-        // is the current program counter.
-        self.write(STACK_BASE + self.sp as u16, ((self.pc >> 8) & 0x00FF) as u8);
-        self.write(STACK_BASE + self.sp as u16 - 1, (self.pc & 0x00FF) as u8);
-        self.sp -= 2;
-
-        // Next we set the correct status flags and push those unto the stack as well
-        self.status.set(StatusFlags::B, false); // Set to 0 when pushing to the stack during IRQ/NMI, 1 during PHP/BRK
-        self.status.set(StatusFlags::U, true);  // Always set to 1 when pushed to the stack during IRQ
-        self.status.set(StatusFlags::I, true);  // Disable interrupts during an interrupt
-        self.write(STACK_BASE + self.sp as u16, self.status.bits);
-        self.sp -= 1;
-
-        // We look up the value of the interrupt handler we're supposed to execute at `IRQ_POINTER` and set the
-        // program counter there.
-        let lo = self.read(NMI_POINTER);
-        let hi = self.read(NMI_POINTER + 1);
-        self.pc = u16::from_be_bytes([lo, hi]);
-
-        // Resets and interrupts actually consume cycles
-        self.cycles_remaining = 8;
-    }
-}
\ No newline at end of file
+        self.nmi_pending = true;
+    }
+}
+
+/// Saves the registers and the progress of the instruction or interrupt sequence that's being
+/// executed, so a state can be saved in the middle of an instruction. The variant isn't saved.
+impl<B: BusDevice + SaveState> SaveState for CPU<B> {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.status.bits());
+        writer.write_u8(self.a);
+        writer.write_u8(self.x);
+        writer.write_u8(self.y);
+        writer.write_u8(self.sp);
+        writer.write_u16(self.pc);
+
+        writer.write_u8(self.fetched);
+        writer.write_u16(self.addr_abs);
+        writer.write_u16(self.addr_rel);
+        writer.write_u16(self.addr_ptr);
+        writer.write_u8(self.step);
+        writer.write_u8(self.opcode);
+        writer.write_u8(match self.interrupt {
+            None => 0,
+            Some(Interrupt::Reset) => 1,
+            Some(Interrupt::Nmi) => 2,
+            Some(Interrupt::Irq) => 3,
+        });
+        writer.write_bool(self.irq_line);
+        writer.write_bool(self.nmi_pending);
+        writer.write_bool(self.nmi_ready);
+        writer.write_bool(self.irq_pending);
+        writer.write_bool(self.irq_ready);
+        writer.write_u64(self.cycles);
+        match self.state {
+            CpuState::Running => writer.write_u8(0),
+            CpuState::Waiting => writer.write_u8(1),
+            CpuState::Jammed { opcode, pc } => {
+                writer.write_u8(2);
+                writer.write_u8(opcode);
+                writer.write_u16(pc);
+            },
+        }
+
+        self.bus.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.status = StatusFlags::from_bits_truncate(reader.read_u8()?);
+        self.a = reader.read_u8()?;
+        self.x = reader.read_u8()?;
+        self.y = reader.read_u8()?;
+        self.sp = reader.read_u8()?;
+        self.pc = reader.read_u16()?;
+
+        self.fetched = reader.read_u8()?;
+        self.addr_abs = reader.read_u16()?;
+        self.addr_rel = reader.read_u16()?;
+        self.addr_ptr = reader.read_u16()?;
+        self.step = reader.read_u8()?;
+        self.opcode = reader.read_u8()?;
+        self.interrupt = match reader.read_u8()? {
+            0 => None,
+            1 => Some(Interrupt::Reset),
+            2 => Some(Interrupt::Nmi),
+            3 => Some(Interrupt::Irq),
+            _ => return Err(SaveStateError::Malformed("invalid interrupt")),
+        };
+        self.irq_line = reader.read_bool()?;
+        self.nmi_pending = reader.read_bool()?;
+        self.nmi_ready = reader.read_bool()?;
+        self.irq_pending = reader.read_bool()?;
+        self.irq_ready = reader.read_bool()?;
+        self.cycles = reader.read_u64()?;
+        self.state = match reader.read_u8()? {
+            0 => CpuState::Running,
+            1 => CpuState::Waiting,
+            2 => CpuState::Jammed { opcode: reader.read_u8()?, pc: reader.read_u16()? },
+            _ => return Err(SaveStateError::Malformed("invalid CPU state")),
+        };
+
+        self.bus.load_state(reader)
+    }
+}
@@ -0,0 +1,167 @@
+use std::{fmt, io::{self, Write}};
+use crate::bus::BusDevice;
+use super::{CPU, CpuVariant, instructions::{AddressingMode, Instruction, Mnemonic}};
+
+/// Output for the instruction trace of a CPU.
+pub struct Tracer(pub Box<dyn Write>);
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Tracer")
+    }
+}
+
+impl<B: BusDevice> CPU<B> {
+    /// Log every instruction to `output` right before it's executed, in the format of nestest.log.
+    /// Passing `None` stops tracing. Returns the previous output, which still has to be flushed.
+    pub fn set_tracer(&mut self, output: Option<Box<dyn Write>>) -> Option<Box<dyn Write>> {
+        std::mem::replace(&mut self.tracer, output.map(Tracer)).map(|Tracer(output)| output)
+    }
+
+    /// Take the error that made tracing stop, if writing to the output failed.
+    pub fn take_trace_error(&mut self) -> Option<io::Error> {
+        self.trace_error.take()
+    }
+
+    /// Write the trace line of the instruction about to be executed, if tracing is enabled.
+    pub(super) fn trace(&mut self) {
+        if self.tracer.is_none() {
+            return;
+        }
+
+        let line = self.trace_line();
+        if let Some(Tracer(output)) = self.tracer.as_mut() {
+            // Stop tracing once the output is gone, instead of failing on every instruction
+            if let Err(err) = writeln!(output, "{}", line) {
+                self.tracer = None;
+                self.trace_error = Some(err);
+            }
+        }
+    }
+
+    /// Format the instruction at the program counter and the state of the CPU as a line of
+    /// nestest.log, e.g.
+    ///
+    /// `C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7`
+    ///
+    /// Operands are resolved to the addresses and values they point to, read without side effects.
+    /// Ref: https://www.qmtpro.com/~nes/misc/nestest.log
+    pub fn trace_line(&self) -> String {
+        let opcode = self.peek(self.pc);
+        let instr = Instruction::decode_for(opcode, self.variant);
+        let length = operand_length(instr) + 1;
+
+        let bytes: Vec<String> = (0..length)
+            .map(|offset| format!("{:02X}", self.peek(self.pc.wrapping_add(offset))))
+            .collect();
+        let marker = if Instruction::is_official(opcode) { ' ' } else { '*' };
+        let (scanline, dot) = self.bus.ppu_position();
+
+        format!(
+            "{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+            self.pc,
+            bytes.join(" "),
+            marker,
+            format!("{} {}", trace_name(instr.mnemonic), self.trace_operand(instr)).trim_end(),
+            self.a,
+            self.x,
+            self.y,
+            self.status.bits(),
+            self.sp,
+            scanline,
+            dot,
+            self.cycles,
+        )
+    }
+
+    fn trace_operand(&self, instr: &Instruction) -> String {
+        let byte = self.peek(self.pc.wrapping_add(1));
+        let word = u16::from_le_bytes([byte, self.peek(self.pc.wrapping_add(2))]);
+        let peek_word = |lo: u16, hi: u16| u16::from_le_bytes([self.peek(lo), self.peek(hi)]);
+
+        match instr.mode {
+            AddressingMode::IMP if is_shift(instr.mnemonic) => "A".to_string(),
+            AddressingMode::IMP => String::new(),
+            AddressingMode::ACC => "A".to_string(),
+            AddressingMode::IMM => format!("#${:02X}", byte),
+            AddressingMode::ZP0 => format!("${:02X} = {:02X}", byte, self.peek(byte as u16)),
+            AddressingMode::ZPX => {
+                let address = byte.wrapping_add(self.x);
+                format!("${:02X},X @ {:02X} = {:02X}", byte, address, self.peek(address as u16))
+            },
+            AddressingMode::ZPY => {
+                let address = byte.wrapping_add(self.y);
+                format!("${:02X},Y @ {:02X} = {:02X}", byte, address, self.peek(address as u16))
+            },
+            AddressingMode::ABS if matches!(instr.mnemonic, Mnemonic::JMP | Mnemonic::JSR) => format!("${:04X}", word),
+            AddressingMode::ABS => format!("${:04X} = {:02X}", word, self.peek(word)),
+            AddressingMode::ABX => {
+                let address = word.wrapping_add(self.x as u16);
+                format!("${:04X},X @ {:04X} = {:02X}", word, address, self.peek(address))
+            },
+            AddressingMode::ABY => {
+                let address = word.wrapping_add(self.y as u16);
+                format!("${:04X},Y @ {:04X} = {:02X}", word, address, self.peek(address))
+            },
+            AddressingMode::IND => {
+                // The high byte of the pointer doesn't carry into the next page, except on the 65C02
+                let target = if self.variant == CpuVariant::Wdc65C02 {
+                    peek_word(word, word.wrapping_add(1))
+                } else {
+                    peek_word(word, (word & 0xFF00) | (word.wrapping_add(1) & 0x00FF))
+                };
+                format!("(${:04X}) = {:04X}", word, target)
+            },
+            AddressingMode::REL => {
+                let target = self.pc.wrapping_add(2).wrapping_add(byte as i8 as u16);
+                format!("${:04X}", target)
+            },
+            AddressingMode::IZX => {
+                let pointer = byte.wrapping_add(self.x);
+                let address = peek_word(pointer as u16, pointer.wrapping_add(1) as u16);
+                format!("(${:02X},X) @ {:02X} = {:04X} = {:02X}", byte, pointer, address, self.peek(address))
+            },
+            AddressingMode::IZY => {
+                let base = peek_word(byte as u16, byte.wrapping_add(1) as u16);
+                let address = base.wrapping_add(self.y as u16);
+                format!("(${:02X}),Y = {:04X} @ {:04X} = {:02X}", byte, base, address, self.peek(address))
+            },
+            AddressingMode::IZP => {
+                let address = peek_word(byte as u16, byte.wrapping_add(1) as u16);
+                format!("(${:02X}) = {:04X} = {:02X}", byte, address, self.peek(address))
+            },
+            AddressingMode::IAX => {
+                let pointer = word.wrapping_add(self.x as u16);
+                format!("(${:04X},X) = {:04X}", word, peek_word(pointer, pointer.wrapping_add(1)))
+            },
+            AddressingMode::ZPR => {
+                let offset = self.peek(self.pc.wrapping_add(2));
+                let target = self.pc.wrapping_add(3).wrapping_add(offset as i8 as u16);
+                format!("${:02X} = {:02X}, ${:04X}", byte, self.peek(byte as u16), target)
+            },
+        }
+    }
+}
+
+/// Number of operand bytes following the opcode.
+fn operand_length(instr: &Instruction) -> u16 {
+    match instr.mode {
+        AddressingMode::IMP | AddressingMode::ACC => 0,
+        AddressingMode::ABS | AddressingMode::ABX | AddressingMode::ABY | AddressingMode::IND |
+        AddressingMode::IAX | AddressingMode::ZPR => 2,
+        _ => 1,
+    }
+}
+
+/// The shifts and rotates operate on the accumulator when they have no operand.
+fn is_shift(mnemonic: Mnemonic) -> bool {
+    matches!(mnemonic, Mnemonic::ASL | Mnemonic::LSR | Mnemonic::ROL | Mnemonic::ROR)
+}
+
+/// Name of the mnemonic the way nestest.log spells it.
+fn trace_name(mnemonic: Mnemonic) -> String {
+    match mnemonic {
+        Mnemonic::ISC => "ISB".to_string(),
+        _ => format!("{:?}", mnemonic),
+    }
+}
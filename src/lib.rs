@@ -1,2 +1,15 @@
+pub mod apu;
 pub mod bus;
-pub mod cpu;
\ No newline at end of file
+pub mod cartridge;
+pub mod controller;
+pub mod cpu;
+pub mod mapper;
+pub mod movie;
+pub mod nes;
+pub mod palette;
+pub mod ppu;
+pub mod rom_info;
+pub mod rewind;
+pub mod save_state;
+pub mod screenshot;
+pub mod test_rom;
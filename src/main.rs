@@ -1,23 +1,379 @@
-use crate::cpu::{CPU, disassemble::Disassembler};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+use clap::{Args, Parser, Subcommand};
+use powerglove::{
+    cartridge::Cartridge,
+    cpu::{CpuState, disassemble::Disassembler},
+    movie::{Movie, MoviePlayer},
+    nes::Nes,
+    palette::Palette,
+    rom_info::RomInfo,
+    screenshot::ImageFormat,
+    test_rom::{TestOutcome, TestRunner},
+};
 
-mod bus;
-mod cpu;
+/// Exit code for a test ROM that failed, a desynced movie, a jammed CPU or a ROM with anomalies
+/// in strict mode.
+const EXIT_FAILURE: u8 = 1;
+/// Exit code for a ROM or other input file that couldn't be loaded, or output that couldn't be
+/// written. Invalid arguments exit with 2, as reported by clap.
+const EXIT_IO_ERROR: u8 = 3;
+/// Exit code for a test ROM that didn't report a result in time.
+const EXIT_TIMEOUT: u8 = 4;
 
-fn main() {
-    let mut cpu = CPU::new();
-    
-    let prog = "A2 00 8E 00 00 A2 40 8E 01 00 A9 00 8D 10 00 8D 11 00 A2 08 38 AD 00 00 E9 40 A8 AD 11 00 ED 10 00 90 06 8C 00 00 8D 11 00 2E 10 00 0E 01 00 2E 00 00 2E 11 00 0E 01 00 2E 00 00 2E 11 00 CA D0 D3";
-    let prog_array: Vec<_> = prog.split_whitespace().collect();
-    for (index, prog_n) in prog_array.iter().enumerate() {
-        cpu.bus.ram[0x8000 + index] = u8::from_str_radix(prog_n, 16).unwrap();
+/// Frames to run when neither a frame or cycle limit nor a movie is given, 10 seconds on NTSC.
+const DEFAULT_RUN_FRAMES: u64 = 600;
+/// Instructions to trace when no limit is given.
+const DEFAULT_TRACE_INSTRUCTIONS: u64 = 10_000;
+
+/// Run NES ROMs headless from the command line.
+#[derive(Debug, Parser)]
+#[command(name = "powerglove", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run a ROM without any video or audio output
+    Run(RunArgs),
+    /// Disassemble part of the CPU address space with the ROM inserted
+    Disasm(DisasmArgs),
+    /// Log every executed instruction in the format of nestest.log
+    Trace(TraceArgs),
+    /// Print a ROM's header, hashes and anything suspicious about it
+    Info(InfoArgs),
+}
+
+#[derive(Debug, Args)]
+struct RunArgs {
+    /// The iNES or NES 2.0 file to run
+    rom: PathBuf,
+    /// Stop after this many frames [default: the movie's length, or 600, unless --cycles is given]
+    #[arg(long)]
+    frames: Option<u64>,
+    /// Stop at the end of the frame in which this many CPU cycles have passed
+    #[arg(long)]
+    cycles: Option<u64>,
+    /// Treat the ROM as a test that reports its result at $6000, and exit with its outcome
+    #[arg(long, conflicts_with_all = ["movie", "cycles"])]
+    test_rom: bool,
+    /// Play back a movie, in the native format or as an FCEUX .fm2 file
+    #[arg(long)]
+    movie: Option<PathBuf>,
+    /// Write a save state of the console when it stops
+    #[arg(long)]
+    save_state: Option<PathBuf>,
+    /// Save the frame as a .png or .ppm image when the console stops
+    #[arg(long, value_parser = parse_image_path)]
+    screenshot: Option<PathBuf>,
+    /// Take the screenshot at the end of this frame instead, and stop there unless --frames is given
+    #[arg(
+        long,
+        requires = "screenshot",
+        conflicts_with = "test_rom",
+        value_parser = clap::value_parser!(u64).range(1..),
+    )]
+    screenshot_at: Option<u64>,
+    /// Take a screenshot every this many frames, with the frame number added to the file name
+    #[arg(
+        long,
+        requires = "screenshot",
+        conflicts_with_all = ["test_rom", "screenshot_at"],
+        value_parser = clap::value_parser!(u64).range(1..),
+    )]
+    screenshot_every: Option<u64>,
+    /// Color screenshots with this .pal file instead of the built-in 2C02 palette
+    #[arg(long, requires = "screenshot")]
+    palette: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct DisasmArgs {
+    /// The iNES or NES 2.0 file to disassemble
+    rom: PathBuf,
+    /// First address to disassemble
+    #[arg(long, default_value = "$8000", value_parser = parse_address)]
+    start: u16,
+    /// Last address to disassemble
+    #[arg(long, default_value = "$FFFF", value_parser = parse_address)]
+    end: u16,
+    /// Write the disassembly to a file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct TraceArgs {
+    /// The iNES or NES 2.0 file to trace
+    rom: PathBuf,
+    /// Number of instructions to trace
+    #[arg(long, default_value_t = DEFAULT_TRACE_INSTRUCTIONS)]
+    instructions: u64,
+    /// Start executing here instead of at the reset vector, e.g. $C000 for nestest's automated mode
+    #[arg(long, value_parser = parse_address)]
+    pc: Option<u16>,
+    /// Write the trace to a file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct InfoArgs {
+    /// The iNES or NES 2.0 file to inspect
+    rom: PathBuf,
+    /// Exit with a failure if anything about the ROM looks wrong, or its mapper isn't supported
+    #[arg(long)]
+    strict: bool,
+}
+
+/// Parse an address given in hex as `$C000` or `0xC000`, or in decimal.
+fn parse_address(value: &str) -> Result<u16, String> {
+    let parsed = match value.strip_prefix('$').or_else(|| value.strip_prefix("0x")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|_| format!("`{}` is not an address between $0000 and $FFFF", value))
+}
+
+/// Accept only paths that a screenshot can be saved to.
+fn parse_image_path(value: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(value);
+    match ImageFormat::from_path(&path) {
+        Some(_) => Ok(path),
+        None => Err(format!("`{}` does not end in .png or .ppm", value)),
+    }
+}
+
+/// Insert the frame number before the extension, e.g. `shot.png` becomes `shot-000060.png`.
+fn numbered_path(path: &Path, frame: u64) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}-{:06}.{}", stem, frame, extension))
+}
+
+/// An error that ends the program, with the exit code to report it with.
+struct Failure {
+    code: u8,
+    message: String,
+}
+
+impl Failure {
+    fn new(code: u8, message: impl Into<String>) -> Self {
+        Failure { code, message: message.into() }
+    }
+
+    fn io(path: &Path, err: impl std::fmt::Display) -> Self {
+        Failure::new(EXIT_IO_ERROR, format!("{}: {}", path.display(), err))
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Run(args) => run(args),
+        Command::Disasm(args) => disasm(args),
+        Command::Trace(args) => trace(args),
+        Command::Info(args) => info(args),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            eprintln!("powerglove: {}", failure.message);
+            ExitCode::from(failure.code)
+        },
     }
+}
+
+fn load_nes(path: &Path) -> Result<Nes, Failure> {
+    Cartridge::load(path)
+        .and_then(Nes::new)
+        .map_err(|err| Failure::io(path, err))
+}
+
+/// Open `path` for writing, or stdout if there's no path.
+fn output(path: Option<&Path>) -> Result<Box<dyn Write>, Failure> {
+    match path {
+        Some(path) => Ok(Box::new(BufWriter::new(File::create(path).map_err(|err| Failure::io(path, err))?))),
+        None => Ok(Box::new(io::stdout())),
+    }
+}
+
+fn load_palette(path: Option<&Path>) -> Result<Palette, Failure> {
+    match path {
+        Some(path) => Palette::load(path).map_err(|err| Failure::io(path, err)),
+        None => Ok(Palette::default()),
+    }
+}
 
-    cpu.bus.ram[0xFFFC] = 0x00;
-    cpu.bus.ram[0xFFFD] = 0x80;
+fn save_screenshot(nes: &Nes, path: &Path, palette: &Palette) -> Result<(), Failure> {
+    nes.save_screenshot(path, palette).map_err(|err| Failure::io(path, err))
+}
+
+fn run(args: RunArgs) -> Result<(), Failure> {
+    let palette = load_palette(args.palette.as_deref())?;
+    if args.test_rom {
+        return run_test_rom(&args, &palette);
+    }
+
+    let mut nes = load_nes(&args.rom)?;
+    let mut player = match &args.movie {
+        Some(path) => {
+            let movie = if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("fm2")) {
+                Movie::load_fm2(path)
+            } else {
+                Movie::load(path)
+            };
+            let movie = movie.map_err(|err| Failure::io(path, err))?;
+            Some(MoviePlayer::new(movie, &mut nes).map_err(|err| Failure::new(EXIT_FAILURE, err.to_string()))?)
+        },
+        None => None,
+    };
+
+    // A cycle limit replaces the default frame limits
+    let frames = match (args.frames.or(args.screenshot_at), args.cycles) {
+        (Some(frames), _) => frames,
+        (None, Some(_)) => u64::MAX,
+        (None, None) => player.as_ref().map_or(DEFAULT_RUN_FRAMES, |player| player.movie().frames.len() as u64),
+    };
+    let cycles = args.cycles.unwrap_or(u64::MAX);
+
+    let mut result = Ok(());
+    let mut screenshot_taken = false;
+    for frame in 1..=frames {
+        if nes.cycles() >= cycles {
+            break;
+        }
+        if let CpuState::Jammed { opcode, pc } = nes.cpu.state {
+            result = Err(Failure::new(EXIT_FAILURE, format!("CPU jammed on opcode ${:02X} at ${:04X}", opcode, pc)));
+            break;
+        }
+
+        match player.as_mut() {
+            Some(player) if !player.is_finished() => {
+                if let Err(err) = player.run_frame(&mut nes) {
+                    result = Err(Failure::new(EXIT_FAILURE, err.to_string()));
+                    break;
+                }
+            },
+            _ => nes.run_frame(),
+        }
+
+        if let Some(path) = &args.screenshot {
+            if args.screenshot_at == Some(frame) {
+                save_screenshot(&nes, path, &palette)?;
+                screenshot_taken = true;
+            } else if args.screenshot_every.is_some_and(|every| frame.is_multiple_of(every)) {
+                save_screenshot(&nes, &numbered_path(path, frame), &palette)?;
+            }
+        }
+    }
+
+    println!("Ran {} frames, {} CPU cycles", nes.frame_count(), nes.cycles());
+
+    if let Some(path) = &args.save_state {
+        std::fs::write(path, nes.save_state()).map_err(|err| Failure::io(path, err))?;
+    }
+    if let Some(path) = &args.screenshot {
+        if let Some(frame) = args.screenshot_at {
+            if !screenshot_taken && result.is_ok() {
+                result = Err(Failure::new(EXIT_FAILURE, format!("stopped before frame {} could be captured", frame)));
+            }
+        } else if args.screenshot_every.is_none() {
+            save_screenshot(&nes, path, &palette)?;
+        }
+    }
+    result
+}
+
+fn run_test_rom(args: &RunArgs, palette: &Palette) -> Result<(), Failure> {
+    let mut runner = TestRunner::load(&args.rom).map_err(|err| Failure::io(&args.rom, err))?;
+    if let Some(frames) = args.frames {
+        runner.set_max_frames(frames);
+    }
+
+    let report = runner.run();
+    let message = report.message.trim_end();
+    if !message.is_empty() {
+        println!("{}", message);
+    }
+
+    if let Some(path) = &args.save_state {
+        std::fs::write(path, runner.nes.save_state()).map_err(|err| Failure::io(path, err))?;
+    }
+    if let Some(path) = &args.screenshot {
+        save_screenshot(&runner.nes, path, palette)?;
+    }
+
+    match report.outcome {
+        TestOutcome::Passed => {
+            println!("Passed after {} frames", report.frames);
+            Ok(())
+        },
+        TestOutcome::Failed(code) => Err(Failure::new(EXIT_FAILURE, format!("test failed with code {}", code))),
+        TestOutcome::TimedOut => {
+            Err(Failure::new(EXIT_TIMEOUT, format!("test didn't finish within {} frames", report.frames)))
+        },
+    }
+}
+
+fn disasm(args: DisasmArgs) -> Result<(), Failure> {
+    let nes = load_nes(&args.rom)?;
+    let mut output = output(args.output.as_deref())?;
+    let write_error = |err| Failure::io(args.output.as_deref().unwrap_or(Path::new("stdout")), err);
+
+    for (_, line) in Disassembler::for_range(&nes.cpu, args.start, args.end) {
+        writeln!(output, "{}", line).map_err(write_error)?;
+    }
+    output.flush().map_err(write_error)
+}
+
+fn trace(args: TraceArgs) -> Result<(), Failure> {
+    let mut nes = load_nes(&args.rom)?;
+
+    // Let the reset sequence finish, so the trace starts at the first instruction
+    nes.clock();
+    while nes.cpu.step > 0 {
+        nes.clock();
+    }
+    if let Some(pc) = args.pc {
+        nes.cpu.pc = pc;
+    }
+
+    nes.cpu.set_tracer(Some(output(args.output.as_deref())?));
+    let write_failure = |err: io::Error| Failure::new(EXIT_IO_ERROR, format!("could not write the trace: {}", err));
+    let mut result = Ok(());
+    for _ in 0..args.instructions {
+        if let CpuState::Jammed { opcode, pc } = nes.cpu.state {
+            result = Err(Failure::new(EXIT_FAILURE, format!("CPU jammed on opcode ${:02X} at ${:04X}", opcode, pc)));
+            break;
+        }
+        nes.step_instruction();
+        if let Some(err) = nes.cpu.take_trace_error() {
+            return Err(write_failure(err));
+        }
+    }
+
+    // Flush explicitly, dropping the output would ignore any error
+    if let Some(mut output) = nes.cpu.set_tracer(None) {
+        output.flush().map_err(write_failure)?;
+    }
+    result
+}
 
-    let code_map = Disassembler::for_range(&cpu, 0x8000, 0xFFFF);
+fn info(args: InfoArgs) -> Result<(), Failure> {
+    let info = RomInfo::load(&args.rom).map_err(|err| Failure::io(&args.rom, err))?;
+    println!("{}", info);
 
-    for (_, op_str) in code_map {
-        println!("{}", op_str);
+    if args.strict && !info.anomalies.is_empty() {
+        return Err(Failure::new(EXIT_FAILURE, format!("{} header anomalies found", info.anomalies.len())));
     }
+    Ok(())
 }
@@ -0,0 +1,71 @@
+use crate::{cartridge::{Cartridge, Mirroring}, save_state::{SaveState, SaveStateError, StateReader, StateWriter}};
+use super::{Mapper, load_mirroring, save_mirroring};
+
+/// Mapper 7, switches a 32 KiB PRG-ROM bank into $8000-$FFFF and selects which page of VRAM
+/// is used for single screen mirroring. CHR is a fixed 8 KiB of RAM.
+/// Ref: https://www.nesdev.org/wiki/AxROM
+#[derive(Debug)]
+pub struct AxRom {
+    cartridge: Cartridge,
+    /// The PRG-ROM bank mapped at $8000-$FFFF
+    prg_bank: u8,
+    /// The nametable used for all four nametable slots
+    mirroring: Mirroring,
+}
+
+impl AxRom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        AxRom { cartridge, prg_bank: 0, mirroring: Mirroring::SingleScreenLower }
+    }
+}
+
+impl Mapper for AxRom {
+    fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn cpu_read(&self, address: u16) -> Option<u8> {
+        match address {
+            0x8000..=0xFFFF => Some(self.cartridge.read_prg_rom(self.prg_bank as usize, 0x8000, address)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        if let 0x8000..=0xFFFF = address {
+            self.prg_bank = data & 0b0111;
+            self.mirroring = if data & 0b1_0000 != 0 {
+                Mirroring::SingleScreenUpper
+            } else {
+                Mirroring::SingleScreenLower
+            };
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.cartridge.read_chr(0, 0x2000, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.cartridge.write_chr(0, 0x2000, address, data);
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+impl SaveState for AxRom {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.cartridge.save_state(writer);
+        writer.write_u8(self.prg_bank);
+        save_mirroring(writer, self.mirroring);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.cartridge.load_state(reader)?;
+        self.prg_bank = reader.read_u8()?;
+        self.mirroring = load_mirroring(reader)?;
+        Ok(())
+    }
+}
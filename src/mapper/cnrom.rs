@@ -0,0 +1,60 @@
+use crate::{cartridge::Cartridge, save_state::{SaveState, SaveStateError, StateReader, StateWriter}};
+use super::Mapper;
+
+/// Mapper 3, switches an 8 KiB CHR-ROM bank into the pattern tables. PRG-ROM is fixed like on NROM.
+/// Ref: https://www.nesdev.org/wiki/CNROM
+#[derive(Debug)]
+pub struct CnRom {
+    cartridge: Cartridge,
+    /// The CHR bank mapped at PPU $0000-$1FFF
+    chr_bank: u8,
+}
+
+impl CnRom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        CnRom { cartridge, chr_bank: 0 }
+    }
+}
+
+impl Mapper for CnRom {
+    fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn cpu_read(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => self.cartridge.read_prg_ram(address),
+            0x8000..=0xFFFF => Some(self.cartridge.read_prg_rom(0, 0x8000, address)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF => self.cartridge.write_prg_ram(address, data),
+            0x8000..=0xFFFF => self.chr_bank = data,
+            _ => {},
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.cartridge.read_chr(self.chr_bank as usize, 0x2000, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.cartridge.write_chr(self.chr_bank as usize, 0x2000, address, data);
+    }
+}
+
+impl SaveState for CnRom {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.cartridge.save_state(writer);
+        writer.write_u8(self.chr_bank);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.cartridge.load_state(reader)?;
+        self.chr_bank = reader.read_u8()?;
+        Ok(())
+    }
+}
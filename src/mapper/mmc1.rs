@@ -0,0 +1,192 @@
+use crate::{cartridge::{Cartridge, Mirroring}, save_state::{SaveState, SaveStateError, StateReader, StateWriter}};
+use super::Mapper;
+
+/// Mapper 1, Nintendo's MMC1. Its registers are loaded serially through a 5-bit shift register,
+/// one bit per write. It can switch PRG-ROM in 16 or 32 KiB banks and CHR in 4 or 8 KiB banks,
+/// and controls the nametable mirroring.
+/// Ref: https://www.nesdev.org/wiki/MMC1
+#[derive(Debug)]
+pub struct Mmc1 {
+    cartridge: Cartridge,
+    /// Bits written so far, shifted in from the top
+    shift: u8,
+    /// Number of bits written into the shift register
+    shift_count: u8,
+    /// Mirroring, PRG-ROM and CHR bank mode ($8000-$9FFF)
+    control: u8,
+    /// CHR bank for PPU $0000, or for the entire pattern table in 8 KiB mode ($A000-$BFFF)
+    chr_bank_0: u8,
+    /// CHR bank for PPU $1000 in 4 KiB mode ($C000-$DFFF)
+    chr_bank_1: u8,
+    /// PRG-ROM bank and PRG-RAM enable ($E000-$FFFF)
+    prg_bank: u8,
+    /// The serial port was written to during the current CPU cycle
+    written: bool,
+    /// The serial port was written to during the previous CPU cycle, so writes are ignored
+    ignore_writes: bool,
+}
+
+impl Mmc1 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Mmc1 {
+            cartridge,
+            shift: 0,
+            shift_count: 0,
+            // The last PRG-ROM bank is fixed at $C000 on power-up
+            control: 0x0C,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            written: false,
+            ignore_writes: false,
+        }
+    }
+
+    /// Boards with 512 KiB of PRG-ROM (SUROM) use the upper CHR bank bit to select which 256 KiB
+    /// half of the PRG-ROM is used.
+    fn prg_outer_bank(&self) -> usize {
+        if self.cartridge.prg_rom.len() > 0x40000 {
+            self.chr_bank_0 as usize & 0x10
+        } else {
+            0
+        }
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.prg_bank & 0x10 == 0
+    }
+
+    fn write_register(&mut self, address: u16, data: u8) {
+        match address {
+            0x8000..=0x9FFF => self.control = data,
+            0xA000..=0xBFFF => self.chr_bank_0 = data,
+            0xC000..=0xDFFF => self.chr_bank_1 = data,
+            _ => self.prg_bank = data,
+        }
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn cpu_read(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.cartridge.read_prg_ram(address),
+            0x8000..=0xFFFF => {
+                let outer = self.prg_outer_bank();
+                let bank = self.prg_bank as usize & 0x0F;
+                let last = (self.cartridge.prg_rom.len().min(0x40000) / 0x4000).saturating_sub(1);
+
+                let bank = match ((self.control >> 2) & 0b11, address) {
+                    // 32 KiB mode, ignoring the lowest bit of the bank number
+                    (0 | 1, _) => (bank & !1) | ((address as usize >> 14) & 1),
+                    // First bank fixed at $8000, switch the bank at $C000
+                    (2, 0x8000..=0xBFFF) => 0,
+                    (2, _) => bank,
+                    // Switch the bank at $8000, last bank fixed at $C000
+                    (_, 0x8000..=0xBFFF) => bank,
+                    (_, _) => last,
+                };
+
+                Some(self.cartridge.read_prg_rom(outer + bank, 0x4000, address))
+            },
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => self.cartridge.write_prg_ram(address, data),
+            0x8000..=0xFFFF => {
+                // Only the first of two writes on consecutive cycles is seen, e.g. the dummy write
+                // of a read-modify-write instruction.
+                self.written = true;
+                if self.ignore_writes {
+                    return;
+                }
+
+                // Writing a value with bit 7 set resets the shift register
+                if data & 0x80 != 0 {
+                    self.shift = 0;
+                    self.shift_count = 0;
+                    self.control |= 0x0C;
+                    return;
+                }
+
+                self.shift = (self.shift >> 1) | ((data & 1) << 4);
+                self.shift_count += 1;
+
+                // On the fifth write, the address decides which register gets loaded
+                if self.shift_count == 5 {
+                    self.write_register(address, self.shift);
+                    self.shift = 0;
+                    self.shift_count = 0;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn cpu_clock(&mut self) {
+        self.ignore_writes = self.written;
+        self.written = false;
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        if self.control & 0x10 == 0 {
+            self.cartridge.read_chr((self.chr_bank_0 >> 1) as usize, 0x2000, address)
+        } else if address < 0x1000 {
+            self.cartridge.read_chr(self.chr_bank_0 as usize, 0x1000, address)
+        } else {
+            self.cartridge.read_chr(self.chr_bank_1 as usize, 0x1000, address)
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        if self.control & 0x10 == 0 {
+            self.cartridge.write_chr((self.chr_bank_0 >> 1) as usize, 0x2000, address, data);
+        } else if address < 0x1000 {
+            self.cartridge.write_chr(self.chr_bank_0 as usize, 0x1000, address, data);
+        } else {
+            self.cartridge.write_chr(self.chr_bank_1 as usize, 0x1000, address, data);
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+}
+
+impl SaveState for Mmc1 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.cartridge.save_state(writer);
+        writer.write_u8(self.shift);
+        writer.write_u8(self.shift_count);
+        writer.write_u8(self.control);
+        writer.write_u8(self.chr_bank_0);
+        writer.write_u8(self.chr_bank_1);
+        writer.write_u8(self.prg_bank);
+        writer.write_bool(self.written);
+        writer.write_bool(self.ignore_writes);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.cartridge.load_state(reader)?;
+        self.shift = reader.read_u8()?;
+        self.shift_count = reader.read_u8()?;
+        self.control = reader.read_u8()?;
+        self.chr_bank_0 = reader.read_u8()?;
+        self.chr_bank_1 = reader.read_u8()?;
+        self.prg_bank = reader.read_u8()?;
+        self.written = reader.read_bool()?;
+        self.ignore_writes = reader.read_bool()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{cartridge::{Cartridge, Mirroring}, save_state::{SaveState, SaveStateError, StateReader, StateWriter}};
+use super::{Mapper, load_mirroring, save_mirroring};
+
+/// Number of PPU dots A12 needs to stay low before a rising edge clocks the IRQ counter. The real
+/// chip filters A12 by counting falling edges of M2, of which there's one every 3 dots.
+const A12_FILTER_DOTS: u32 = 10;
+/// Size of the internal PRG-RAM of the MMC6.
+const MMC6_RAM_SIZE: usize = 1024;
+
+/// Mapper 4, Nintendo's MMC3 and its MMC6 variant. Switches PRG-ROM in 8 KiB banks and CHR in 1 and
+/// 2 KiB banks, and generates IRQs on specific scanlines by counting rising edges of the PPU's A12
+/// address line, which happens once per scanline when the background and sprites use different
+/// pattern tables.
+/// Ref: https://www.nesdev.org/wiki/MMC3
+#[derive(Debug)]
+pub struct Mmc3 {
+    cartridge: Cartridge,
+    /// The chip is an MMC6, which comes with its own 1 KiB of PRG-RAM
+    mmc6: bool,
+    /// Internal PRG-RAM of the MMC6
+    mmc6_ram: Vec<u8>,
+    /// Bank register to update on the next write to $8001, and the PRG/CHR bank modes ($8000)
+    bank_select: u8,
+    /// Bank registers R0-R7
+    registers: [u8; 8],
+    mirroring: Mirroring,
+    /// PRG-RAM enable and write protection ($A001)
+    prg_ram_protect: u8,
+    /// Value the IRQ counter gets reloaded with ($C000)
+    irq_latch: u8,
+    irq_counter: u8,
+    /// Reload the IRQ counter on the next clock ($C001)
+    irq_reload: bool,
+    /// IRQs are generated when the counter hits 0 ($E000/$E001)
+    irq_enabled: bool,
+    /// The IRQ line is being asserted
+    irq_pending: bool,
+    /// Number of dots the PPU's A12 line has been low
+    a12_low_dots: u32,
+}
+
+impl Mmc3 {
+    pub fn new(cartridge: Cartridge) -> Self {
+        let mmc6 = cartridge.header.submapper == 1;
+        let mirroring = cartridge.header.mirroring;
+
+        Mmc3 {
+            cartridge,
+            mmc6,
+            mmc6_ram: if mmc6 { vec![0x0; MMC6_RAM_SIZE] } else { Vec::new() },
+            bank_select: 0,
+            registers: [0, 2, 4, 5, 6, 7, 0, 1],
+            mirroring,
+            prg_ram_protect: 0,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            a12_low_dots: 0,
+        }
+    }
+
+    /// Map a CPU address in $8000-$FFFF to an 8 KiB PRG-ROM bank.
+    fn prg_bank(&self, address: u16) -> usize {
+        let second_last = (self.cartridge.prg_rom.len() / 0x2000).saturating_sub(2);
+        let swapped = self.bank_select & 0x40 != 0;
+
+        match (address, swapped) {
+            (0x8000..=0x9FFF, false) | (0xC000..=0xDFFF, true) => (self.registers[6] & 0x3F) as usize,
+            (0x8000..=0x9FFF, true) | (0xC000..=0xDFFF, false) => second_last,
+            (0xA000..=0xBFFF, _) => (self.registers[7] & 0x3F) as usize,
+            _ => second_last + 1,
+        }
+    }
+
+    /// Map a PPU address in $0000-$1FFF to a 1 KiB CHR bank.
+    fn chr_bank(&self, address: u16) -> usize {
+        // With A12 inversion, the 2 KiB banks are mapped to $1000 instead of $0000
+        let address = if self.bank_select & 0x80 != 0 { address ^ 0x1000 } else { address };
+
+        match address {
+            0x0000..=0x07FF => (self.registers[0] & 0xFE) as usize + (address as usize >> 10 & 1),
+            0x0800..=0x0FFF => (self.registers[1] & 0xFE) as usize + (address as usize >> 10 & 1),
+            _ => self.registers[2 + ((address as usize - 0x1000) >> 10)] as usize,
+        }
+    }
+
+    /// Clock the scanline counter, raising an IRQ when it reaches 0.
+    fn clock_irq_counter(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+
+    /// The MMC6 splits its PRG-RAM into two 512 byte halves, each with its own read and write enable.
+    /// Writes are only possible if the half is readable as well.
+    fn mmc6_ram_access(&self, address: u16) -> (bool, bool) {
+        let enabled = self.bank_select & 0x20 != 0;
+        let (read_bit, write_bit) = if address & 0x0200 != 0 { (0x80, 0x40) } else { (0x20, 0x10) };
+        let read = enabled && self.prg_ram_protect & read_bit != 0;
+        let write = read && self.prg_ram_protect & write_bit != 0;
+        (read, write)
+    }
+
+    fn mmc6_read(&self, address: u16) -> Option<u8> {
+        let (read_lo, _) = self.mmc6_ram_access(0x7000);
+        let (read_hi, _) = self.mmc6_ram_access(0x7200);
+        let (read, _) = self.mmc6_ram_access(address);
+
+        match (read_lo || read_hi, read) {
+            // Neither half is readable, so nothing drives the bus
+            (false, _) => None,
+            (true, false) => Some(0x0),
+            (true, true) => Some(self.mmc6_ram[address as usize & (MMC6_RAM_SIZE - 1)]),
+        }
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn cpu_read(&self, address: u16) -> Option<u8> {
+        match address {
+            0x7000..=0x7FFF if self.mmc6 => self.mmc6_read(address),
+            0x6000..=0x7FFF if !self.mmc6 && self.prg_ram_protect & 0x80 != 0 => {
+                self.cartridge.read_prg_ram(address)
+            },
+            0x8000..=0xFFFF => Some(self.cartridge.read_prg_rom(self.prg_bank(address), 0x2000, address)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match (address, address & 1) {
+            (0x7000..=0x7FFF, _) if self.mmc6 && self.mmc6_ram_access(address).1 => {
+                self.mmc6_ram[address as usize & (MMC6_RAM_SIZE - 1)] = data;
+            },
+            // PRG-RAM has to be enabled and not write protected
+            (0x6000..=0x7FFF, _) if !self.mmc6 && self.prg_ram_protect & 0xC0 == 0x80 => {
+                self.cartridge.write_prg_ram(address, data);
+            },
+            (0x8000..=0x9FFF, 0) => self.bank_select = data,
+            (0x8000..=0x9FFF, _) => self.registers[(self.bank_select & 0b111) as usize] = data,
+            (0xA000..=0xBFFF, 0) => {
+                // Boards wired for four-screen VRAM ignore the mirroring register
+                self.mirroring = match (self.mirroring, data & 1) {
+                    (Mirroring::FourScreen, _) => Mirroring::FourScreen,
+                    (_, 0) => Mirroring::Vertical,
+                    (_, _) => Mirroring::Horizontal,
+                };
+            },
+            (0xA000..=0xBFFF, _) => self.prg_ram_protect = data,
+            (0xC000..=0xDFFF, 0) => self.irq_latch = data,
+            (0xC000..=0xDFFF, _) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            },
+            (0xE000..=0xFFFF, 0) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            },
+            (0xE000..=0xFFFF, _) => self.irq_enabled = true,
+            _ => {},
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.cartridge.read_chr(self.chr_bank(address), 0x0400, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.cartridge.write_chr(self.chr_bank(address), 0x0400, address, data);
+    }
+
+    fn ppu_clock(&mut self, address: u16) {
+        if address & 0x1000 == 0 {
+            self.a12_low_dots = self.a12_low_dots.saturating_add(1);
+        } else {
+            // Only count rising edges after A12 has been low for a while, this filters out
+            // the short pulses caused by fetching sprite patterns from $1000.
+            if self.a12_low_dots >= A12_FILTER_DOTS {
+                self.clock_irq_counter();
+            }
+            self.a12_low_dots = 0;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+}
+
+impl SaveState for Mmc3 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.cartridge.save_state(writer);
+        writer.write_vec(&self.mmc6_ram);
+        writer.write_u8(self.bank_select);
+        writer.write_bytes(&self.registers);
+        save_mirroring(writer, self.mirroring);
+        writer.write_u8(self.prg_ram_protect);
+        writer.write_u8(self.irq_latch);
+        writer.write_u8(self.irq_counter);
+        writer.write_bool(self.irq_reload);
+        writer.write_bool(self.irq_enabled);
+        writer.write_bool(self.irq_pending);
+        writer.write_u32(self.a12_low_dots);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.cartridge.load_state(reader)?;
+        reader.read_vec_into(&mut self.mmc6_ram)?;
+        self.bank_select = reader.read_u8()?;
+        reader.read_bytes(&mut self.registers)?;
+        self.mirroring = load_mirroring(reader)?;
+        self.prg_ram_protect = reader.read_u8()?;
+        self.irq_latch = reader.read_u8()?;
+        self.irq_counter = reader.read_u8()?;
+        self.irq_reload = reader.read_bool()?;
+        self.irq_enabled = reader.read_bool()?;
+        self.irq_pending = reader.read_bool()?;
+        self.a12_low_dots = reader.read_u32()?;
+        Ok(())
+    }
+}
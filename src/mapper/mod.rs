@@ -0,0 +1,126 @@
+pub mod axrom;
+pub mod cnrom;
+pub mod mmc1;
+pub mod mmc3;
+pub mod nrom;
+pub mod uxrom;
+
+use std::fmt::Debug;
+use crate::{cartridge::{Cartridge, CartridgeError, Mirroring}, save_state::{SaveState, SaveStateError, StateReader, StateWriter}};
+use self::{axrom::AxRom, cnrom::CnRom, mmc1::Mmc1, mmc3::Mmc3, nrom::Nrom, uxrom::UxRom};
+
+/// The circuitry on a cartridge board that decides how its memory shows up in the address spaces of
+/// the CPU and PPU. Most boards support some form of bank switching to work around the limited space
+/// available for each.
+/// Ref: https://www.nesdev.org/wiki/Mapper
+/// Mappers save their registers along with the cartridge's RAM as part of a save state.
+pub trait Mapper: Debug + SaveState {
+    /// The cartridge the mapper is connected to.
+    fn cartridge(&self) -> &Cartridge;
+
+    /// Read from the cartridge space of the CPU at $4020-$FFFF. Unmapped addresses return `None`.
+    fn cpu_read(&self, address: u16) -> Option<u8>;
+
+    /// Write to the cartridge space of the CPU at $4020-$FFFF. This is how most mappers are configured.
+    fn cpu_write(&mut self, address: u16, data: u8);
+
+    /// Read from the pattern tables in the PPU's address space at $0000-$1FFF.
+    fn ppu_read(&mut self, address: u16) -> u8;
+
+    /// Write to the pattern tables in the PPU's address space at $0000-$1FFF.
+    fn ppu_write(&mut self, address: u16, data: u8);
+
+    /// Called at the start of every CPU cycle, for mappers that react to the timing of CPU writes.
+    fn cpu_clock(&mut self) {}
+
+    /// Called on every PPU dot with the address currently on the PPU's address bus, for mappers
+    /// that watch the PPU's memory accesses.
+    fn ppu_clock(&mut self, _address: u16) {}
+
+    /// Called when the PPU starts a new scanline, with its number. The pre-render scanline is the
+    /// last one of the frame.
+    fn scanline(&mut self, _scanline: u16) {}
+
+    /// The current nametable layout.
+    fn mirroring(&self) -> Mirroring {
+        self.cartridge().header.mirroring
+    }
+
+    /// Whether the mapper is currently asserting the IRQ line of the CPU.
+    fn irq(&self) -> bool {
+        false
+    }
+}
+
+/// The iNES mapper numbers `from_cartridge` can connect a cartridge to.
+pub const SUPPORTED_MAPPERS: [u16; 6] = [0, 1, 2, 3, 4, 7];
+
+/// Common names of the boards or chips behind well-known iNES mapper numbers.
+/// Ref: https://www.nesdev.org/wiki/Mapper#iNES_1.0_mapper_grid
+const MAPPER_NAMES: [(u16, &str); 24] = [
+    (0, "NROM"),
+    (1, "MMC1"),
+    (2, "UxROM"),
+    (3, "CNROM"),
+    (4, "MMC3"),
+    (5, "MMC5"),
+    (7, "AxROM"),
+    (9, "MMC2"),
+    (10, "MMC4"),
+    (11, "Color Dreams"),
+    (13, "CPROM"),
+    (19, "Namco 163"),
+    (21, "VRC4a/VRC4c"),
+    (23, "VRC2b/VRC4e"),
+    (24, "VRC6a"),
+    (25, "VRC4b/VRC4d"),
+    (26, "VRC6b"),
+    (34, "BNROM/NINA-001"),
+    (66, "GxROM"),
+    (69, "Sunsoft FME-7"),
+    (71, "Camerica"),
+    (85, "VRC7"),
+    (206, "DxROM"),
+    (228, "Action 52"),
+];
+
+/// The common name of an iNES mapper number, if it's a well-known one.
+pub fn mapper_name(mapper: u16) -> Option<&'static str> {
+    MAPPER_NAMES.iter().find(|(number, _)| *number == mapper).map(|(_, name)| *name)
+}
+
+/// Connect a cartridge to the mapper indicated by its header.
+pub fn from_cartridge(cartridge: Cartridge) -> Result<Box<dyn Mapper>, CartridgeError> {
+    Ok(match cartridge.header.mapper {
+        0 => Box::new(Nrom::new(cartridge)),
+        1 => Box::new(Mmc1::new(cartridge)),
+        2 => Box::new(UxRom::new(cartridge)),
+        3 => Box::new(CnRom::new(cartridge)),
+        4 => Box::new(Mmc3::new(cartridge)),
+        7 => Box::new(AxRom::new(cartridge)),
+        mapper => return Err(CartridgeError::UnsupportedMapper(mapper)),
+    })
+}
+
+/// Save a nametable layout selected by a mapper.
+pub(crate) fn save_mirroring(writer: &mut StateWriter, mirroring: Mirroring) {
+    writer.write_u8(match mirroring {
+        Mirroring::Horizontal => 0,
+        Mirroring::Vertical => 1,
+        Mirroring::FourScreen => 2,
+        Mirroring::SingleScreenLower => 3,
+        Mirroring::SingleScreenUpper => 4,
+    });
+}
+
+/// Load a nametable layout written by `save_mirroring`.
+pub(crate) fn load_mirroring(reader: &mut StateReader) -> Result<Mirroring, SaveStateError> {
+    Ok(match reader.read_u8()? {
+        0 => Mirroring::Horizontal,
+        1 => Mirroring::Vertical,
+        2 => Mirroring::FourScreen,
+        3 => Mirroring::SingleScreenLower,
+        4 => Mirroring::SingleScreenUpper,
+        _ => return Err(SaveStateError::Malformed("invalid mirroring")),
+    })
+}
@@ -0,0 +1,54 @@
+use crate::{cartridge::Cartridge, save_state::{SaveState, SaveStateError, StateReader, StateWriter}};
+use super::Mapper;
+
+/// Mapper 0, the board without any bank switching. PRG-ROM is either 16 KiB, mirrored into both halves
+/// of $8000-$FFFF, or 32 KiB. CHR is a fixed 8 KiB.
+/// Ref: https://www.nesdev.org/wiki/NROM
+#[derive(Debug)]
+pub struct Nrom {
+    cartridge: Cartridge,
+}
+
+impl Nrom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Nrom { cartridge }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn cpu_read(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => self.cartridge.read_prg_ram(address),
+            0x8000..=0xFFFF => Some(self.cartridge.read_prg_rom(0, 0x8000, address)),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        if let 0x6000..=0x7FFF = address {
+            self.cartridge.write_prg_ram(address, data);
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.cartridge.read_chr(0, 0x2000, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.cartridge.write_chr(0, 0x2000, address, data);
+    }
+}
+
+impl SaveState for Nrom {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.cartridge.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.cartridge.load_state(reader)
+    }
+}
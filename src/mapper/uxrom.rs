@@ -0,0 +1,65 @@
+use crate::{cartridge::Cartridge, save_state::{SaveState, SaveStateError, StateReader, StateWriter}};
+use super::Mapper;
+
+/// Mapper 2, switches a 16 KiB PRG-ROM bank into $8000-$BFFF while the last bank is fixed at
+/// $C000-$FFFF. CHR is a fixed 8 KiB, usually RAM.
+/// Ref: https://www.nesdev.org/wiki/UxROM
+#[derive(Debug)]
+pub struct UxRom {
+    cartridge: Cartridge,
+    /// The PRG-ROM bank mapped at $8000-$BFFF
+    prg_bank: u8,
+}
+
+impl UxRom {
+    pub fn new(cartridge: Cartridge) -> Self {
+        UxRom { cartridge, prg_bank: 0 }
+    }
+}
+
+impl Mapper for UxRom {
+    fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    fn cpu_read(&self, address: u16) -> Option<u8> {
+        match address {
+            0x6000..=0x7FFF => self.cartridge.read_prg_ram(address),
+            0x8000..=0xBFFF => Some(self.cartridge.read_prg_rom(self.prg_bank as usize, 0x4000, address)),
+            0xC000..=0xFFFF => {
+                let last = (self.cartridge.prg_rom.len() / 0x4000).saturating_sub(1);
+                Some(self.cartridge.read_prg_rom(last, 0x4000, address))
+            },
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        match address {
+            0x6000..=0x7FFF => self.cartridge.write_prg_ram(address, data),
+            0x8000..=0xFFFF => self.prg_bank = data,
+            _ => {},
+        }
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.cartridge.read_chr(0, 0x2000, address)
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.cartridge.write_chr(0, 0x2000, address, data);
+    }
+}
+
+impl SaveState for UxRom {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.cartridge.save_state(writer);
+        writer.write_u8(self.prg_bank);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.cartridge.load_state(reader)?;
+        self.prg_bank = reader.read_u8()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,406 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+use crate::{
+    controller::ButtonState,
+    nes::Nes,
+    save_state::{SaveStateError, StateReader, StateWriter},
+};
+
+/// Every native movie starts with these four bytes.
+pub const MOVIE_MAGIC: [u8; 4] = *b"PGMV";
+/// Revision of the native movie format.
+pub const MOVIE_VERSION: u16 = 1;
+/// Frames between RAM checkpoints of a recording, unless configured otherwise.
+pub const DEFAULT_CHECKPOINT_INTERVAL: u32 = 60;
+
+/// The buttons of a standard controller in the order FM2 input logs list them, most significant
+/// bit first.
+const FM2_BUTTONS: [ButtonState; 8] = [
+    ButtonState::RIGHT,
+    ButtonState::LEFT,
+    ButtonState::DOWN,
+    ButtonState::UP,
+    ButtonState::START,
+    ButtonState::SELECT,
+    ButtonState::B,
+    ButtonState::A,
+];
+
+/// Errors that can occur while loading or playing back a movie.
+#[derive(Debug)]
+pub enum MovieError {
+    /// The file could not be read or written.
+    Io(io::Error),
+    /// The data does not start with the movie magic bytes.
+    InvalidMagic,
+    /// The movie was written by an incompatible version of the emulator.
+    UnsupportedVersion(u16),
+    /// The data ends before the movie does.
+    Truncated,
+    /// The movie contains a value that can't occur in a valid movie.
+    Malformed(&'static str),
+    /// A line of an FM2 file couldn't be parsed, or uses a feature that isn't supported.
+    Fm2 { line: usize, reason: &'static str },
+    /// The movie was recorded with a different cartridge.
+    WrongCartridge,
+    /// The RAM doesn't match the recording after `frame` frames, so the playback has desynced.
+    Desync { frame: u32, expected: u32, actual: u32 },
+}
+
+impl fmt::Display for MovieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MovieError::Io(err) => write!(f, "could not access movie: {}", err),
+            MovieError::InvalidMagic => write!(f, "not a movie (invalid magic bytes)"),
+            MovieError::UnsupportedVersion(version) => write!(f, "movie version {} is not supported", version),
+            MovieError::Truncated => write!(f, "movie is truncated"),
+            MovieError::Malformed(reason) => write!(f, "malformed movie: {}", reason),
+            MovieError::Fm2 { line, reason } => write!(f, "FM2 line {}: {}", line, reason),
+            MovieError::WrongCartridge => write!(f, "movie was recorded with a different cartridge"),
+            MovieError::Desync { frame, expected, actual } => {
+                write!(f, "movie desynced at frame {}: expected RAM CRC {:08X}, found {:08X}", frame, expected, actual)
+            },
+        }
+    }
+}
+
+impl Error for MovieError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MovieError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MovieError {
+    fn from(err: io::Error) -> Self {
+        MovieError::Io(err)
+    }
+}
+
+impl From<SaveStateError> for MovieError {
+    fn from(err: SaveStateError) -> Self {
+        match err {
+            SaveStateError::Truncated => MovieError::Truncated,
+            SaveStateError::Malformed(reason) => MovieError::Malformed(reason),
+            _ => MovieError::Malformed("invalid value"),
+        }
+    }
+}
+
+/// A button on the console pressed at the start of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovieCommand {
+    Reset,
+    PowerOn,
+}
+
+/// The input of a single frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MovieFrame {
+    /// The buttons held on both controllers during the frame
+    pub buttons: [ButtonState; 2],
+    /// A console button pressed before the frame starts
+    pub command: Option<MovieCommand>,
+}
+
+/// The CRC32 of the console's internal RAM after a number of frames, used to detect desyncs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Number of frames played before the checkpoint
+    pub frame: u32,
+    pub ram_crc: u32,
+}
+
+/// A recording of the input of a play session, starting from power on. Because the emulation is
+/// deterministic, playing it back reproduces the session exactly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Movie {
+    /// CRC32 of the PRG-ROM of the cartridge the movie was recorded with. Unknown for FM2 movies,
+    /// which identify the ROM by an MD5 hash.
+    pub rom_crc: Option<u32>,
+    pub frames: Vec<MovieFrame>,
+    /// RAM checkpoints in order of their frame
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+impl Movie {
+    pub fn new(rom_crc: Option<u32>) -> Self {
+        Movie { rom_crc, frames: Vec::new(), checkpoints: Vec::new() }
+    }
+
+    /// Load a movie in the native format from a file on disk.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MovieError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Write the movie to a file on disk in the native format.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), MovieError> {
+        Ok(fs::write(path, self.to_bytes())?)
+    }
+
+    /// Encode the movie in the native format: a header, 3 bytes per frame and the checkpoints.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+        writer.write_bytes(&MOVIE_MAGIC);
+        writer.write_u16(MOVIE_VERSION);
+        writer.write_bool(self.rom_crc.is_some());
+        writer.write_u32(self.rom_crc.unwrap_or(0));
+
+        writer.write_u32(self.frames.len() as u32);
+        for frame in &self.frames {
+            writer.write_u8(match frame.command {
+                None => 0,
+                Some(MovieCommand::Reset) => 1,
+                Some(MovieCommand::PowerOn) => 2,
+            });
+            writer.write_u8(frame.buttons[0].bits());
+            writer.write_u8(frame.buttons[1].bits());
+        }
+
+        writer.write_u32(self.checkpoints.len() as u32);
+        for checkpoint in &self.checkpoints {
+            writer.write_u32(checkpoint.frame);
+            writer.write_u32(checkpoint.ram_crc);
+        }
+
+        writer.into_bytes()
+    }
+
+    /// Decode a movie in the native format.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MovieError> {
+        let mut reader = StateReader::new(data);
+        let mut magic = [0; 4];
+        reader.read_bytes(&mut magic)?;
+        if magic != MOVIE_MAGIC {
+            return Err(MovieError::InvalidMagic);
+        }
+        let version = reader.read_u16()?;
+        if version != MOVIE_VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+
+        let has_crc = reader.read_bool()?;
+        let rom_crc = reader.read_u32()?;
+        let mut movie = Movie::new(has_crc.then_some(rom_crc));
+
+        for _ in 0..reader.read_u32()? {
+            let command = match reader.read_u8()? {
+                0 => None,
+                1 => Some(MovieCommand::Reset),
+                2 => Some(MovieCommand::PowerOn),
+                _ => return Err(MovieError::Malformed("invalid command")),
+            };
+            let port_1 = ButtonState::from_bits_truncate(reader.read_u8()?);
+            let port_2 = ButtonState::from_bits_truncate(reader.read_u8()?);
+            movie.frames.push(MovieFrame { buttons: [port_1, port_2], command });
+        }
+
+        for _ in 0..reader.read_u32()? {
+            let frame = reader.read_u32()?;
+            let ram_crc = reader.read_u32()?;
+            if movie.checkpoints.last().is_some_and(|last| last.frame >= frame) {
+                return Err(MovieError::Malformed("checkpoints out of order"));
+            }
+            movie.checkpoints.push(Checkpoint { frame, ram_crc });
+        }
+
+        if !reader.is_empty() {
+            return Err(MovieError::Malformed("unexpected data after the movie"));
+        }
+        Ok(movie)
+    }
+
+    /// Load an FCEUX movie from a file on disk.
+    pub fn load_fm2<P: AsRef<Path>>(path: P) -> Result<Self, MovieError> {
+        Self::from_fm2(&fs::read_to_string(path)?)
+    }
+
+    /// Import the input log of an FCEUX movie. Only text movies that start from power on with
+    /// standard controllers are supported. FM2 movies don't contain checkpoints.
+    /// Ref: https://fceux.com/web/FM2.html
+    pub fn from_fm2(text: &str) -> Result<Self, MovieError> {
+        let mut movie = Movie::new(None);
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let error = |reason| MovieError::Fm2 { line: line_number, reason };
+            let line = line.trim_end();
+
+            if let Some(input) = line.strip_prefix('|') {
+                movie.frames.push(parse_fm2_frame(input).map_err(error)?);
+                continue;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match (key, value.trim()) {
+                ("binary", "1") => return Err(error("binary input logs are not supported")),
+                ("fourscore", "1") => return Err(error("the Four Score is not supported")),
+                ("port0" | "port1", "0" | "1") => {},
+                ("port0" | "port1", _) => return Err(error("only standard controllers are supported")),
+                ("savestate", _) => return Err(error("movies that start from a save state are not supported")),
+                _ => {},
+            }
+        }
+
+        Ok(movie)
+    }
+
+    /// Play the whole movie on `nes`, powering it on first.
+    pub fn play(&self, nes: &mut Nes) -> Result<(), MovieError> {
+        let mut player = MoviePlayer::new(self.clone(), nes)?;
+        while player.run_frame(nes)? {}
+        Ok(())
+    }
+}
+
+/// Parse the fields of an FM2 input line after the leading `|`: the commands, followed by the
+/// buttons held on both ports.
+fn parse_fm2_frame(input: &str) -> Result<MovieFrame, &'static str> {
+    let mut fields = input.split('|');
+    let commands: u8 = fields.next().unwrap_or("").trim().parse().map_err(|_| "invalid commands")?;
+
+    let mut frame = MovieFrame {
+        command: if commands & 0x02 != 0 {
+            Some(MovieCommand::PowerOn)
+        } else if commands & 0x01 != 0 {
+            Some(MovieCommand::Reset)
+        } else {
+            None
+        },
+        ..Default::default()
+    };
+
+    for buttons in &mut frame.buttons {
+        let field = fields.next().unwrap_or("");
+        if field.is_empty() {
+            continue;
+        }
+        if field.chars().count() != FM2_BUTTONS.len() {
+            return Err("a controller needs 8 buttons");
+        }
+        for (button, char) in FM2_BUTTONS.iter().zip(field.chars()) {
+            if char != '.' && char != ' ' {
+                buttons.insert(*button);
+            }
+        }
+    }
+
+    Ok(frame)
+}
+
+/// Apply the input of a movie frame and run it.
+fn run_movie_frame(nes: &mut Nes, frame: &MovieFrame) {
+    match frame.command {
+        Some(MovieCommand::Reset) => nes.reset(),
+        Some(MovieCommand::PowerOn) => nes.power_on(),
+        None => {},
+    }
+    nes.set_buttons(0, frame.buttons[0]);
+    nes.set_buttons(1, frame.buttons[1]);
+    nes.run_frame();
+}
+
+/// The CRC32 of the console's internal RAM.
+fn ram_crc(nes: &Nes) -> u32 {
+    crc32fast::hash(&nes.cpu.bus.ram)
+}
+
+/// Records a movie by running the console one frame at a time with the given input.
+#[derive(Debug)]
+pub struct MovieRecorder {
+    movie: Movie,
+    checkpoint_interval: u32,
+}
+
+impl MovieRecorder {
+    /// Power on `nes` and start recording. Every `checkpoint_interval` frames the RAM is hashed
+    /// for desync detection, or never if it's 0.
+    pub fn new(nes: &mut Nes, checkpoint_interval: u32) -> Self {
+        nes.power_on();
+        MovieRecorder { movie: Movie::new(Some(nes.rom_crc())), checkpoint_interval }
+    }
+
+    /// Run a frame with `buttons` held, after pressing a console button if there's a `command`.
+    pub fn run_frame(&mut self, nes: &mut Nes, buttons: [ButtonState; 2], command: Option<MovieCommand>) {
+        let frame = MovieFrame { buttons, command };
+        run_movie_frame(nes, &frame);
+        self.movie.frames.push(frame);
+
+        let frames = self.movie.frames.len() as u32;
+        if self.checkpoint_interval > 0 && frames.is_multiple_of(self.checkpoint_interval) {
+            self.movie.checkpoints.push(Checkpoint { frame: frames, ram_crc: ram_crc(nes) });
+        }
+    }
+
+    /// The movie recorded so far.
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    /// Stop recording, returning the movie.
+    pub fn finish(self) -> Movie {
+        self.movie
+    }
+}
+
+/// Plays back a movie one frame at a time, checking for desyncs at every checkpoint.
+#[derive(Debug)]
+pub struct MoviePlayer {
+    movie: Movie,
+    /// Number of frames played so far
+    position: usize,
+    /// Index of the next checkpoint to verify
+    next_checkpoint: usize,
+}
+
+impl MoviePlayer {
+    /// Power on `nes` to start playing `movie`, which must have been recorded with the same
+    /// cartridge if it's known.
+    pub fn new(movie: Movie, nes: &mut Nes) -> Result<Self, MovieError> {
+        if movie.rom_crc.is_some_and(|crc| crc != nes.rom_crc()) {
+            return Err(MovieError::WrongCartridge);
+        }
+
+        nes.power_on();
+        Ok(MoviePlayer { movie, position: 0, next_checkpoint: 0 })
+    }
+
+    /// Run the next frame of the movie. Returns `false` once the movie has ended, or an error if
+    /// the RAM doesn't match a checkpoint.
+    pub fn run_frame(&mut self, nes: &mut Nes) -> Result<bool, MovieError> {
+        let Some(frame) = self.movie.frames.get(self.position) else {
+            return Ok(false);
+        };
+        run_movie_frame(nes, frame);
+        self.position += 1;
+
+        while let Some(checkpoint) = self.movie.checkpoints.get(self.next_checkpoint) {
+            if checkpoint.frame as usize > self.position {
+                break;
+            }
+            self.next_checkpoint += 1;
+
+            // A checkpoint before the first frame can't be verified
+            let actual = ram_crc(nes);
+            if checkpoint.frame as usize == self.position && checkpoint.ram_crc != actual {
+                return Err(MovieError::Desync { frame: checkpoint.frame, expected: checkpoint.ram_crc, actual });
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Number of frames played so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.movie.frames.len()
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+}
@@ -0,0 +1,310 @@
+use std::path::Path;
+use crate::{
+    apu::{Apu, CPU_CLOCK_DENDY, CPU_CLOCK_NTSC, CPU_CLOCK_PAL},
+    bus::{Bus, RAM_SIZE},
+    cartridge::{Cartridge, CartridgeError, Region},
+    controller::ButtonState,
+    cpu::CPU,
+    mapper,
+    palette::Palette,
+    ppu::{Ppu, FRAME_HEIGHT, FRAME_WIDTH},
+    rewind::{RewindBuffer, RewindConfig},
+    save_state::{self, SaveState, SaveStateError, StateReader, StateWriter},
+    screenshot::{self, ScreenshotError},
+};
+
+/// The PPU runs 3 dots per CPU cycle on NTSC and Dendy consoles, and 3.2 on PAL consoles. To keep
+/// the ratio exact, it is expressed in fifths of a dot.
+const DOT_FRACTIONS: u32 = 5;
+
+/// A complete console, with the CPU, PPU, APU and cartridge all driven by a single master clock.
+/// All components hang off the CPU's bus, which is how they are connected on the real hardware.
+#[derive(Debug)]
+pub struct Nes {
+    /// The CPU, which owns the bus and with it everything else
+    pub cpu: CPU,
+    region: Region,
+    /// Fifths of a PPU dot owed to the PPU
+    dot_fractions: u32,
+    /// Cycles the CPU is halted for by DMA
+    stall_cycles: u32,
+    /// Snapshots of the last few seconds, if rewinding is enabled
+    rewind: Option<RewindBuffer>,
+}
+
+impl Nes {
+    /// Build a console with `cartridge` inserted and power it on, using the region indicated by
+    /// the cartridge's header.
+    pub fn new(cartridge: Cartridge) -> Result<Self, CartridgeError> {
+        let region = cartridge.header.region;
+        let mut bus = Bus::new();
+        bus.insert_cartridge(cartridge)?;
+
+        let mut nes = Nes {
+            cpu: CPU::with_bus(bus),
+            region,
+            dot_fractions: 0,
+            stall_cycles: 0,
+            rewind: None,
+        };
+        nes.power_on();
+        Ok(nes)
+    }
+
+    /// Switch the timing of the console between NTSC, PAL and Dendy.
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+        self.cpu.bus.ppu.set_region(region);
+        self.cpu.bus.apu.set_cpu_clock_rate(match region {
+            Region::Ntsc | Region::Multi => CPU_CLOCK_NTSC,
+            Region::Pal => CPU_CLOCK_PAL,
+            Region::Dendy => CPU_CLOCK_DENDY,
+        });
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// Turn the console on, as if it had just been plugged in. All memory is cleared and the mapper
+    /// is returned to its initial state, but the cartridge's PRG-RAM is kept.
+    pub fn power_on(&mut self) {
+        let bus = &mut self.cpu.bus;
+        bus.ram = [0x0; RAM_SIZE];
+        bus.ppu = Ppu::new();
+        let sample_rate = bus.apu.sample_rate();
+        bus.apu = Apu::new();
+        bus.apu.set_sample_rate(sample_rate);
+        bus.take_stall_cycles();
+        if let Some(cartridge) = bus.mapper.as_ref().map(|mapper| mapper.cartridge().clone()) {
+            // Recreating the mapper from the same cartridge can't fail
+            bus.mapper = mapper::from_cartridge(cartridge).ok();
+        }
+
+        self.set_region(self.region);
+        self.dot_fractions = 0;
+        self.stall_cycles = 0;
+        self.cpu.cycles = 0;
+        self.cpu.reset();
+        self.clear_rewind();
+    }
+
+    /// Press the reset button.
+    pub fn reset(&mut self) {
+        self.cpu.bus.ppu.reset();
+        self.cpu.bus.apu.reset();
+        self.stall_cycles = 0;
+        self.cpu.reset();
+        self.clear_rewind();
+    }
+
+    /// Simulate a single CPU cycle, along with the PPU dots and APU cycle that happen during it.
+    pub fn clock(&mut self) {
+        // DMA halts the CPU once the current instruction has finished
+        if self.stall_cycles > 0 && self.cpu.step == 0 {
+            self.stall_cycles -= 1;
+            self.cpu.stall();
+        } else {
+            self.cpu.clock();
+        }
+        self.stall_cycles += self.cpu.bus.take_stall_cycles() as u32;
+
+        self.dot_fractions += if self.region == Region::Pal { 16 } else { 15 };
+        while self.dot_fractions >= DOT_FRACTIONS {
+            self.cpu.bus.clock_ppu();
+            self.dot_fractions -= DOT_FRACTIONS;
+        }
+
+        self.cpu.bus.clock_apu();
+    }
+
+    /// Run until the CPU has finished its current instruction and executed the next one, along
+    /// with any interrupt sequence in between. Returns the number of CPU cycles that took.
+    pub fn step_instruction(&mut self) -> u64 {
+        let start = self.cpu.cycles;
+
+        loop {
+            while self.cpu.step > 0 || self.stall_cycles > 0 {
+                self.clock();
+            }
+            self.clock();
+
+            let interrupt = self.cpu.interrupt.is_some();
+            while self.cpu.step > 0 {
+                self.clock();
+            }
+
+            if !interrupt {
+                break;
+            }
+        }
+
+        self.cpu.cycles - start
+    }
+
+    /// Run until the PPU has finished the current frame.
+    pub fn run_frame(&mut self) {
+        let buttons = self.buttons();
+        let frame = self.cpu.bus.ppu.frame_count();
+        while self.cpu.bus.ppu.frame_count() == frame {
+            self.clock();
+        }
+
+        if let Some(mut rewind) = self.rewind.take() {
+            rewind.record_input(buttons);
+            if rewind.snapshot_due() {
+                rewind.push(self.frame_count(), &self.save_state());
+            }
+            self.rewind = Some(rewind);
+        }
+    }
+
+    /// Start keeping snapshots to rewind to, starting with one of the current frame.
+    pub fn enable_rewind(&mut self, config: RewindConfig) {
+        let mut rewind = RewindBuffer::new(config);
+        rewind.push(self.frame_count(), &self.save_state());
+        self.rewind = Some(rewind);
+    }
+
+    /// Stop keeping snapshots and drop the ones kept so far.
+    pub fn disable_rewind(&mut self) {
+        self.rewind = None;
+    }
+
+    /// The snapshots kept for rewinding, if it's enabled.
+    pub fn rewind_buffer(&self) -> Option<&RewindBuffer> {
+        self.rewind.as_ref()
+    }
+
+    /// Go back `frames` frames, or as far back as the rewind buffer allows, by loading the snapshot
+    /// before the target frame and replaying the recorded input from there. Returns the number of
+    /// frames actually rewound.
+    pub fn rewind_frames(&mut self, frames: u64) -> u64 {
+        let Some(mut rewind) = self.rewind.take() else {
+            return 0;
+        };
+
+        let current = self.frame_count();
+        let target = current.saturating_sub(frames).max(rewind.oldest_frame().unwrap_or(current));
+        if let Some((state, inputs)) = rewind.rewind_to(target) {
+            // Snapshots are taken from this console, so they are always valid
+            self.load_state(&state).expect("invalid rewind snapshot");
+            for [port_1, port_2] in inputs {
+                self.set_buttons(0, port_1);
+                self.set_buttons(1, port_2);
+                self.run_frame();
+            }
+        }
+
+        self.rewind = Some(rewind);
+        current - self.frame_count()
+    }
+
+    /// Number of CPU cycles since the console was powered on.
+    pub fn cycles(&self) -> u64 {
+        self.cpu.cycles
+    }
+
+    /// Number of frames since the console was powered on.
+    pub fn frame_count(&self) -> u64 {
+        self.cpu.bus.ppu.frame_count()
+    }
+
+    /// The last frame, as 256x240 row-major indices into the system palette, including the
+    /// emphasis bits.
+    pub fn frame_buffer(&self) -> &[u16] {
+        self.cpu.bus.ppu.frame_buffer()
+    }
+
+    /// Dimensions of the frame buffer, in pixels.
+    pub fn frame_size(&self) -> (usize, usize) {
+        (FRAME_WIDTH, FRAME_HEIGHT)
+    }
+
+    /// The last frame as 3 bytes per pixel, with colors looked up in `palette`.
+    pub fn frame_rgb(&self, palette: &Palette) -> Vec<u8> {
+        palette.to_rgb24(self.frame_buffer())
+    }
+
+    /// Save the last frame as a PNG or PPM image, depending on the extension of `path`.
+    pub fn save_screenshot<P: AsRef<Path>>(&self, path: P, palette: &Palette) -> Result<(), ScreenshotError> {
+        screenshot::save(path, FRAME_WIDTH, FRAME_HEIGHT, &self.frame_rgb(palette))
+    }
+
+    /// Change the rate at which audio samples are produced.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.cpu.bus.apu.set_sample_rate(sample_rate);
+    }
+
+    /// Take the audio samples produced since the last call.
+    pub fn take_audio_samples(&mut self) -> Vec<f32> {
+        self.cpu.bus.apu.take_samples()
+    }
+
+    /// Set the buttons held down on the controller in `port` 0 or 1.
+    pub fn set_buttons(&mut self, port: usize, buttons: ButtonState) {
+        self.cpu.bus.set_buttons(port, buttons);
+    }
+
+    /// Save the complete state of the console, including the cartridge's RAM. The state can only be
+    /// loaded with the same cartridge inserted.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+        writer.write_u8(match self.region {
+            Region::Ntsc => 0,
+            Region::Pal => 1,
+            Region::Multi => 2,
+            Region::Dendy => 3,
+        });
+        writer.write_u32(self.dot_fractions);
+        writer.write_u32(self.stall_cycles);
+        self.cpu.save_state(&mut writer);
+
+        save_state::wrap(&writer.into_bytes(), self.rom_crc())
+    }
+
+    /// Restore a state written by `save_state`. The header is checked before anything is changed,
+    /// but a state that passes those checks and still turns out malformed leaves the console in an
+    /// undefined state.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), SaveStateError> {
+        let mut reader = StateReader::new(save_state::unwrap(data, self.rom_crc())?);
+        let region = match reader.read_u8()? {
+            0 => Region::Ntsc,
+            1 => Region::Pal,
+            2 => Region::Multi,
+            3 => Region::Dendy,
+            _ => return Err(SaveStateError::Malformed("invalid region")),
+        };
+        self.set_region(region);
+        self.dot_fractions = reader.read_u32()?;
+        self.stall_cycles = reader.read_u32()?;
+        self.cpu.load_state(&mut reader)?;
+
+        if !reader.is_empty() {
+            return Err(SaveStateError::Malformed("unexpected data after the state"));
+        }
+        self.clear_rewind();
+        Ok(())
+    }
+
+    /// CRC32 of the inserted cartridge's PRG-ROM, which identifies the game save states and movies
+    /// belong to.
+    pub fn rom_crc(&self) -> u32 {
+        self.cpu.bus.mapper.as_ref().map_or(0, |mapper| crc32fast::hash(&mapper.cartridge().prg_rom))
+    }
+
+    /// The buttons held on both controllers.
+    fn buttons(&self) -> [ButtonState; 2] {
+        [self.cpu.bus.controllers[0].buttons, self.cpu.bus.controllers[1].buttons]
+    }
+
+    /// Snapshots from before a jump in time, like a reset or a loaded state, can't be replayed into
+    /// the present, so they're dropped.
+    fn clear_rewind(&mut self) {
+        if let Some(mut rewind) = self.rewind.take() {
+            rewind.clear();
+            rewind.push(self.frame_count(), &self.save_state());
+            self.rewind = Some(rewind);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+/// Number of colors the PPU can output, not counting emphasis.
+pub const PALETTE_SIZE: usize = 64;
+/// Number of colors including every combination of the three emphasis bits.
+pub const EMPHASIS_PALETTE_SIZE: usize = PALETTE_SIZE * 8;
+/// Size of a .pal file with one RGB triplet per color.
+pub const PAL_FILE_SIZE: usize = PALETTE_SIZE * 3;
+/// Size of a .pal file that also has the colors for every combination of emphasis bits.
+pub const EMPHASIS_PAL_FILE_SIZE: usize = EMPHASIS_PALETTE_SIZE * 3;
+
+/// Emphasis bits of a frame buffer pixel, above its 6-bit color. The PPU already swaps red and
+/// green on PAL consoles, so these always mean the same.
+pub const EMPHASIS_RED: u16 = 1 << 6;
+/// See `EMPHASIS_RED`
+pub const EMPHASIS_GREEN: u16 = 1 << 7;
+/// See `EMPHASIS_RED`
+pub const EMPHASIS_BLUE: u16 = 1 << 8;
+
+/// How much every emphasis bit darkens the two color components it doesn't emphasize, as measured
+/// on a 2C02. Used to derive the emphasized colors of palettes that only have 64 entries.
+/// Ref: https://www.nesdev.org/wiki/NTSC_video#Color_Tint_Bits
+const EMPHASIS_ATTENUATION: f32 = 0.746;
+
+/// The colors of the NTSC 2C02, as measured from a real console.
+/// Ref: https://www.nesdev.org/wiki/PPU_palettes#2C02
+const DEFAULT_COLORS: [[u8; 3]; PALETTE_SIZE] = [
+    [84, 84, 84], [0, 30, 116], [8, 16, 144], [48, 0, 136],
+    [68, 0, 100], [92, 0, 48], [84, 4, 0], [60, 24, 0],
+    [32, 42, 0], [8, 58, 0], [0, 64, 0], [0, 60, 0],
+    [0, 50, 60], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+    [152, 150, 152], [8, 76, 196], [48, 50, 236], [92, 30, 228],
+    [136, 20, 176], [160, 20, 100], [152, 34, 32], [120, 60, 0],
+    [84, 90, 0], [40, 114, 0], [8, 124, 0], [0, 118, 40],
+    [0, 102, 120], [0, 0, 0], [0, 0, 0], [0, 0, 0],
+    [236, 238, 236], [76, 154, 236], [120, 124, 236], [176, 98, 236],
+    [228, 84, 236], [236, 88, 180], [236, 106, 100], [212, 136, 32],
+    [160, 170, 0], [116, 196, 0], [76, 208, 32], [56, 204, 108],
+    [56, 180, 204], [60, 60, 60], [0, 0, 0], [0, 0, 0],
+    [236, 238, 236], [168, 204, 236], [188, 188, 236], [212, 178, 236],
+    [236, 174, 236], [236, 174, 212], [236, 180, 176], [228, 196, 144],
+    [204, 210, 120], [180, 222, 120], [168, 226, 144], [152, 226, 180],
+    [160, 214, 228], [160, 162, 160], [0, 0, 0], [0, 0, 0],
+];
+
+/// Errors that can occur while loading a palette.
+#[derive(Debug)]
+pub enum PaletteError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file doesn't contain exactly one RGB triplet for every color, with or without
+    /// emphasis.
+    InvalidSize(usize),
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaletteError::Io(err) => write!(f, "could not read palette: {}", err),
+            PaletteError::InvalidSize(size) => {
+                write!(f, "palette is {} bytes, expected {} or {}", size, PAL_FILE_SIZE, EMPHASIS_PAL_FILE_SIZE)
+            },
+        }
+    }
+}
+
+impl Error for PaletteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PaletteError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PaletteError {
+    fn from(err: io::Error) -> Self {
+        PaletteError::Io(err)
+    }
+}
+
+/// Maps the pixels the PPU outputs to RGB colors. How the PPU's composite video signal looks on a
+/// TV is a matter of taste, so emulators come with different palettes.
+///
+/// Pixels are 9-bit indices: the 6-bit color from palette RAM, after greyscale has been applied,
+/// and the PPUMASK emphasis bits above it as `EMPHASIS_RED`, `EMPHASIS_GREEN` and `EMPHASIS_BLUE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    colors: Box<[[u8; 3]; EMPHASIS_PALETTE_SIZE]>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::with_emphasis(&DEFAULT_COLORS)
+    }
+}
+
+impl Palette {
+    /// Load a palette from a .pal file on disk.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PaletteError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Parse the contents of a .pal file as used by FCEUX and Mesen: either 64 RGB triplets, or
+    /// 512 of them with the colors for each combination of emphasis bits after each other.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PaletteError> {
+        let triplets = bytes.chunks_exact(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]);
+        match bytes.len() {
+            PAL_FILE_SIZE => {
+                let mut base = [[0; 3]; PALETTE_SIZE];
+                base.iter_mut().zip(triplets).for_each(|(color, rgb)| *color = rgb);
+                Ok(Palette::with_emphasis(&base))
+            },
+            EMPHASIS_PAL_FILE_SIZE => {
+                let mut colors = Box::new([[0; 3]; EMPHASIS_PALETTE_SIZE]);
+                colors.iter_mut().zip(triplets).for_each(|(color, rgb)| *color = rgb);
+                Ok(Palette { colors })
+            },
+            size => Err(PaletteError::InvalidSize(size)),
+        }
+    }
+
+    /// Derive the emphasized colors from the 64 base colors, by darkening the components that
+    /// aren't emphasized.
+    fn with_emphasis(base: &[[u8; 3]; PALETTE_SIZE]) -> Self {
+        let mut colors = Box::new([[0; 3]; EMPHASIS_PALETTE_SIZE]);
+        for (index, color) in colors.iter_mut().enumerate() {
+            let emphasis = index >> 6;
+            *color = base[index % PALETTE_SIZE];
+            for (component, value) in color.iter_mut().enumerate() {
+                let attenuated = (emphasis & !(1 << component)).count_ones() as i32;
+                *value = (*value as f32 * EMPHASIS_ATTENUATION.powi(attenuated)).round() as u8;
+            }
+        }
+        Palette { colors }
+    }
+
+    /// The RGB color of a pixel. Only the lower 9 bits are used.
+    pub fn rgb(&self, pixel: u16) -> [u8; 3] {
+        self.colors[pixel as usize % EMPHASIS_PALETTE_SIZE]
+    }
+
+    /// The color of a pixel packed as `0xAARRGGBB`, with full alpha.
+    pub fn argb(&self, pixel: u16) -> u32 {
+        let [r, g, b] = self.rgb(pixel);
+        u32::from_be_bytes([0xFF, r, g, b])
+    }
+
+    /// Convert a frame into 3 bytes per pixel, in RGB order.
+    pub fn to_rgb24(&self, frame: &[u16]) -> Vec<u8> {
+        frame.iter().flat_map(|&pixel| self.rgb(pixel)).collect()
+    }
+
+    /// Convert a frame into 4 bytes per pixel, in RGBA order with full alpha.
+    pub fn to_rgba32(&self, frame: &[u16]) -> Vec<u8> {
+        frame.iter().flat_map(|&pixel| {
+            let [r, g, b] = self.rgb(pixel);
+            [r, g, b, 0xFF]
+        }).collect()
+    }
+
+    /// Convert a frame into one `0xAARRGGBB` word per pixel, as most window and texture APIs
+    /// expect.
+    pub fn to_argb(&self, frame: &[u16]) -> Vec<u32> {
+        frame.iter().map(|&pixel| self.argb(pixel)).collect()
+    }
+}
@@ -0,0 +1,796 @@
+pub mod registers;
+
+use crate::{cartridge::{Mirroring, Region}, mapper::Mapper, save_state::{SaveState, SaveStateError, StateReader, StateWriter}};
+use self::registers::{Control, Mask, Status};
+
+/// Width of the visible picture in pixels.
+pub const FRAME_WIDTH: usize = 256;
+/// Height of the visible picture in pixels.
+pub const FRAME_HEIGHT: usize = 240;
+/// Number of PPU clock cycles (dots) in a scanline.
+pub const DOTS_PER_SCANLINE: u16 = 341;
+/// Number of scanlines in an NTSC frame, including vertical blanking.
+pub const SCANLINES_PER_FRAME: u16 = 262;
+/// Number of scanlines in a PAL or Dendy frame, including vertical blanking.
+pub const SCANLINES_PER_FRAME_PAL: u16 = 312;
+/// Size of the primary object attribute memory, holding 64 sprites of 4 bytes each.
+pub const OAM_SIZE: usize = 256;
+
+/// The scanline on which vertical blanking starts.
+const VBLANK_SCANLINE: u16 = 241;
+/// The scanline on which vertical blanking starts on the Dendy, which has its extra scanlines
+/// before vertical blanking instead of after.
+const VBLANK_SCANLINE_DENDY: u16 = 291;
+/// Size of the nametable RAM. The NES only has 2 KiB of it, the other half is provided by
+/// cartridges that use four-screen mirroring.
+const NAMETABLE_RAM_SIZE: usize = 4 * 1024;
+/// Maximum number of sprites the PPU can draw on a single scanline.
+const SPRITES_PER_SCANLINE: usize = 8;
+
+/// The 2C02 Picture Processing Unit. Every call to `clock` simulates a single dot, fetching
+/// background tiles and sprites from the cartridge the same way the real PPU does, so that mappers
+/// watching the PPU's address bus see the same access patterns. The picture is written into a
+/// frame buffer of indices into the NES's 64 color system palette.
+/// Ref: https://www.nesdev.org/wiki/PPU
+#[derive(Debug)]
+pub struct Ppu {
+    // Registers
+
+    /// PPUCTRL ($2000)
+    pub ctrl: Control,
+    /// PPUMASK ($2001)
+    pub mask: Mask,
+    /// PPUSTATUS ($2002)
+    pub status: Status,
+    /// OAMADDR ($2003)
+    pub oam_addr: u8,
+    /// Object attribute memory, accessed through OAMDATA ($2004) or OAM DMA
+    pub oam: [u8; OAM_SIZE],
+
+    // Internal registers
+    // Ref: https://www.nesdev.org/wiki/PPU_scrolling
+
+    /// Current VRAM address (v)
+    vram_addr: u16,
+    /// Temporary VRAM address, the address of the top left tile on screen (t)
+    temp_addr: u16,
+    /// Fine X scroll (x)
+    fine_x: u8,
+    /// Selects the first or second write of PPUSCROLL and PPUADDR (w)
+    write_toggle: bool,
+    /// Data read through PPUDATA is delayed by one read
+    read_buffer: u8,
+    /// The value last written to or read from a register, returned for bits that aren't driven
+    open_bus: u8,
+    /// The address last put on the PPU's address bus
+    address_bus: u16,
+
+    // Memory
+
+    nametables: [u8; NAMETABLE_RAM_SIZE],
+    palette: [u8; 32],
+
+    // Background rendering
+
+    next_tile: u8,
+    next_attribute: u8,
+    next_pattern_lo: u8,
+    next_pattern_hi: u8,
+    pattern_shifter_lo: u16,
+    pattern_shifter_hi: u16,
+    attribute_shifter_lo: u16,
+    attribute_shifter_hi: u16,
+
+    // Sprite rendering
+
+    /// The sprites found on the current scanline, to be drawn on the next one
+    secondary_oam: [u8; SPRITES_PER_SCANLINE * 4],
+    sprite_count: usize,
+    /// Whether sprite 0 is the first sprite in secondary OAM
+    sprite_zero_on_line: bool,
+    sprite_pattern_lo: [u8; SPRITES_PER_SCANLINE],
+    sprite_pattern_hi: [u8; SPRITES_PER_SCANLINE],
+
+    // Timing
+
+    region: Region,
+    scanline: u16,
+    dot: u16,
+    frame_count: u64,
+    odd_frame: bool,
+    /// An NMI has been signalled but not yet picked up by the CPU
+    nmi_pending: bool,
+
+    frame_buffer: Box<[u16; FRAME_WIDTH * FRAME_HEIGHT]>,
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ppu {
+    pub fn new() -> Self {
+        Ppu {
+            ctrl: Control::empty(),
+            mask: Mask::empty(),
+            status: Status::empty(),
+            oam_addr: 0,
+            oam: [0x0; OAM_SIZE],
+            vram_addr: 0,
+            temp_addr: 0,
+            fine_x: 0,
+            write_toggle: false,
+            read_buffer: 0,
+            open_bus: 0,
+            address_bus: 0,
+            nametables: [0x0; NAMETABLE_RAM_SIZE],
+            palette: [0x0; 32],
+            next_tile: 0,
+            next_attribute: 0,
+            next_pattern_lo: 0,
+            next_pattern_hi: 0,
+            pattern_shifter_lo: 0,
+            pattern_shifter_hi: 0,
+            attribute_shifter_lo: 0,
+            attribute_shifter_hi: 0,
+            secondary_oam: [0xFF; SPRITES_PER_SCANLINE * 4],
+            sprite_count: 0,
+            sprite_zero_on_line: false,
+            sprite_pattern_lo: [0x0; SPRITES_PER_SCANLINE],
+            sprite_pattern_hi: [0x0; SPRITES_PER_SCANLINE],
+            region: Region::Ntsc,
+            scanline: 0,
+            dot: 0,
+            frame_count: 0,
+            odd_frame: false,
+            nmi_pending: false,
+            frame_buffer: Box::new([0x0; FRAME_WIDTH * FRAME_HEIGHT]),
+        }
+    }
+
+    /// Reset the PPU. Unlike the CPU, most of the PPU's state survives a reset.
+    pub fn reset(&mut self) {
+        self.ctrl = Control::empty();
+        self.mask = Mask::empty();
+        self.write_toggle = false;
+        self.read_buffer = 0;
+        self.scanline = 0;
+        self.dot = 0;
+        self.odd_frame = false;
+        self.nmi_pending = false;
+    }
+
+    /// Switch between the NTSC (2C02), PAL (2C07) and Dendy timings.
+    pub fn set_region(&mut self, region: Region) {
+        self.region = region;
+    }
+
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    /// Number of scanlines in a frame, including vertical blanking.
+    pub fn scanlines_per_frame(&self) -> u16 {
+        match self.region {
+            Region::Ntsc | Region::Multi => SCANLINES_PER_FRAME,
+            Region::Pal | Region::Dendy => SCANLINES_PER_FRAME_PAL,
+        }
+    }
+
+    /// The finished picture, as row-major indices into the system palette. Each pixel has the
+    /// emphasis bits in bits 6-8, see `palette::Palette`.
+    pub fn frame_buffer(&self) -> &[u16] {
+        &self.frame_buffer[..]
+    }
+
+    /// Number of frames completed so far. A frame is completed when vertical blanking starts.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// The scanline currently being processed, where 0-239 are visible.
+    pub fn scanline(&self) -> u16 {
+        self.scanline
+    }
+
+    /// The dot of the current scanline being processed.
+    pub fn dot(&self) -> u16 {
+        self.dot
+    }
+
+    /// Whether an NMI has been signalled since the last call. The NMI line of the CPU is edge
+    /// triggered, so taking the signal acknowledges it.
+    pub fn poll_nmi(&mut self) -> bool {
+        std::mem::take(&mut self.nmi_pending)
+    }
+
+    /// Read one of the 8 registers at $2000-$2007 from the CPU.
+    pub fn cpu_read(&mut self, register: u16, mapper: &mut Option<Box<dyn Mapper>>) -> u8 {
+        let data = match register & 0x7 {
+            2 => {
+                let data = self.peek_register(register);
+                self.status.remove(Status::VBLANK);
+                self.write_toggle = false;
+                data
+            },
+            4 => self.peek_register(register),
+            7 => {
+                let address = self.vram_addr & 0x3FFF;
+                let data = if address >= 0x3F00 {
+                    // Palette reads aren't buffered, but still fill the buffer with the nametable
+                    // byte "underneath" the palette.
+                    self.read_buffer = self.read(mapper, address - 0x1000);
+                    self.peek_register(register)
+                } else {
+                    let data = self.read_buffer;
+                    self.read_buffer = self.read(mapper, address);
+                    data
+                };
+                self.increment_vram_addr();
+                data
+            },
+            // The other registers are write only
+            _ => self.open_bus,
+        };
+
+        self.open_bus = data;
+        data
+    }
+
+    /// Read one of the 8 registers at $2000-$2007 without any side effects.
+    pub fn peek_register(&self, register: u16) -> u8 {
+        match register & 0x7 {
+            2 => self.status.bits() | (self.open_bus & 0x1F),
+            4 => {
+                // The unused bits of the sprite attributes don't exist
+                let data = self.oam[self.oam_addr as usize];
+                if self.oam_addr & 0x3 == 2 { data & 0xE3 } else { data }
+            },
+            7 if self.vram_addr & 0x3FFF >= 0x3F00 => {
+                // Greyscale applies to palette reads as well
+                let mut data = self.palette[palette_index(self.vram_addr)];
+                if self.mask.contains(Mask::GREYSCALE) {
+                    data &= 0x30;
+                }
+                (self.open_bus & 0xC0) | data
+            },
+            7 => self.read_buffer,
+            _ => self.open_bus,
+        }
+    }
+
+    /// Write to one of the 8 registers at $2000-$2007 from the CPU.
+    pub fn cpu_write(&mut self, register: u16, data: u8, mapper: &mut Option<Box<dyn Mapper>>) {
+        self.open_bus = data;
+
+        match register & 0x7 {
+            0 => {
+                let nmi_enabled = self.ctrl.contains(Control::NMI);
+                self.ctrl = Control::from_bits_truncate(data);
+                self.temp_addr = (self.temp_addr & !0x0C00) | ((data as u16 & 0x03) << 10);
+
+                // Enabling NMIs during vertical blanking immediately triggers one
+                if !nmi_enabled && self.ctrl.contains(Control::NMI) && self.status.contains(Status::VBLANK) {
+                    self.nmi_pending = true;
+                }
+            },
+            1 => self.mask = Mask::from_bits_truncate(data),
+            2 => {},
+            3 => self.oam_addr = data,
+            4 => self.write_oam(data),
+            5 => {
+                if !self.write_toggle {
+                    self.temp_addr = (self.temp_addr & !0x001F) | (data as u16 >> 3);
+                    self.fine_x = data & 0x07;
+                } else {
+                    self.temp_addr = (self.temp_addr & !0x73E0) | ((data as u16 & 0x07) << 12) | ((data as u16 & 0xF8) << 2);
+                }
+                self.write_toggle = !self.write_toggle;
+            },
+            6 => {
+                if !self.write_toggle {
+                    self.temp_addr = (self.temp_addr & 0x00FF) | ((data as u16 & 0x3F) << 8);
+                } else {
+                    self.temp_addr = (self.temp_addr & 0xFF00) | data as u16;
+                    self.vram_addr = self.temp_addr;
+                }
+                self.write_toggle = !self.write_toggle;
+            },
+            _ => {
+                self.write(mapper, self.vram_addr, data);
+                self.increment_vram_addr();
+            },
+        }
+    }
+
+    /// Write a byte to OAM at the current OAM address, as done by OAMDATA and OAM DMA.
+    pub fn write_oam(&mut self, data: u8) {
+        self.oam[self.oam_addr as usize] = data;
+        self.oam_addr = self.oam_addr.wrapping_add(1);
+    }
+
+    /// Simulates the passing of a single dot.
+    pub fn clock(&mut self, mapper: &mut Option<Box<dyn Mapper>>) {
+        let visible = self.scanline < FRAME_HEIGHT as u16;
+        let prerender = self.scanline == self.scanlines_per_frame() - 1;
+        let vblank = if self.region == Region::Dendy { VBLANK_SCANLINE_DENDY } else { VBLANK_SCANLINE };
+
+        if prerender && self.dot == 1 {
+            self.status.remove(Status::VBLANK | Status::SPRITE_ZERO_HIT | Status::SPRITE_OVERFLOW);
+        }
+
+        if (visible || prerender) && self.rendering_enabled() {
+            self.fetch(mapper, prerender);
+        } else {
+            // While idle, the current VRAM address is left on the address bus
+            self.address_bus = self.vram_addr & 0x3FFF;
+        }
+
+        if visible && (1..=FRAME_WIDTH as u16).contains(&self.dot) {
+            self.render_pixel();
+        }
+
+        if self.scanline == vblank && self.dot == 1 {
+            self.status.insert(Status::VBLANK);
+            if self.ctrl.contains(Control::NMI) {
+                self.nmi_pending = true;
+            }
+            self.frame_count += 1;
+        }
+
+        if let Some(mapper) = mapper.as_mut() {
+            if self.dot == 0 {
+                mapper.scanline(self.scanline);
+            }
+            mapper.ppu_clock(self.address_bus);
+        }
+
+        self.dot += 1;
+
+        // On odd NTSC frames the idle dot at the end of the pre-render scanline is skipped
+        let skip = prerender
+            && self.dot == DOTS_PER_SCANLINE - 1
+            && self.odd_frame
+            && self.rendering_enabled()
+            && self.scanlines_per_frame() == SCANLINES_PER_FRAME;
+        if self.dot == DOTS_PER_SCANLINE || skip {
+            self.dot = 0;
+            self.scanline += 1;
+
+            if self.scanline == self.scanlines_per_frame() {
+                self.scanline = 0;
+                self.odd_frame = !self.odd_frame;
+            }
+        }
+    }
+
+    fn rendering_enabled(&self) -> bool {
+        self.mask.intersects(Mask::SHOW_BACKGROUND | Mask::SHOW_SPRITES)
+    }
+
+    /// Perform the memory accesses for the current dot of a visible or pre-render scanline.
+    /// Ref: https://www.nesdev.org/wiki/PPU_rendering
+    fn fetch(&mut self, mapper: &mut Option<Box<dyn Mapper>>, prerender: bool) {
+        let dot = self.dot;
+
+        if (2..=257).contains(&dot) || (321..=337).contains(&dot) {
+            self.shift_background();
+
+            match (dot - 1) % 8 {
+                0 => {
+                    self.load_background_shifters();
+                    self.next_tile = self.read(mapper, 0x2000 | (self.vram_addr & 0x0FFF));
+                },
+                2 => {
+                    let v = self.vram_addr;
+                    let address = 0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07);
+                    let mut attribute = self.read(mapper, address);
+                    // Each attribute byte covers 4x4 tiles, pick the 2x2 quadrant we're in
+                    if v & 0x0040 != 0 {
+                        attribute >>= 4;
+                    }
+                    if v & 0x0002 != 0 {
+                        attribute >>= 2;
+                    }
+                    self.next_attribute = attribute & 0x03;
+                },
+                4 => self.next_pattern_lo = self.read(mapper, self.background_pattern_address()),
+                6 => self.next_pattern_hi = self.read(mapper, self.background_pattern_address() + 8),
+                7 => self.increment_x(),
+                _ => {},
+            }
+        }
+
+        match dot {
+            256 => self.increment_y(),
+            257 => {
+                self.load_background_shifters();
+                self.transfer_x();
+                self.oam_addr = 0;
+                if prerender {
+                    self.secondary_oam = [0xFF; SPRITES_PER_SCANLINE * 4];
+                    self.sprite_count = 0;
+                    self.sprite_zero_on_line = false;
+                } else {
+                    self.evaluate_sprites();
+                }
+            },
+            280..=304 if prerender => self.transfer_y(),
+            // Unused nametable fetches at the end of the scanline
+            337 | 339 => self.next_tile = self.read(mapper, 0x2000 | (self.vram_addr & 0x0FFF)),
+            _ => {},
+        }
+
+        if (257..=320).contains(&dot) {
+            let sprite = (dot as usize - 257) / 8;
+            match (dot - 257) % 8 {
+                // Garbage nametable fetches
+                0 | 2 => {
+                    self.read(mapper, 0x2000 | (self.vram_addr & 0x0FFF));
+                },
+                4 => {
+                    let address = self.sprite_pattern_address(sprite);
+                    let pattern = self.read(mapper, address);
+                    self.sprite_pattern_lo[sprite] = self.flip_sprite(sprite, pattern);
+                },
+                6 => {
+                    let address = self.sprite_pattern_address(sprite) + 8;
+                    let pattern = self.read(mapper, address);
+                    self.sprite_pattern_hi[sprite] = self.flip_sprite(sprite, pattern);
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn background_pattern_address(&self) -> u16 {
+        let table = if self.ctrl.contains(Control::BACKGROUND_TABLE) { 0x1000 } else { 0x0000 };
+        table | ((self.next_tile as u16) << 4) | ((self.vram_addr >> 12) & 0x07)
+    }
+
+    fn sprite_height(&self) -> u16 {
+        if self.ctrl.contains(Control::SPRITE_SIZE) { 16 } else { 8 }
+    }
+
+    /// Find the first 8 sprites on the current scanline and copy them into secondary OAM, to be
+    /// drawn on the next scanline. Setting the overflow flag is done with the same bug as the real
+    /// hardware, which increments both the sprite and the byte index after finding 8 sprites.
+    /// Ref: https://www.nesdev.org/wiki/PPU_sprite_evaluation
+    fn evaluate_sprites(&mut self) {
+        let height = self.sprite_height();
+        let scanline = self.scanline;
+        let in_range = |y: u8| scanline.wrapping_sub(y as u16) < height;
+
+        self.secondary_oam = [0xFF; SPRITES_PER_SCANLINE * 4];
+        self.sprite_count = 0;
+        self.sprite_zero_on_line = false;
+
+        let mut sprite = 0;
+        while sprite < 64 && self.sprite_count < SPRITES_PER_SCANLINE {
+            if in_range(self.oam[sprite * 4]) {
+                let slot = self.sprite_count * 4;
+                self.secondary_oam[slot..slot + 4].copy_from_slice(&self.oam[sprite * 4..sprite * 4 + 4]);
+                self.sprite_zero_on_line |= sprite == 0;
+                self.sprite_count += 1;
+            }
+            sprite += 1;
+        }
+
+        let mut byte = 0;
+        while sprite < 64 {
+            if in_range(self.oam[sprite * 4 + byte]) {
+                self.status.insert(Status::SPRITE_OVERFLOW);
+                break;
+            }
+            sprite += 1;
+            byte = (byte + 1) & 0x03;
+        }
+    }
+
+    fn sprite_pattern_address(&self, sprite: usize) -> u16 {
+        // Empty slots fetch tile $FF
+        let (y, tile, attributes) = if sprite < self.sprite_count {
+            let entry = &self.secondary_oam[sprite * 4..sprite * 4 + 4];
+            (entry[0], entry[1], entry[2])
+        } else {
+            (self.scanline as u8, 0xFF, 0x00)
+        };
+
+        let height = self.sprite_height();
+        let mut row = self.scanline.wrapping_sub(y as u16) & (height - 1);
+        if attributes & 0x80 != 0 {
+            row = height - 1 - row;
+        }
+
+        if height == 16 {
+            // 8x16 sprites select their pattern table with the lowest bit of the tile number
+            let table = (tile as u16 & 1) << 12;
+            let tile = (tile as u16 & 0xFE) + (row >> 3);
+            table | (tile << 4) | (row & 0x07)
+        } else {
+            let table = if self.ctrl.contains(Control::SPRITE_TABLE) { 0x1000 } else { 0x0000 };
+            table | ((tile as u16) << 4) | row
+        }
+    }
+
+    fn flip_sprite(&self, sprite: usize, pattern: u8) -> u8 {
+        if sprite < self.sprite_count && self.secondary_oam[sprite * 4 + 2] & 0x40 != 0 {
+            pattern.reverse_bits()
+        } else {
+            pattern
+        }
+    }
+
+    fn shift_background(&mut self) {
+        self.pattern_shifter_lo <<= 1;
+        self.pattern_shifter_hi <<= 1;
+        self.attribute_shifter_lo <<= 1;
+        self.attribute_shifter_hi <<= 1;
+    }
+
+    fn load_background_shifters(&mut self) {
+        self.pattern_shifter_lo = (self.pattern_shifter_lo & 0xFF00) | self.next_pattern_lo as u16;
+        self.pattern_shifter_hi = (self.pattern_shifter_hi & 0xFF00) | self.next_pattern_hi as u16;
+        let attribute_lo = if self.next_attribute & 0x01 != 0 { 0xFF } else { 0x00 };
+        let attribute_hi = if self.next_attribute & 0x02 != 0 { 0xFF } else { 0x00 };
+        self.attribute_shifter_lo = (self.attribute_shifter_lo & 0xFF00) | attribute_lo;
+        self.attribute_shifter_hi = (self.attribute_shifter_hi & 0xFF00) | attribute_hi;
+    }
+
+    fn render_pixel(&mut self) {
+        let x = self.dot as usize - 1;
+
+        let mut background = 0;
+        if self.mask.contains(Mask::SHOW_BACKGROUND) && (x >= 8 || self.mask.contains(Mask::SHOW_BACKGROUND_LEFT)) {
+            let bit = 0x8000 >> self.fine_x;
+            let pixel = (((self.pattern_shifter_hi & bit) != 0) as u8) << 1 | ((self.pattern_shifter_lo & bit) != 0) as u8;
+            let palette = (((self.attribute_shifter_hi & bit) != 0) as u8) << 1 | ((self.attribute_shifter_lo & bit) != 0) as u8;
+            if pixel != 0 {
+                background = palette << 2 | pixel;
+            }
+        }
+
+        // The first opaque sprite pixel wins, regardless of its priority
+        let mut sprite = None;
+        if self.mask.contains(Mask::SHOW_SPRITES) && (x >= 8 || self.mask.contains(Mask::SHOW_SPRITES_LEFT)) {
+            sprite = (0..self.sprite_count).find_map(|index| {
+                let offset = x.wrapping_sub(self.secondary_oam[index * 4 + 3] as usize);
+                if offset >= 8 {
+                    return None;
+                }
+
+                let bit = 7 - offset;
+                let pixel = ((self.sprite_pattern_hi[index] >> bit) & 1) << 1 | ((self.sprite_pattern_lo[index] >> bit) & 1);
+                let attributes = self.secondary_oam[index * 4 + 2];
+                (pixel != 0).then_some((index, 0x10 | (attributes & 0x03) << 2 | pixel, attributes & 0x20 != 0))
+            });
+        }
+
+        let color = match (background, sprite) {
+            (0, None) => 0,
+            (0, Some((_, sprite, _))) => sprite,
+            (_, None) => background,
+            (_, Some((index, sprite, behind))) => {
+                if index == 0 && self.sprite_zero_on_line && x != 255 {
+                    self.status.insert(Status::SPRITE_ZERO_HIT);
+                }
+                if behind { background } else { sprite }
+            },
+        };
+
+        // With rendering disabled, the backdrop color is displayed, unless the VRAM address points
+        // into the palette
+        let address = if !self.rendering_enabled() && self.vram_addr & 0x3F00 == 0x3F00 {
+            self.vram_addr
+        } else {
+            0x3F00 | color as u16
+        };
+
+        let mut color = self.palette[palette_index(address)] & 0x3F;
+        if self.mask.contains(Mask::GREYSCALE) {
+            color &= 0x30;
+        }
+
+        // PAL PPUs swap the red and green emphasis bits, store them in the same order regardless
+        let mut emphasis = self.mask.bits() >> 5;
+        if matches!(self.region, Region::Pal | Region::Dendy) {
+            emphasis = (emphasis & 0b100) | (emphasis & 0b01) << 1 | (emphasis & 0b10) >> 1;
+        }
+
+        self.frame_buffer[self.scanline as usize * FRAME_WIDTH + x] = color as u16 | (emphasis as u16) << 6;
+    }
+
+    fn increment_vram_addr(&mut self) {
+        let increment = if self.ctrl.contains(Control::INCREMENT) { 32 } else { 1 };
+        self.vram_addr = self.vram_addr.wrapping_add(increment) & 0x7FFF;
+    }
+
+    /// Move to the next tile horizontally, wrapping into the next nametable.
+    fn increment_x(&mut self) {
+        if self.vram_addr & 0x001F == 31 {
+            self.vram_addr &= !0x001F;
+            self.vram_addr ^= 0x0400;
+        } else {
+            self.vram_addr += 1;
+        }
+    }
+
+    /// Move to the next row of pixels, wrapping into the next nametable after 30 rows of tiles.
+    fn increment_y(&mut self) {
+        if self.vram_addr & 0x7000 != 0x7000 {
+            self.vram_addr += 0x1000;
+            return;
+        }
+
+        self.vram_addr &= !0x7000;
+        let coarse_y = match (self.vram_addr & 0x03E0) >> 5 {
+            29 => {
+                self.vram_addr ^= 0x0800;
+                0
+            },
+            // Rows 30 and 31 are the attribute table, and wrap without switching nametables
+            31 => 0,
+            coarse_y => coarse_y + 1,
+        };
+        self.vram_addr = (self.vram_addr & !0x03E0) | (coarse_y << 5);
+    }
+
+    fn transfer_x(&mut self) {
+        self.vram_addr = (self.vram_addr & !0x041F) | (self.temp_addr & 0x041F);
+    }
+
+    fn transfer_y(&mut self) {
+        self.vram_addr = (self.vram_addr & !0x7BE0) | (self.temp_addr & 0x7BE0);
+    }
+
+    /// Read from the PPU's address space.
+    /// Ref: https://www.nesdev.org/wiki/PPU_memory_map
+    fn read(&mut self, mapper: &mut Option<Box<dyn Mapper>>, address: u16) -> u8 {
+        let address = address & 0x3FFF;
+        self.address_bus = address;
+
+        match address {
+            0x0000..=0x1FFF => mapper.as_mut().map_or(0x0, |mapper| mapper.ppu_read(address)),
+            0x2000..=0x3EFF => self.nametables[nametable_index(mapper, address)],
+            _ => self.palette[palette_index(address)],
+        }
+    }
+
+    /// Write to the PPU's address space.
+    fn write(&mut self, mapper: &mut Option<Box<dyn Mapper>>, address: u16, data: u8) {
+        let address = address & 0x3FFF;
+        self.address_bus = address;
+
+        match address {
+            0x0000..=0x1FFF => {
+                if let Some(mapper) = mapper.as_mut() {
+                    mapper.ppu_write(address, data);
+                }
+            },
+            0x2000..=0x3EFF => self.nametables[nametable_index(mapper, address)] = data,
+            _ => self.palette[palette_index(address)] = data & 0x3F,
+        }
+    }
+}
+
+/// Map an address in $2000-$3EFF to the nametable RAM, following the mirroring of the cartridge.
+/// Ref: https://www.nesdev.org/wiki/Mirroring
+fn nametable_index(mapper: &Option<Box<dyn Mapper>>, address: u16) -> usize {
+    let mirroring = mapper.as_ref().map_or(Mirroring::Horizontal, |mapper| mapper.mirroring());
+    let address = address as usize & 0x0FFF;
+
+    match mirroring {
+        Mirroring::Horizontal => ((address >> 1) & 0x0400) | (address & 0x03FF),
+        Mirroring::Vertical => address & 0x07FF,
+        Mirroring::SingleScreenLower => address & 0x03FF,
+        Mirroring::SingleScreenUpper => 0x0400 | (address & 0x03FF),
+        Mirroring::FourScreen => address,
+    }
+}
+
+/// Map an address in $3F00-$3FFF to the palette RAM. The backdrop entries of the sprite palettes
+/// are mirrors of those of the background palettes.
+fn palette_index(address: u16) -> usize {
+    let index = address as usize & 0x1F;
+    if index & 0x13 == 0x10 { index & 0x0F } else { index }
+}
+
+/// The region isn't saved, it's configured through `set_region`.
+impl SaveState for Ppu {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.ctrl.bits());
+        writer.write_u8(self.mask.bits());
+        writer.write_u8(self.status.bits());
+        writer.write_u8(self.oam_addr);
+        writer.write_bytes(&self.oam);
+
+        writer.write_u16(self.vram_addr);
+        writer.write_u16(self.temp_addr);
+        writer.write_u8(self.fine_x);
+        writer.write_bool(self.write_toggle);
+        writer.write_u8(self.read_buffer);
+        writer.write_u8(self.open_bus);
+        writer.write_u16(self.address_bus);
+
+        writer.write_bytes(&self.nametables);
+        writer.write_bytes(&self.palette);
+
+        writer.write_u8(self.next_tile);
+        writer.write_u8(self.next_attribute);
+        writer.write_u8(self.next_pattern_lo);
+        writer.write_u8(self.next_pattern_hi);
+        writer.write_u16(self.pattern_shifter_lo);
+        writer.write_u16(self.pattern_shifter_hi);
+        writer.write_u16(self.attribute_shifter_lo);
+        writer.write_u16(self.attribute_shifter_hi);
+
+        writer.write_bytes(&self.secondary_oam);
+        writer.write_u8(self.sprite_count as u8);
+        writer.write_bool(self.sprite_zero_on_line);
+        writer.write_bytes(&self.sprite_pattern_lo);
+        writer.write_bytes(&self.sprite_pattern_hi);
+
+        writer.write_u16(self.scanline);
+        writer.write_u16(self.dot);
+        writer.write_u64(self.frame_count);
+        writer.write_bool(self.odd_frame);
+        writer.write_bool(self.nmi_pending);
+
+        for &pixel in self.frame_buffer.iter() {
+            writer.write_u16(pixel);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.ctrl = Control::from_bits_truncate(reader.read_u8()?);
+        self.mask = Mask::from_bits_truncate(reader.read_u8()?);
+        self.status = Status::from_bits_truncate(reader.read_u8()?);
+        self.oam_addr = reader.read_u8()?;
+        reader.read_bytes(&mut self.oam)?;
+
+        self.vram_addr = reader.read_u16()? & 0x7FFF;
+        self.temp_addr = reader.read_u16()? & 0x7FFF;
+        self.fine_x = reader.read_u8()? & 0x07;
+        self.write_toggle = reader.read_bool()?;
+        self.read_buffer = reader.read_u8()?;
+        self.open_bus = reader.read_u8()?;
+        self.address_bus = reader.read_u16()?;
+
+        reader.read_bytes(&mut self.nametables)?;
+        reader.read_bytes(&mut self.palette)?;
+
+        self.next_tile = reader.read_u8()?;
+        self.next_attribute = reader.read_u8()?;
+        self.next_pattern_lo = reader.read_u8()?;
+        self.next_pattern_hi = reader.read_u8()?;
+        self.pattern_shifter_lo = reader.read_u16()?;
+        self.pattern_shifter_hi = reader.read_u16()?;
+        self.attribute_shifter_lo = reader.read_u16()?;
+        self.attribute_shifter_hi = reader.read_u16()?;
+
+        reader.read_bytes(&mut self.secondary_oam)?;
+        self.sprite_count = reader.read_u8()? as usize;
+        if self.sprite_count > SPRITES_PER_SCANLINE {
+            return Err(SaveStateError::Malformed("too many sprites on a scanline"));
+        }
+        self.sprite_zero_on_line = reader.read_bool()?;
+        reader.read_bytes(&mut self.sprite_pattern_lo)?;
+        reader.read_bytes(&mut self.sprite_pattern_hi)?;
+
+        self.scanline = reader.read_u16()?;
+        self.dot = reader.read_u16()?;
+        if self.scanline >= SCANLINES_PER_FRAME_PAL || self.dot >= DOTS_PER_SCANLINE {
+            return Err(SaveStateError::Malformed("PPU position out of range"));
+        }
+        self.frame_count = reader.read_u64()?;
+        self.odd_frame = reader.read_bool()?;
+        self.nmi_pending = reader.read_bool()?;
+
+        for pixel in self.frame_buffer.iter_mut() {
+            *pixel = reader.read_u16()?;
+        }
+        Ok(())
+    }
+}
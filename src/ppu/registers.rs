@@ -0,0 +1,57 @@
+use bitflags::bitflags;
+
+bitflags! {
+    /// PPUCTRL ($2000)
+    pub struct Control: u8 {
+        /// Base nametable, horizontal bit
+        const NAMETABLE_X = 1;
+        /// Base nametable, vertical bit
+        const NAMETABLE_Y = 1 << 1;
+        /// Increment the VRAM address by 32 instead of 1 after each access of PPUDATA
+        const INCREMENT = 1 << 2;
+        /// 8x8 sprites use the pattern table at $1000
+        const SPRITE_TABLE = 1 << 3;
+        /// The background uses the pattern table at $1000
+        const BACKGROUND_TABLE = 1 << 4;
+        /// Use 8x16 sprites
+        const SPRITE_SIZE = 1 << 5;
+        /// PPU master/slave select (Unused, as the EXT pins are grounded on the NES)
+        const MASTER_SLAVE = 1 << 6;
+        /// Generate an NMI at the start of vertical blanking
+        const NMI = 1 << 7;
+    }
+}
+
+bitflags! {
+    /// PPUMASK ($2001)
+    pub struct Mask: u8 {
+        /// Only output the grey column of the palette
+        const GREYSCALE = 1;
+        /// Show the background in the leftmost 8 pixels of the screen
+        const SHOW_BACKGROUND_LEFT = 1 << 1;
+        /// Show sprites in the leftmost 8 pixels of the screen
+        const SHOW_SPRITES_LEFT = 1 << 2;
+        /// Render the background
+        const SHOW_BACKGROUND = 1 << 3;
+        /// Render sprites
+        const SHOW_SPRITES = 1 << 4;
+        /// Emphasize red (green on PAL)
+        const EMPHASIZE_RED = 1 << 5;
+        /// Emphasize green (red on PAL)
+        const EMPHASIZE_GREEN = 1 << 6;
+        /// Emphasize blue
+        const EMPHASIZE_BLUE = 1 << 7;
+    }
+}
+
+bitflags! {
+    /// PPUSTATUS ($2002). The lower 5 bits aren't driven and return whatever was last on the bus.
+    pub struct Status: u8 {
+        /// More than 8 sprites were found on a scanline, including the hardware's false positives
+        const SPRITE_OVERFLOW = 1 << 5;
+        /// An opaque pixel of sprite 0 overlapped an opaque background pixel
+        const SPRITE_ZERO_HIT = 1 << 6;
+        /// Vertical blanking has started
+        const VBLANK = 1 << 7;
+    }
+}
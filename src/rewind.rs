@@ -0,0 +1,195 @@
+use std::{collections::VecDeque, io::{Read, Write}};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use crate::controller::ButtonState;
+
+/// How often the rewind buffer takes snapshots and how many it keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RewindConfig {
+    /// Frames between snapshots. Rewinding to a frame in between replays the recorded input from
+    /// the snapshot before it.
+    pub interval: u32,
+    /// Maximum number of snapshots kept. Once it's reached, the oldest keyframe is dropped along
+    /// with all snapshots stored as deltas against it.
+    pub capacity: usize,
+    /// Number of snapshots that share a keyframe, including the keyframe itself
+    pub keyframe_interval: usize,
+}
+
+impl Default for RewindConfig {
+    /// About 10 seconds of NTSC gameplay, with a snapshot every 6th of a second.
+    fn default() -> Self {
+        RewindConfig { interval: 10, capacity: 60, keyframe_interval: 15 }
+    }
+}
+
+/// A snapshot of the console, stored as the compressed XOR of its save state and the keyframe of
+/// its segment, which makes the parts that didn't change compress to almost nothing.
+#[derive(Debug)]
+struct Snapshot {
+    /// The frame count at which the snapshot was taken
+    frame: u64,
+    delta: Vec<u8>,
+    /// The buttons held on both controllers during each frame following the snapshot
+    inputs: Vec<[ButtonState; 2]>,
+}
+
+/// A keyframe and the snapshots stored as deltas against it. The first snapshot is the keyframe
+/// itself.
+#[derive(Debug)]
+struct Segment {
+    /// The compressed save state of the keyframe
+    keyframe: Vec<u8>,
+    snapshots: Vec<Snapshot>,
+}
+
+/// A ring buffer of periodic save states that allows stepping the console back frame by frame. The
+/// console feeds it through `Nes::enable_rewind` and rewinds with `Nes::rewind_frames`.
+#[derive(Debug)]
+pub struct RewindBuffer {
+    config: RewindConfig,
+    segments: VecDeque<Segment>,
+    /// The uncompressed keyframe of the last segment, which new snapshots are compared against
+    keyframe: Vec<u8>,
+    len: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(config: RewindConfig) -> Self {
+        RewindBuffer {
+            config: RewindConfig {
+                interval: config.interval.max(1),
+                capacity: config.capacity.max(1),
+                keyframe_interval: config.keyframe_interval.clamp(1, config.capacity.max(1)),
+            },
+            segments: VecDeque::new(),
+            keyframe: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn config(&self) -> RewindConfig {
+        self.config
+    }
+
+    /// Number of snapshots in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Drop all snapshots.
+    pub fn clear(&mut self) {
+        self.segments.clear();
+        self.keyframe.clear();
+        self.len = 0;
+    }
+
+    /// The frame of the oldest snapshot, as far back as the console can be rewound.
+    pub fn oldest_frame(&self) -> Option<u64> {
+        self.segments.front().map(|segment| segment.snapshots[0].frame)
+    }
+
+    /// Approximate number of bytes taken up by the snapshots and the recorded input.
+    pub fn memory_usage(&self) -> usize {
+        let snapshots: usize = self.segments.iter()
+            .flat_map(|segment| &segment.snapshots)
+            .map(|snapshot| snapshot.delta.len() + snapshot.inputs.len() * 2)
+            .sum();
+        let keyframes: usize = self.segments.iter().map(|segment| segment.keyframe.len()).sum();
+
+        snapshots + keyframes + self.keyframe.len()
+    }
+
+    /// Record the buttons that were held during the frame that just finished.
+    pub fn record_input(&mut self, buttons: [ButtonState; 2]) {
+        if let Some(snapshot) = self.segments.back_mut().and_then(|segment| segment.snapshots.last_mut()) {
+            snapshot.inputs.push(buttons);
+        }
+    }
+
+    /// Whether enough frames have passed since the last snapshot to take a new one.
+    pub fn snapshot_due(&self) -> bool {
+        match self.segments.back().and_then(|segment| segment.snapshots.last()) {
+            Some(snapshot) => snapshot.inputs.len() >= self.config.interval as usize,
+            None => true,
+        }
+    }
+
+    /// Add a snapshot of the console at `frame`, dropping the oldest ones if the buffer is full.
+    pub fn push(&mut self, frame: u64, state: &[u8]) {
+        let new_segment = match self.segments.back() {
+            Some(segment) => {
+                segment.snapshots.len() >= self.config.keyframe_interval || state.len() != self.keyframe.len()
+            },
+            None => true,
+        };
+
+        if new_segment {
+            self.keyframe = state.to_vec();
+            self.segments.push_back(Segment { keyframe: compress(state), snapshots: Vec::new() });
+        }
+
+        let delta = compress(&xor(state, &self.keyframe));
+        let segment = self.segments.back_mut().unwrap();
+        segment.snapshots.push(Snapshot { frame, delta, inputs: Vec::new() });
+        self.len += 1;
+
+        while self.len > self.config.capacity && self.segments.len() > 1 {
+            let segment = self.segments.pop_front().unwrap();
+            self.len -= segment.snapshots.len();
+        }
+    }
+
+    /// Go back to the latest snapshot at or before `frame`, dropping everything recorded after
+    /// `frame`. Returns the save state of that snapshot and the input to replay to get from there
+    /// to `frame`, or `None` if `frame` is older than the oldest snapshot.
+    pub fn rewind_to(&mut self, frame: u64) -> Option<(Vec<u8>, Vec<[ButtonState; 2]>)> {
+        if self.oldest_frame()? > frame {
+            return None;
+        }
+
+        // Drop the snapshots taken after the target frame
+        let mut keyframe_dropped = false;
+        while let Some(segment) = self.segments.back_mut() {
+            while segment.snapshots.last().is_some_and(|snapshot| snapshot.frame > frame) {
+                segment.snapshots.pop();
+                self.len -= 1;
+            }
+            if !segment.snapshots.is_empty() {
+                break;
+            }
+            self.segments.pop_back();
+            keyframe_dropped = true;
+        }
+
+        let segment = self.segments.back_mut()?;
+        if keyframe_dropped {
+            self.keyframe = decompress(&segment.keyframe);
+        }
+        let snapshot = segment.snapshots.last_mut()?;
+        snapshot.inputs.truncate((frame - snapshot.frame) as usize);
+
+        Some((xor(&decompress(&snapshot.delta), &self.keyframe), snapshot.inputs.clone()))
+    }
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+    // Writing into a Vec can't fail
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn decompress(data: &[u8]) -> Vec<u8> {
+    let mut decompressed = Vec::new();
+    // The data was compressed by `compress`, so it's valid
+    DeflateDecoder::new(data).read_to_end(&mut decompressed).unwrap();
+    decompressed
+}
@@ -0,0 +1,176 @@
+use std::{fmt, fs, path::Path};
+use sha1::{Digest, Sha1};
+use crate::{
+    cartridge::{CartridgeError, Header, HeaderFormat, HEADER_SIZE, TRAINER_SIZE},
+    mapper::{mapper_name, SUPPORTED_MAPPERS},
+};
+
+/// Something about a ROM file that doesn't look right, even though its header could be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderAnomaly {
+    /// The unused bytes at the end of an iNES header contain garbage, usually the signature of
+    /// an old dumping tool like "DiskDude!". The upper nibble of the mapper number is ignored.
+    DirtyHeader {
+        /// The printable characters found in bytes 7-15 of the header
+        signature: String,
+    },
+    /// The header claims there's no PRG-ROM, which no cartridge can run without.
+    NoPrgRom,
+    /// The file is shorter than the header says it should be.
+    Truncated { expected: usize, found: usize },
+    /// The file contains more data than the header accounts for.
+    TrailingData(usize),
+    /// The NES 2.0 ROM sizes add up to more than can be addressed.
+    RomSizeOverflow,
+    /// The PRG-ROM or CHR-ROM size isn't a multiple of 8 KiB, which no known board uses.
+    OddRomSize,
+    /// The mapper isn't implemented, so the ROM can't be run.
+    UnsupportedMapper(u16),
+}
+
+impl fmt::Display for HeaderAnomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderAnomaly::DirtyHeader { signature } => {
+                write!(f, "dirty header padding ({:?}), mapper high nibble ignored", signature)
+            },
+            HeaderAnomaly::NoPrgRom => write!(f, "header specifies no PRG-ROM"),
+            HeaderAnomaly::Truncated { expected, found } => {
+                write!(f, "file is truncated: expected {} bytes, found {}", expected, found)
+            },
+            HeaderAnomaly::TrailingData(len) => write!(f, "{} bytes of trailing data after the ROM", len),
+            HeaderAnomaly::RomSizeOverflow => write!(f, "ROM sizes add up to more than can be addressed"),
+            HeaderAnomaly::OddRomSize => write!(f, "ROM size is not a multiple of 8 KiB"),
+            HeaderAnomaly::UnsupportedMapper(mapper) => write!(f, "mapper {} is not supported", mapper),
+        }
+    }
+}
+
+/// The CRC32 and SHA-1 of a chunk of ROM data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomHashes {
+    pub crc32: u32,
+    /// The SHA-1 as a lowercase hex string
+    pub sha1: String,
+}
+
+impl RomHashes {
+    pub fn of(data: &[u8]) -> Self {
+        let sha1 = Sha1::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect();
+        RomHashes { crc32: crc32fast::hash(data), sha1 }
+    }
+}
+
+/// Everything there is to know about a ROM file without running it: its parsed header, hashes of
+/// its contents as used by ROM databases, and anything suspicious about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomInfo {
+    pub header: Header,
+    /// The common name of the mapper, if it's a well-known one
+    pub mapper_name: Option<&'static str>,
+    /// Hashes of the PRG-ROM, or whatever part of it is in the file
+    pub prg: RomHashes,
+    /// Hashes of the CHR-ROM, if the cartridge has any
+    pub chr: Option<RomHashes>,
+    /// Hashes of the PRG-ROM and CHR-ROM together, excluding the header and trainer
+    pub rom: RomHashes,
+    pub anomalies: Vec<HeaderAnomaly>,
+}
+
+impl RomInfo {
+    /// Inspect an iNES or NES 2.0 file on disk.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CartridgeError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Inspect the full contents of an iNES or NES 2.0 file. Only a missing or incomplete header
+    /// is an error, problems with the rest of the file are reported as anomalies.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CartridgeError> {
+        let header = Header::parse(bytes)?;
+        let mut anomalies = Vec::new();
+
+        if header.format == HeaderFormat::INes && bytes[12..HEADER_SIZE].iter().any(|&b| b != 0) {
+            let signature = bytes[7..HEADER_SIZE].iter()
+                .filter(|byte| byte.is_ascii_graphic())
+                .map(|&byte| byte as char)
+                .collect();
+            anomalies.push(HeaderAnomaly::DirtyHeader { signature });
+        }
+        if header.prg_rom_size == 0 {
+            anomalies.push(HeaderAnomaly::NoPrgRom);
+        }
+        if header.prg_rom_size % 0x2000 != 0 || header.chr_rom_size % 0x2000 != 0 {
+            anomalies.push(HeaderAnomaly::OddRomSize);
+        }
+
+        let prg_start = HEADER_SIZE + if header.trainer { TRAINER_SIZE } else { 0 };
+        // NES 2.0 exponent sizes can overflow, the sections below are clamped to the file anyway
+        let chr_start = prg_start.saturating_add(header.prg_rom_size);
+        let expected = chr_start.saturating_add(header.chr_rom_size);
+        if expected == usize::MAX {
+            anomalies.push(HeaderAnomaly::RomSizeOverflow);
+        } else if bytes.len() < expected {
+            anomalies.push(HeaderAnomaly::Truncated { expected, found: bytes.len() });
+        } else if bytes.len() > expected {
+            anomalies.push(HeaderAnomaly::TrailingData(bytes.len() - expected));
+        }
+        if !SUPPORTED_MAPPERS.contains(&header.mapper) {
+            anomalies.push(HeaderAnomaly::UnsupportedMapper(header.mapper));
+        }
+
+        let section = |start: usize, end: usize| &bytes[start.min(bytes.len())..end.min(bytes.len())];
+
+        Ok(RomInfo {
+            mapper_name: mapper_name(header.mapper),
+            prg: RomHashes::of(section(prg_start, chr_start)),
+            chr: (header.chr_rom_size > 0).then(|| RomHashes::of(section(chr_start, expected))),
+            rom: RomHashes::of(section(prg_start, expected)),
+            header,
+            anomalies,
+        })
+    }
+}
+
+impl fmt::Display for RomInfo {
+    /// A human readable report, one field per line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = &self.header;
+        let yes_no = |value| if value { "yes" } else { "no" };
+
+        writeln!(f, "Format:      {}", match header.format {
+            HeaderFormat::INes => "iNES",
+            HeaderFormat::Nes2 => "NES 2.0",
+        })?;
+        write!(f, "Mapper:      {}", header.mapper)?;
+        if let Some(name) = self.mapper_name {
+            write!(f, " ({})", name)?;
+        }
+        writeln!(f, ", submapper {}", header.submapper)?;
+        writeln!(f, "PRG-ROM:     {} KiB", header.prg_rom_size / 1024)?;
+        writeln!(f, "CHR-ROM:     {} KiB", header.chr_rom_size / 1024)?;
+        writeln!(f, "PRG-RAM:     {} bytes, {} bytes non-volatile", header.prg_ram_size, header.prg_nvram_size)?;
+        writeln!(f, "CHR-RAM:     {} bytes, {} bytes non-volatile", header.chr_ram_size, header.chr_nvram_size)?;
+        writeln!(f, "Mirroring:   {:?}", header.mirroring)?;
+        writeln!(f, "Battery:     {}", yes_no(header.battery))?;
+        writeln!(f, "Trainer:     {}", yes_no(header.trainer))?;
+        writeln!(f, "Region:      {:?}", header.region)?;
+        writeln!(f, "PRG CRC32:   {:08X}", self.prg.crc32)?;
+        writeln!(f, "PRG SHA-1:   {}", self.prg.sha1)?;
+        if let Some(chr) = &self.chr {
+            writeln!(f, "CHR CRC32:   {:08X}", chr.crc32)?;
+            writeln!(f, "CHR SHA-1:   {}", chr.sha1)?;
+        }
+        writeln!(f, "ROM CRC32:   {:08X}", self.rom.crc32)?;
+        writeln!(f, "ROM SHA-1:   {}", self.rom.sha1)?;
+
+        if self.anomalies.is_empty() {
+            write!(f, "Anomalies:   none")
+        } else {
+            write!(f, "Anomalies:")?;
+            for anomaly in &self.anomalies {
+                write!(f, "\n  - {}", anomaly)?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::{error::Error, fmt};
+
+/// Every save state starts with these four bytes.
+pub const STATE_MAGIC: [u8; 4] = *b"PGST";
+/// Revision of the save state format. States of other versions are rejected.
+pub const STATE_VERSION: u16 = 4;
+/// Size of the header: magic, version, CRC32 of the PRG-ROM, payload length and payload CRC32.
+pub const STATE_HEADER_SIZE: usize = 18;
+
+/// Errors that can occur while restoring a save state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaveStateError {
+    /// The data does not start with the save state magic bytes.
+    InvalidMagic,
+    /// The state was written by an incompatible version of the emulator.
+    UnsupportedVersion(u16),
+    /// The data ends before the state does.
+    Truncated,
+    /// The payload doesn't match its checksum.
+    ChecksumMismatch,
+    /// The state was saved while a different cartridge was inserted.
+    WrongCartridge,
+    /// The payload contains a value that can't occur in a valid state.
+    Malformed(&'static str),
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveStateError::InvalidMagic => write!(f, "not a save state (invalid magic bytes)"),
+            SaveStateError::UnsupportedVersion(version) => write!(f, "save state version {} is not supported", version),
+            SaveStateError::Truncated => write!(f, "save state is truncated"),
+            SaveStateError::ChecksumMismatch => write!(f, "save state is corrupted (checksum mismatch)"),
+            SaveStateError::WrongCartridge => write!(f, "save state belongs to a different cartridge"),
+            SaveStateError::Malformed(reason) => write!(f, "malformed save state: {}", reason),
+        }
+    }
+}
+
+impl Error for SaveStateError {}
+
+/// A component whose state can be saved and restored. Configuration, like the region or the
+/// audio sample rate, isn't part of the state of a component.
+pub trait SaveState {
+    fn save_state(&self, writer: &mut StateWriter);
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError>;
+}
+
+/// Builds the payload of a save state, storing all values little endian.
+#[derive(Debug, Default)]
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.data.extend(value.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_u64(value.to_bits());
+    }
+
+    /// Write bytes of a length both sides know, like the contents of a fixed size array.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Write bytes of a variable length, prefixed with that length.
+    pub fn write_vec(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.write_bytes(bytes);
+    }
+}
+
+/// Reads back the values of a payload written by a `StateWriter`, in the same order.
+#[derive(Debug)]
+pub struct StateReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        StateReader { data, position: 0 }
+    }
+
+    /// Whether all data has been read.
+    pub fn is_empty(&self) -> bool {
+        self.position == self.data.len()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SaveStateError> {
+        let bytes = self.data.get(self.position..self.position + len).ok_or(SaveStateError::Truncated)?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, SaveStateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, SaveStateError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SaveStateError::Malformed("invalid boolean")),
+        }
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, SaveStateError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, SaveStateError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, SaveStateError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, SaveStateError> {
+        Ok(f32::from_bits(self.read_u32()?))
+    }
+
+    pub fn read_f64(&mut self) -> Result<f64, SaveStateError> {
+        Ok(f64::from_bits(self.read_u64()?))
+    }
+
+    /// Fill `bytes` with data written by `StateWriter::write_bytes`.
+    pub fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<(), SaveStateError> {
+        bytes.copy_from_slice(self.take(bytes.len())?);
+        Ok(())
+    }
+
+    /// Fill `bytes` with data written by `StateWriter::write_vec`, which must have the same length.
+    pub fn read_vec_into(&mut self, bytes: &mut [u8]) -> Result<(), SaveStateError> {
+        if self.read_u32()? as usize != bytes.len() {
+            return Err(SaveStateError::Malformed("memory size differs"));
+        }
+        self.read_bytes(bytes)
+    }
+}
+
+/// Wrap a payload in a header that identifies it as a save state for the PRG-ROM with the given
+/// CRC32, and protects it with a checksum.
+pub fn wrap(payload: &[u8], rom_crc: u32) -> Vec<u8> {
+    let mut data = Vec::with_capacity(STATE_HEADER_SIZE + payload.len());
+    data.extend(STATE_MAGIC);
+    data.extend(STATE_VERSION.to_le_bytes());
+    data.extend(rom_crc.to_le_bytes());
+    data.extend((payload.len() as u32).to_le_bytes());
+    data.extend(crc32fast::hash(payload).to_le_bytes());
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Check the header of a save state, returning its payload if it's intact and was saved for the
+/// PRG-ROM with the given CRC32.
+pub fn unwrap(data: &[u8], rom_crc: u32) -> Result<&[u8], SaveStateError> {
+    let mut reader = StateReader::new(data);
+    let mut magic = [0; 4];
+    reader.read_bytes(&mut magic)?;
+    if magic != STATE_MAGIC {
+        return Err(SaveStateError::InvalidMagic);
+    }
+
+    let version = reader.read_u16()?;
+    if version != STATE_VERSION {
+        return Err(SaveStateError::UnsupportedVersion(version));
+    }
+
+    let state_rom_crc = reader.read_u32()?;
+    let len = reader.read_u32()? as usize;
+    let checksum = reader.read_u32()?;
+    let payload = reader.take(len)?;
+    if crc32fast::hash(payload) != checksum {
+        return Err(SaveStateError::ChecksumMismatch);
+    }
+    if state_rom_crc != rom_crc {
+        return Err(SaveStateError::WrongCartridge);
+    }
+
+    Ok(payload)
+}
@@ -0,0 +1,108 @@
+use std::{
+    error::Error,
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// The image formats a screenshot can be saved in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    /// Binary PPM (P6), trivial to read back without any dependencies
+    Ppm,
+}
+
+impl ImageFormat {
+    /// Guess the format from a file's extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        if extension.eq_ignore_ascii_case("png") {
+            Some(ImageFormat::Png)
+        } else if extension.eq_ignore_ascii_case("ppm") {
+            Some(ImageFormat::Ppm)
+        } else {
+            None
+        }
+    }
+}
+
+/// Errors that can occur while saving a screenshot.
+#[derive(Debug)]
+pub enum ScreenshotError {
+    /// The image could not be written.
+    Io(io::Error),
+    /// The PNG encoder failed.
+    Png(png::EncodingError),
+    /// The format couldn't be determined from the file's extension.
+    UnknownFormat,
+}
+
+impl fmt::Display for ScreenshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScreenshotError::Io(err) => write!(f, "could not write screenshot: {}", err),
+            ScreenshotError::Png(err) => write!(f, "could not encode PNG: {}", err),
+            ScreenshotError::UnknownFormat => write!(f, "screenshots must be saved as .png or .ppm"),
+        }
+    }
+}
+
+impl Error for ScreenshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScreenshotError::Io(err) => Some(err),
+            ScreenshotError::Png(err) => Some(err),
+            ScreenshotError::UnknownFormat => None,
+        }
+    }
+}
+
+impl From<io::Error> for ScreenshotError {
+    fn from(err: io::Error) -> Self {
+        ScreenshotError::Io(err)
+    }
+}
+
+impl From<png::EncodingError> for ScreenshotError {
+    fn from(err: png::EncodingError) -> Self {
+        ScreenshotError::Png(err)
+    }
+}
+
+/// Encode an image of 3 bytes per pixel, row by row, and write it to `writer`.
+pub fn encode<W: Write>(
+    writer: W,
+    format: ImageFormat,
+    width: usize,
+    height: usize,
+    rgb: &[u8],
+) -> Result<(), ScreenshotError> {
+    debug_assert_eq!(width * height * 3, rgb.len());
+
+    match format {
+        ImageFormat::Png => {
+            let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(rgb)?;
+            writer.finish()?;
+        },
+        ImageFormat::Ppm => {
+            let mut writer = writer;
+            write!(writer, "P6\n{} {}\n255\n", width, height)?;
+            writer.write_all(rgb)?;
+            writer.flush()?;
+        },
+    }
+    Ok(())
+}
+
+/// Save an image of 3 bytes per pixel to a file, in the format matching its extension.
+pub fn save<P: AsRef<Path>>(path: P, width: usize, height: usize, rgb: &[u8]) -> Result<(), ScreenshotError> {
+    let format = ImageFormat::from_path(&path).ok_or(ScreenshotError::UnknownFormat)?;
+    let file = BufWriter::new(File::create(path)?);
+    encode(file, format, width, height, rgb)
+}
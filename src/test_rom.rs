@@ -0,0 +1,128 @@
+use std::path::Path;
+use crate::{cartridge::{Cartridge, CartridgeError}, nes::Nes};
+
+/// Where blargg's test ROMs report their status. The message follows the signature at $6004.
+/// Ref: https://github.com/christopherpow/nes-test-roms/blob/master/README.md
+pub const STATUS_ADDRESS: u16 = 0x6000;
+/// Written to $6001-$6003 once the status at $6000 is valid
+pub const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+/// The test is still running
+const STATUS_RUNNING: u8 = 0x80;
+/// The test wants the reset button pressed, at least 100ms from now
+const STATUS_RESET_REQUESTED: u8 = 0x81;
+/// Frames to wait before pressing reset, a little over 100ms
+const RESET_DELAY_FRAMES: u64 = 7;
+/// The message is a zero terminated string somewhere in the rest of PRG-RAM
+const MAX_MESSAGE_LENGTH: u16 = 0x1FFC;
+
+/// How a test ROM finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestOutcome {
+    Passed,
+    /// The test failed with the given result code
+    Failed(u8),
+    /// The test didn't report a result within the frame limit
+    TimedOut,
+}
+
+/// The result of running a test ROM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestReport {
+    pub outcome: TestOutcome,
+    /// The text the test ROM printed, usually explaining what failed
+    pub message: String,
+    /// Number of frames the test ran for
+    pub frames: u64,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> bool {
+        self.outcome == TestOutcome::Passed
+    }
+}
+
+/// Runs test ROMs that follow blargg's protocol of reporting their status at $6000.
+#[derive(Debug)]
+pub struct TestRunner {
+    pub nes: Nes,
+    max_frames: u64,
+}
+
+impl TestRunner {
+    /// Frames after which a test is considered hung, unless changed with `set_max_frames`.
+    pub const DEFAULT_MAX_FRAMES: u64 = 60 * 60;
+
+    pub fn new(cartridge: Cartridge) -> Result<Self, CartridgeError> {
+        Ok(TestRunner {
+            nes: Nes::new(cartridge)?,
+            max_frames: Self::DEFAULT_MAX_FRAMES,
+        })
+    }
+
+    /// Load a test ROM from an iNES or NES 2.0 file on disk.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CartridgeError> {
+        Self::new(Cartridge::load(path)?)
+    }
+
+    /// Give up on the test after `max_frames` frames.
+    pub fn set_max_frames(&mut self, max_frames: u64) {
+        self.max_frames = max_frames;
+    }
+
+    /// Run the test until it reports its result, pressing reset whenever it asks for it.
+    pub fn run(&mut self) -> TestReport {
+        let mut reset_at = None;
+
+        for frame in 1..=self.max_frames {
+            self.nes.run_frame();
+
+            if !self.has_signature() {
+                continue;
+            }
+
+            match self.status() {
+                STATUS_RUNNING => {},
+                STATUS_RESET_REQUESTED => {
+                    let at = *reset_at.get_or_insert(frame + RESET_DELAY_FRAMES);
+                    if frame >= at {
+                        self.nes.reset();
+                        reset_at = None;
+                    }
+                },
+                0 => return self.report(TestOutcome::Passed, frame),
+                code => return self.report(TestOutcome::Failed(code), frame),
+            }
+        }
+
+        self.report(TestOutcome::TimedOut, self.max_frames)
+    }
+
+    /// Whether the signature is in place, so the status can be trusted.
+    pub fn has_signature(&self) -> bool {
+        SIGNATURE.iter().enumerate().all(|(index, &byte)| self.peek(STATUS_ADDRESS + 1 + index as u16) == byte)
+    }
+
+    /// The status code at $6000.
+    pub fn status(&self) -> u8 {
+        self.peek(STATUS_ADDRESS)
+    }
+
+    /// The text the test ROM printed so far.
+    pub fn message(&self) -> String {
+        let start = STATUS_ADDRESS + 4;
+        let bytes: Vec<u8> = (0..MAX_MESSAGE_LENGTH)
+            .map(|offset| self.peek(start + offset))
+            .take_while(|&byte| byte != 0)
+            .collect();
+
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.nes.cpu.peek(address)
+    }
+
+    fn report(&self, outcome: TestOutcome, frames: u64) -> TestReport {
+        TestReport { outcome, message: self.message(), frames }
+    }
+}
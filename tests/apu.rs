@@ -0,0 +1,214 @@
+mod common;
+
+use powerglove::{apu::{Apu, CPU_CLOCK_NTSC}, bus::{Bus, BusDevice}, cartridge::Cartridge};
+
+fn run(apu: &mut Apu, cycles: u32) {
+    for _ in 0..cycles {
+        apu.clock(&None);
+    }
+}
+
+#[test]
+fn test_length_counter() {
+    let mut apu = Apu::new();
+
+    // Length counters can't be loaded while the channel is disabled
+    apu.write(0x4003, 0x18);
+    assert_eq!(0x00, apu.read_status());
+
+    apu.write(0x4015, 0x0F);
+    apu.write(0x4003, 0x18);
+    apu.write(0x4007, 0x18);
+    apu.write(0x400B, 0x18);
+    apu.write(0x400F, 0x18);
+    assert_eq!(0x0F, apu.read_status());
+
+    // A length of 2 runs out after two half frames
+    run(&mut apu, 14913);
+    assert_eq!(0x0F, apu.read_status() & 0x0F);
+    run(&mut apu, 29829 - 14913);
+    assert_eq!(0x00, apu.read_status() & 0x0F);
+
+    // Unless the counter is halted
+    apu.write(0x4000, 0x20);
+    apu.write(0x4003, 0x18);
+    run(&mut apu, 2 * 29830);
+    assert_eq!(0x01, apu.read_status() & 0x0F);
+
+    // Disabling a channel clears its length counter
+    apu.write(0x4015, 0x00);
+    assert_eq!(0x00, apu.read_status() & 0x0F);
+}
+
+#[test]
+fn test_frame_irq() {
+    let mut apu = Apu::new();
+
+    run(&mut apu, 29828);
+    assert!(!apu.irq());
+    run(&mut apu, 1);
+    assert!(apu.irq());
+
+    // Reading the status acknowledges the IRQ
+    assert_eq!(0x40, apu.read_status());
+    assert!(!apu.irq());
+
+    // The IRQ can be inhibited
+    apu.write(0x4017, 0x40);
+    run(&mut apu, 2 * 29830);
+    assert!(!apu.irq());
+
+    // And is never generated in 5-step mode
+    apu.write(0x4017, 0x80);
+    run(&mut apu, 2 * 37282);
+    assert!(!apu.irq());
+}
+
+#[test]
+fn test_sweep_mutes() {
+    let mut apu = Apu::new();
+    apu.write(0x4015, 0x01);
+    apu.write(0x4000, 0xBF);
+
+    // Periods below 8 are muted
+    apu.write(0x4002, 0x07);
+    apu.write(0x4003, 0x08);
+    run(&mut apu, 64);
+    assert_eq!(0, apu.pulse_1.output());
+
+    // As are periods the sweep unit would move above $7FF, even if it's disabled
+    apu.write(0x4001, 0x01);
+    apu.write(0x4002, 0x00);
+    apu.write(0x4003, 0x0F);
+    run(&mut apu, 64);
+    assert_eq!(0, apu.pulse_1.output());
+
+    // Otherwise, the constant volume is output during the high part of the duty cycle
+    apu.write(0x4002, 0xFF);
+    apu.write(0x4003, 0x08);
+    let outputs: Vec<u8> = (0..8192).map(|_| {
+        apu.clock(&None);
+        apu.pulse_1.output()
+    }).collect();
+    assert!(outputs.contains(&15));
+    assert!(outputs.contains(&0));
+}
+
+/// Determine the timer period of a pulse channel with a 50% duty cycle, by counting the cycles
+/// between changes of its output.
+fn pulse_period(apu: &Apu, first: bool) -> u32 {
+    let mut apu = apu.clone();
+    let output = |apu: &Apu| if first { apu.pulse_1.output() } else { apu.pulse_2.output() };
+
+    let mut last = output(&apu);
+    let mut edges = Vec::new();
+    let mut cycle = 0;
+    while edges.len() < 3 {
+        apu.clock(&None);
+        cycle += 1;
+        if output(&apu) != last {
+            last = output(&apu);
+            edges.push(cycle);
+        }
+    }
+
+    // The output is high for 4 steps, and low for 4 steps. Each step takes 2 * (period + 1) CPU cycles.
+    (edges[2] - edges[1]) / 8 - 1
+}
+
+#[test]
+fn test_sweep() {
+    let mut apu = Apu::new();
+    apu.write(0x4015, 0x03);
+
+    // Sweep both channels down by period >> 1 every half frame
+    for channel in [0x4000, 0x4004] {
+        apu.write(channel, 0xBF);
+        apu.write(channel + 1, 0x89);
+        apu.write(channel + 2, 0x00);
+        apu.write(channel + 3, 0x01);
+    }
+    assert_eq!(0x100, pulse_period(&apu, true));
+
+    // Only the first pulse channel subtracts an extra 1
+    run(&mut apu, 14913);
+    assert_eq!(0x7F, pulse_period(&apu, true));
+    assert_eq!(0x80, pulse_period(&apu, false));
+}
+
+#[test]
+fn test_mixer() {
+    // The triangle channel is never muted, and holds the top of its wave until it starts playing
+    let mut apu = Apu::new();
+    let triangle = 15.0 / 8227.0;
+    let expected = 159.79 / (1.0 / triangle + 100.0);
+    assert!((apu.mix() - expected).abs() < 0.0001);
+
+    apu.write(0x4015, 0x03);
+    for channel in [0x4000, 0x4004] {
+        apu.write(channel, 0x7F);
+        apu.write(channel + 2, 0xFF);
+        apu.write(channel + 3, 0x08);
+    }
+
+    // Find a cycle where both pulse channels are at their maximum volume
+    while apu.pulse_1.output() + apu.pulse_2.output() != 30 {
+        apu.clock(&None);
+    }
+    let expected = 95.88 / (8128.0 / 30.0 + 100.0) + 159.79 / (1.0 / triangle + 100.0);
+    assert!((apu.mix() - expected).abs() < 0.0001);
+
+    // The DMC's output level is directly loaded with $4011
+    let mut apu = Apu::new();
+    apu.write(0x4011, 0x7F);
+    let expected = 159.79 / (1.0 / (triangle + 127.0 / 22638.0) + 100.0);
+    assert!((apu.mix() - expected).abs() < 0.0001);
+}
+
+#[test]
+fn test_sample_rate() {
+    let mut apu = Apu::new();
+
+    run(&mut apu, CPU_CLOCK_NTSC as u32);
+    let samples = apu.take_samples();
+    assert!((44_099..=44_101).contains(&samples.len()));
+    assert!(samples.iter().all(|&sample| (sample - apu.mix()).abs() < 0.0001));
+
+    apu.set_sample_rate(48_000);
+    run(&mut apu, CPU_CLOCK_NTSC as u32 / 10);
+    assert!((4_799..=4_801).contains(&apu.take_samples().len()));
+}
+
+#[test]
+fn test_dmc() {
+    // Fill $C000-$FFFF with samples that only move the output level up
+    let mut prg = vec![0x00; 0x4000];
+    prg.resize(0x8000, 0xFF);
+
+    let mut bus = Bus::new();
+    bus.insert_cartridge(Cartridge::from_bytes(&common::nrom(prg)).unwrap()).unwrap();
+
+    // IRQ enabled at the fastest rate, starting at $C000 with a length of 17 bytes
+    bus.write(0x4010, 0x8F);
+    bus.write(0x4011, 0x00);
+    bus.write(0x4012, 0x00);
+    bus.write(0x4013, 0x01);
+    bus.write(0x4015, 0x10);
+    assert_eq!(0x10, bus.read(0x4015));
+
+    // Fetching a sample byte halts the CPU
+    bus.clock_apu();
+    assert_eq!(4, bus.take_stall_cycles());
+
+    // Each byte takes 8 * 54 cycles to play
+    for _ in 0..17 * 8 * 54 {
+        bus.clock_apu();
+    }
+    assert!(bus.irq());
+    assert_eq!(0x80, bus.read(0x4015));
+    assert!(bus.apu.dmc.output() > 0x40);
+
+    // Writing to $4015 acknowledges the IRQ
+    bus.write(0x4015, 0x00);
+    assert!(!bus.irq());
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::Recorder;
+use powerglove::{bus::{Bus, BusDevice, FlatRam}, cartridge::Cartridge, cpu::CPU, ppu::registers::Control};
+
+#[test]
+fn test_ram_mirroring() {
+    let mut bus = Bus::new();
+
+    bus.write(0x0012, 0x42);
+    assert_eq!(0x42, bus.read(0x0812));
+    assert_eq!(0x42, bus.read(0x1012));
+    assert_eq!(0x42, bus.read(0x1812));
+
+    bus.write(0x1FFF, 0x24);
+    assert_eq!(0x24, bus.read(0x07FF));
+}
+
+#[test]
+fn test_ppu_register_mirroring() {
+    let mut bus = Bus::new();
+
+    bus.write(0x3FF8, 0x80);
+    assert!(bus.ppu.ctrl.contains(Control::NMI));
+
+    // Set the VRAM address through one mirror, and write data through another
+    bus.write(0x3FFE, 0x21);
+    bus.write(0x200E, 0x08);
+    bus.write(0x2FFF, 0x42);
+    bus.write(0x2006, 0x21);
+    bus.write(0x2006, 0x08);
+    bus.read(0x2007);
+    assert_eq!(0x42, bus.read(0x3FF7));
+}
+
+#[test]
+fn test_cartridge_space() {
+    let mut prg = vec![0x0; 0x8000];
+    prg[0] = 0x11;
+    prg[0x7FFF] = 0x22;
+
+    let mut bus = Bus::new();
+
+    // Without a cartridge nothing is mapped there
+    bus.write(0x8000, 0x42);
+    assert_eq!(0x00, bus.read(0x8000));
+
+    bus.insert_cartridge(Cartridge::from_bytes(&common::nrom(prg)).unwrap()).unwrap();
+    assert_eq!(0x11, bus.read(0x8000));
+    assert_eq!(0x22, bus.read(0xFFFF));
+
+    // ROM can't be written to, but PRG-RAM can
+    bus.write(0x8000, 0x42);
+    assert_eq!(0x11, bus.read(0x8000));
+    bus.write(0x6000, 0x42);
+    assert_eq!(0x42, bus.read(0x6000));
+}
+
+#[test]
+fn test_cpu_on_custom_bus() {
+    let mut ram = FlatRam::new();
+
+    // LDA #$42; STA $9000
+    ram.ram[0x8000..0x8005].copy_from_slice(&[0xA9, 0x42, 0x8D, 0x00, 0x90]);
+    ram.ram[0xFFFC] = 0x00;
+    ram.ram[0xFFFD] = 0x80;
+
+    let mut cpu = CPU::with_bus(Recorder { ram, accesses: Vec::new() });
+    cpu.reset();
+
+    // The reset takes 7 cycles, and both instructions take 6 cycles combined
+    for _ in 0..13 {
+        cpu.clock();
+    }
+
+    assert_eq!(vec![(0x9000, 0x42)], cpu.bus.writes());
+    assert_eq!(0x42, cpu.peek(0x9000));
+}
@@ -0,0 +1,67 @@
+use powerglove::cartridge::{Cartridge, CartridgeError, Header, HeaderFormat, Mirroring, Region};
+
+/// Build an iNES image with the given header fields and PRG/CHR sizes in banks.
+fn ines(prg_banks: u8, chr_banks: u8, flags6: u8, flags7: u8) -> Vec<u8> {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, prg_banks, chr_banks, flags6, flags7];
+    rom.resize(16, 0);
+    rom.resize(16 + prg_banks as usize * 0x4000 + chr_banks as usize * 0x2000, 0);
+    rom
+}
+
+#[test]
+fn test_parse_ines_header() {
+    let cart = Cartridge::from_bytes(&ines(2, 1, 0b0001_0011, 0b0100_0000)).unwrap();
+
+    assert_eq!(HeaderFormat::INes, cart.header.format);
+    assert_eq!(0x41, cart.header.mapper);
+    assert_eq!(Mirroring::Vertical, cart.header.mirroring);
+    assert_eq!(Region::Ntsc, cart.header.region);
+    assert!(cart.header.battery);
+    assert_eq!(0x2000, cart.header.prg_nvram_size);
+    assert_eq!(0x8000, cart.prg_rom.len());
+    assert_eq!(0x2000, cart.chr_rom.len());
+}
+
+#[test]
+fn test_parse_dirty_ines_header() {
+    let mut rom = ines(1, 1, 0x10, 0x00);
+    rom[7..16].copy_from_slice(b"DiskDude!");
+
+    assert_eq!(0x01, Header::parse(&rom).unwrap().mapper);
+}
+
+#[test]
+fn test_parse_nes2_header() {
+    let mut rom = ines(2, 0, 0b0000_1000, 0b0000_1000);
+    rom[8] = 0x31;
+    rom[10] = 0x70;
+    rom[11] = 0x07;
+    rom[12] = 0x01;
+    let header = Header::parse(&rom).unwrap();
+
+    assert_eq!(HeaderFormat::Nes2, header.format);
+    assert_eq!(0x100, header.mapper);
+    assert_eq!(3, header.submapper);
+    assert_eq!(Mirroring::FourScreen, header.mirroring);
+    assert_eq!(Region::Pal, header.region);
+    assert_eq!(0, header.prg_ram_size);
+    assert_eq!(0x2000, header.prg_nvram_size);
+    assert_eq!(0x2000, header.chr_ram_size);
+}
+
+#[test]
+fn test_reject_invalid_files() {
+    assert!(matches!(Cartridge::from_bytes(b"NOPE"), Err(CartridgeError::InvalidMagic)));
+    assert!(matches!(Cartridge::from_bytes(b"NES\x1A\x01"), Err(CartridgeError::Truncated { .. })));
+    assert!(matches!(Cartridge::from_bytes(&ines(0, 1, 0, 0)), Err(CartridgeError::Malformed(_))));
+
+    let mut rom = ines(2, 1, 0, 0);
+    rom.truncate(16 + 0x4000);
+    assert!(matches!(Cartridge::from_bytes(&rom), Err(CartridgeError::Truncated { expected: 0xA010, found: 0x4010 })));
+
+    // NES 2.0 exponent sizes of 2^63 bytes each for PRG-ROM and CHR-ROM
+    let mut rom = ines(0, 0, 0, 0b0000_1000);
+    rom[4..6].copy_from_slice(&[0xFC, 0xFC]);
+    rom[9] = 0xFF;
+    assert!(matches!(Cartridge::from_bytes(&rom), Err(CartridgeError::Malformed(_))));
+}
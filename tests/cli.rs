@@ -0,0 +1,115 @@
+mod common;
+
+use std::{fs, path::PathBuf, process::{Command, Output}};
+
+/// Write an NROM test ROM to the temporary directory that immediately reports `code` at $6000.
+fn report_rom(name: &str, code: u8) -> PathBuf {
+    let prg = common::prg(&[
+        0xA9, 0xDE, 0x8D, 0x01, 0x60, // LDA #$DE; STA $6001
+        0xA9, 0xB0, 0x8D, 0x02, 0x60, // LDA #$B0; STA $6002
+        0xA9, 0x61, 0x8D, 0x03, 0x60, // LDA #$61; STA $6003
+        0xA9, code, 0x8D, 0x00, 0x60, // LDA #code; STA $6000
+        0x4C, 0x14, 0x80,             // JMP $8014
+    ]);
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, common::nrom(prg)).unwrap();
+    path
+}
+
+fn powerglove(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_powerglove")).args(args).output().unwrap()
+}
+
+#[test]
+fn test_exit_codes() {
+    let passing = report_rom("cli-pass.nes", 0);
+    let failing = report_rom("cli-fail.nes", 2);
+
+    let output = powerglove(&["run", "--test-rom", passing.to_str().unwrap()]);
+    assert_eq!(Some(0), output.status.code());
+
+    let output = powerglove(&["run", "--test-rom", failing.to_str().unwrap()]);
+    assert_eq!(Some(1), output.status.code());
+
+    let output = powerglove(&["run", "--frames", "2", passing.to_str().unwrap()]);
+    assert_eq!(Some(0), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Ran 2 frames"));
+
+    // A cycle limit isn't cut short by the default of 600 frames
+    let output = powerglove(&["run", "--cycles", "17900000", passing.to_str().unwrap()]);
+    assert_eq!(Some(0), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Ran 602 frames"));
+
+    let output = powerglove(&["info", "--strict", passing.to_str().unwrap()]);
+    assert_eq!(Some(0), output.status.code());
+
+    let output = powerglove(&["info", "does-not-exist.nes"]);
+    assert_eq!(Some(3), output.status.code());
+
+    let output = powerglove(&["disasm", "--start", "nowhere", passing.to_str().unwrap()]);
+    assert_eq!(Some(2), output.status.code());
+}
+
+#[test]
+fn test_disasm_and_trace() {
+    let rom = report_rom("cli-disasm.nes", 0);
+
+    let output = powerglove(&["disasm", "--start", "$8000", "--end", "0x8004", rom.to_str().unwrap()]);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("$8000: LDA #$DE {IMM}\n$8002: STA $6001 {ABS}\n", String::from_utf8_lossy(&output.stdout));
+
+    let output = powerglove(&["trace", "--instructions", "2", rom.to_str().unwrap()]);
+    assert_eq!(Some(0), output.status.code());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(2, lines.len());
+    assert!(lines[0].starts_with("8000  A9 DE     LDA #$DE "));
+    assert!(lines[1].starts_with("8002  8D 01 60  STA $6001 = "));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_trace_write_error() {
+    let rom = report_rom("cli-trace-error.nes", 0);
+
+    let output = powerglove(&["trace", "--output", "/dev/full", rom.to_str().unwrap()]);
+    assert_eq!(Some(3), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("could not write the trace"));
+}
+
+#[test]
+fn test_screenshots() {
+    let rom = report_rom("cli-screenshot.nes", 0);
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let path = dir.join("cli-shot.ppm");
+
+    let output = powerglove(&["run", "--frames", "5", "--screenshot", path.to_str().unwrap(), rom.to_str().unwrap()]);
+    assert_eq!(Some(0), output.status.code());
+    assert!(fs::read(&path).unwrap().starts_with(b"P6\n256 240\n255\n"));
+
+    let every = dir.join("cli-every.png");
+    let output = powerglove(&[
+        "run", "--frames", "5", "--screenshot-every", "2", "--screenshot", every.to_str().unwrap(), rom.to_str().unwrap(),
+    ]);
+    assert_eq!(Some(0), output.status.code());
+    assert!(dir.join("cli-every-000002.png").exists());
+    assert!(dir.join("cli-every-000004.png").exists());
+    assert!(!dir.join("cli-every-000005.png").exists());
+
+    let output = powerglove(&["run", "--screenshot-at", "3", "--screenshot", path.to_str().unwrap(), rom.to_str().unwrap()]);
+    assert_eq!(Some(0), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Ran 3 frames"));
+
+    let output = powerglove(&["run", "--screenshot", "shot.bmp", rom.to_str().unwrap()]);
+    assert_eq!(Some(2), output.status.code());
+
+    let palette = dir.join("cli-flat.pal");
+    fs::write(&palette, [1, 2, 3].repeat(64)).unwrap();
+    let output = powerglove(&[
+        "run", "--frames", "2", "--palette", palette.to_str().unwrap(), "--screenshot", path.to_str().unwrap(),
+        rom.to_str().unwrap(),
+    ]);
+    assert_eq!(Some(0), output.status.code());
+    assert!(fs::read(&path).unwrap().ends_with(&[1, 2, 3]));
+}
@@ -0,0 +1,78 @@
+//! Fixtures shared by the integration tests. Each test crate only uses some of them.
+#![allow(dead_code)]
+
+use powerglove::{bus::{BusDevice, FlatRam}, cartridge::Cartridge, nes::Nes};
+
+/// Wrap 32 KiB of PRG-ROM in an iNES image of an NROM cartridge with 8 KiB of blank CHR-ROM.
+pub fn nrom(prg: Vec<u8>) -> Vec<u8> {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 0x02, 0x01, 0x00, 0x00];
+    rom.resize(16, 0);
+    rom.extend(prg);
+    rom.resize(16 + 0x8000 + 0x2000, 0);
+    rom
+}
+
+/// 32 KiB of PRG-ROM filled with NOPs, with `program` at $8000 and every vector pointing at it.
+pub fn prg(program: &[u8]) -> Vec<u8> {
+    let mut prg = vec![0xEA; 0x8000];
+    prg[..program.len()].copy_from_slice(program);
+    prg[0x7FFA..].copy_from_slice(&[0x00, 0x80, 0x00, 0x80, 0x00, 0x80]);
+    prg
+}
+
+/// Like `prg`, with an NMI handler at $8100 that stores the state of the A button at $10 and
+/// counts frames at $11.
+pub fn polling_prg(program: &[u8]) -> Vec<u8> {
+    let mut prg = prg(program);
+    prg[0x100..0x116].copy_from_slice(&[
+        0x48,                         // PHA
+        0xA9, 0x01, 0x8D, 0x16, 0x40, // LDA #$01; STA $4016
+        0xA9, 0x00, 0x8D, 0x16, 0x40, // LDA #$00; STA $4016
+        0xAD, 0x16, 0x40, 0x29, 0x01, // LDA $4016; AND #$01
+        0x85, 0x10, 0xE6, 0x11,       // STA $10; INC $11
+        0x68, 0x40,                   // PLA; RTI
+    ]);
+    prg[0x7FFA..0x7FFC].copy_from_slice(&[0x00, 0x81]);
+    prg
+}
+
+/// Build a console with an NROM cartridge running `prg`.
+pub fn nrom_console(prg: Vec<u8>) -> Nes {
+    Nes::new(Cartridge::from_bytes(&nrom(prg)).unwrap()).unwrap()
+}
+
+/// Build an NROM console running `program` at $8000, with the NMI handler of `polling_prg`.
+pub fn polling_console(program: &[u8]) -> Nes {
+    nrom_console(polling_prg(program))
+}
+
+/// Flat RAM that records every bus access as `(address, data, is_write)`.
+#[derive(Default)]
+pub struct Recorder {
+    pub ram: FlatRam,
+    pub accesses: Vec<(u16, u8, bool)>,
+}
+
+impl Recorder {
+    /// The writes among the recorded accesses.
+    pub fn writes(&self) -> Vec<(u16, u8)> {
+        self.accesses.iter().filter(|access| access.2).map(|&(address, data, _)| (address, data)).collect()
+    }
+}
+
+impl BusDevice for Recorder {
+    fn read(&mut self, address: u16) -> u8 {
+        let data = self.ram.read(address);
+        self.accesses.push((address, data, false));
+        data
+    }
+
+    fn write(&mut self, address: u16, data: u8) {
+        self.ram.write(address, data);
+        self.accesses.push((address, data, true));
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.ram.peek(address)
+    }
+}
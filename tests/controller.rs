@@ -0,0 +1,71 @@
+use powerglove::{bus::{Bus, BusDevice}, controller::ButtonState, cpu::CPU};
+
+fn read_buttons(bus: &mut Bus, address: u16) -> Vec<u8> {
+    bus.write(0x4016, 0x01);
+    bus.write(0x4016, 0x00);
+    (0..10).map(|_| bus.read(address)).collect()
+}
+
+#[test]
+fn test_shift_register() {
+    let mut bus = Bus::new();
+
+    bus.set_buttons(0, ButtonState::A | ButtonState::START | ButtonState::RIGHT);
+    bus.set_buttons(1, ButtonState::B | ButtonState::UP);
+
+    // Buttons are reported in the order A, B, Select, Start, Up, Down, Left, Right, followed by 1s.
+    // The upper bits are those of the $00 written to $4016.
+    assert_eq!(vec![0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01, 0x01], read_buttons(&mut bus, 0x4016));
+    assert_eq!(vec![0x00, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01], read_buttons(&mut bus, 0x4017));
+}
+
+#[test]
+fn test_strobe() {
+    let mut bus = Bus::new();
+    bus.set_buttons(0, ButtonState::A);
+
+    // While the strobe is high, the state of A is returned over and over
+    bus.write(0x4016, 0x01);
+    assert_eq!(0x01, bus.read(0x4016));
+    assert_eq!(0x01, bus.read(0x4016));
+
+    bus.set_buttons(0, ButtonState::B);
+    assert_eq!(0x00, bus.read(0x4016));
+
+    // Peeking doesn't shift the register
+    bus.write(0x4016, 0x00);
+    assert_eq!(0x00, bus.peek(0x4016));
+    assert_eq!(0x00, bus.peek(0x4016));
+    assert_eq!(0x00, bus.read(0x4016));
+    assert_eq!(0x01, bus.peek(0x4016));
+
+    // Button changes after the strobe went low aren't seen until the next strobe
+    bus.set_buttons(0, ButtonState::empty());
+    assert_eq!(0x01, bus.read(0x4016));
+}
+
+#[test]
+fn test_open_bus() {
+    let mut cpu = CPU::new();
+    cpu.bus.set_buttons(0, ButtonState::A);
+    cpu.bus.write(0x4016, 0x01);
+
+    let program = [
+        0xA9, 0xA0, 0x8D, 0x03, 0x20, // LDA #$A0; STA $2003
+        0xA2, 0x17, 0xBD, 0xFF, 0x3F, // LDX #$17; LDA $3FFF,X
+        0x85, 0x10,                   // STA $10
+        0xAD, 0x16, 0x40, 0x85, 0x11, // LDA $4016; STA $11
+    ];
+    for (offset, &data) in program.iter().enumerate() {
+        cpu.bus.write(0x0200 + offset as u16, data);
+    }
+    cpu.pc = 0x0200;
+    while cpu.pc < 0x0200 + program.len() as u16 || cpu.step > 0 {
+        cpu.clock();
+    }
+
+    // The page crossing reads $3F16 first, which returns the $A0 latched by the PPU. A plain
+    // absolute read leaves the high byte of the address on the bus.
+    assert_eq!(0xA1, cpu.peek(0x10));
+    assert_eq!(0x41, cpu.peek(0x11));
+}
@@ -0,0 +1,412 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+use common::Recorder;
+use powerglove::{bus::FlatRam, cpu::{CPU, CpuState, CpuVariant, StatusFlags}};
+
+/// Load `program` at $8000 and run the reset sequence. The NMI handler is at $A000, the IRQ
+/// handler at $9000.
+fn boot(program: &[u8]) -> CPU<FlatRam> {
+    let mut ram = FlatRam::new();
+    ram.ram[0x8000..0x8000 + program.len()].copy_from_slice(program);
+    ram.ram[0xFFFA..].copy_from_slice(&[0x00, 0xA0, 0x00, 0x80, 0x00, 0x90]);
+
+    let mut cpu = CPU::with_bus(ram);
+    cpu.reset();
+    step(&mut cpu);
+
+    cpu
+}
+
+/// Load `program` like `boot`, for the given variant of the 6502.
+fn boot_variant(variant: CpuVariant, program: &[u8]) -> CPU<FlatRam> {
+    let mut cpu = boot(program);
+    cpu.variant = variant;
+    cpu
+}
+
+/// Execute a single instruction, returning the number of cycles it took.
+fn step(cpu: &mut CPU<FlatRam>) -> u64 {
+    let start = cpu.cycles;
+    cpu.clock();
+    while cpu.step > 0 {
+        cpu.clock();
+    }
+    cpu.cycles - start
+}
+
+/// Run `program` from $8000 and return the bus accesses of its first instruction.
+fn accesses(program: &[u8], setup: impl FnOnce(&mut CPU<Recorder>)) -> Vec<(u16, u8, bool)> {
+    let mut bus = Recorder::default();
+    bus.ram.ram[0x8000..0x8000 + program.len()].copy_from_slice(program);
+    bus.ram.ram[0xFFFD] = 0x80;
+
+    let mut cpu = CPU::with_bus(bus);
+    cpu.reset();
+    cpu.clock();
+    while cpu.step > 0 {
+        cpu.clock();
+    }
+    setup(&mut cpu);
+
+    cpu.bus.accesses.clear();
+    cpu.clock();
+    while cpu.step > 0 {
+        cpu.clock();
+    }
+    cpu.bus.accesses
+}
+
+#[test]
+fn test_bus_accesses() {
+    // INC $10 writes the unmodified value back before the result
+    let inc = accesses(&[0xE6, 0x10], |cpu| cpu.bus.ram.ram[0x10] = 0x41);
+    assert_eq!(vec![
+        (0x8000, 0xE6, false), (0x8001, 0x10, false),
+        (0x0010, 0x41, false), (0x0010, 0x41, true), (0x0010, 0x42, true),
+    ], inc);
+
+    // LDA $12FF,X reads from the wrong page before the high byte is fixed
+    let lda = accesses(&[0xBD, 0xFF, 0x12], |cpu| cpu.x = 0x02);
+    assert_eq!(vec![
+        (0x8000, 0xBD, false), (0x8001, 0xFF, false), (0x8002, 0x12, false),
+        (0x1201, 0x00, false), (0x1301, 0x00, false),
+    ], lda);
+
+    // STA $1200,X always reads before it writes, even without a page cross
+    let sta = accesses(&[0x9D, 0x00, 0x12], |cpu| { cpu.x = 0x02; cpu.a = 0x55; });
+    assert_eq!(&[(0x1202, 0x00, false), (0x1202, 0x55, true)], &sta[3..]);
+
+    // Implied instructions read the next byte and throw it away
+    let inx = accesses(&[0xE8, 0x99], |_| ());
+    assert_eq!(vec![(0x8000, 0xE8, false), (0x8001, 0x99, false)], inx);
+}
+
+#[test]
+fn test_interrupt_flag_latency() {
+    // CLI; NOP; NOP
+    let mut cpu = boot(&[0x58, 0xEA, 0xEA]);
+    cpu.set_irq_line(true);
+
+    // The IRQ is polled before CLI clears the flag, so the next instruction still runs
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(0x8002, cpu.pc);
+    assert_eq!(7, step(&mut cpu));
+    assert_eq!(0x9000, cpu.pc);
+
+    // CLI; SEI; NOP
+    let mut cpu = boot(&[0x58, 0x78, 0xEA]);
+    step(&mut cpu);
+    cpu.set_irq_line(true);
+
+    // The IRQ is polled before SEI sets the flag, so it's serviced right after it
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(0x9000, cpu.pc);
+    let status = cpu.peek(0x0100 + cpu.sp as u16 + 1);
+    assert_eq!(StatusFlags::I.bits(), status & StatusFlags::I.bits());
+}
+
+#[test]
+fn test_taken_branch_delays_irq() {
+    // CLI; BCC +0; NOP; NOP
+    let program = [0x58, 0x90, 0x00, 0xEA, 0xEA];
+
+    // An IRQ that comes in during the operand fetch of a taken branch waits for the next instruction
+    let mut cpu = boot(&program);
+    step(&mut cpu);
+    cpu.clock();
+    cpu.set_irq_line(true);
+    assert_eq!(2, step(&mut cpu));
+    assert_eq!(0x8003, cpu.pc);
+    step(&mut cpu);
+    assert_eq!(0x8004, cpu.pc);
+    step(&mut cpu);
+    assert_eq!(0x9000, cpu.pc);
+
+    // One cycle earlier, it's serviced right after the branch
+    let mut cpu = boot(&program);
+    step(&mut cpu);
+    cpu.set_irq_line(true);
+    assert_eq!(3, step(&mut cpu));
+    step(&mut cpu);
+    assert_eq!(0x9000, cpu.pc);
+
+    // The same goes for an NMI detected during the operand fetch
+    let mut cpu = boot(&program);
+    step(&mut cpu);
+    cpu.clock();
+    cpu.clock();
+    cpu.nmi();
+    cpu.clock();
+    assert_eq!((0, 0x8003), (cpu.step, cpu.pc));
+    step(&mut cpu);
+    assert_eq!(0x8004, cpu.pc);
+    step(&mut cpu);
+    assert_eq!(0xA000, cpu.pc);
+
+    // Or one cycle earlier
+    let mut cpu = boot(&program);
+    step(&mut cpu);
+    cpu.clock();
+    cpu.nmi();
+    assert_eq!(2, step(&mut cpu));
+    step(&mut cpu);
+    assert_eq!(0xA000, cpu.pc);
+}
+
+#[test]
+fn test_nmi_hijacks_brk() {
+    // BRK
+    let mut cpu = boot(&[0x00]);
+    cpu.clock();
+    cpu.clock();
+    cpu.nmi();
+    step(&mut cpu);
+
+    // The NMI handler runs, but the pushed status still shows a BRK
+    assert_eq!(0xA000, cpu.pc);
+    let status = cpu.peek(0x0100 + cpu.sp as u16 + 1);
+    assert_eq!(StatusFlags::B.bits(), status & StatusFlags::B.bits());
+    assert!(!cpu.nmi_pending);
+
+    // An NMI at the very end of the sequence waits until the first instruction of the handler ran
+    let mut cpu = boot(&[0x00]);
+    cpu.bus.ram[0x9000] = 0xEA;
+    for _ in 0..6 {
+        cpu.clock();
+    }
+    cpu.nmi();
+    cpu.clock();
+    assert_eq!(0x9000, cpu.pc);
+    step(&mut cpu);
+    assert_eq!(0x9001, cpu.pc);
+    step(&mut cpu);
+    assert_eq!(0xA000, cpu.pc);
+}
+
+#[test]
+fn test_unofficial_nop_lengths() {
+    // NOP #$12; NOP $12; NOP $12,X; NOP $1234; NOP $12FF,X; NOP
+    let mut cpu = boot(&[0x80, 0x12, 0x04, 0x12, 0x14, 0x12, 0x0C, 0x34, 0x12, 0x1C, 0xFF, 0x12, 0x1A]);
+    cpu.x = 0x01;
+
+    let expected = [(0x8002, 2), (0x8004, 3), (0x8006, 4), (0x8009, 4), (0x800C, 5), (0x800D, 2)];
+    for (pc, cycles) in expected {
+        assert_eq!(cycles, step(&mut cpu));
+        assert_eq!(pc, cpu.pc);
+    }
+}
+
+#[test]
+fn test_immediate_combinations() {
+    // ANC #$81
+    let mut cpu = boot(&[0x0B, 0x81]);
+    cpu.a = 0xF0;
+    step(&mut cpu);
+    assert_eq!(0x80, cpu.a);
+    assert!(cpu.status.contains(StatusFlags::C | StatusFlags::N));
+
+    // ALR #$03
+    let mut cpu = boot(&[0x4B, 0x03]);
+    cpu.a = 0xFF;
+    step(&mut cpu);
+    assert_eq!(0x01, cpu.a);
+    assert!(cpu.status.contains(StatusFlags::C));
+
+    // ARR #$FF with the carry set
+    let mut cpu = boot(&[0x6B, 0xFF]);
+    cpu.a = 0x40;
+    cpu.status.insert(StatusFlags::C);
+    step(&mut cpu);
+    assert_eq!(0xA0, cpu.a);
+    assert!(!cpu.status.contains(StatusFlags::C));
+    assert!(cpu.status.contains(StatusFlags::V | StatusFlags::N));
+
+    // AXS #$02
+    let mut cpu = boot(&[0xCB, 0x02]);
+    cpu.a = 0x0F;
+    cpu.x = 0x03;
+    step(&mut cpu);
+    assert_eq!(0x01, cpu.x);
+    assert!(cpu.status.contains(StatusFlags::C));
+}
+
+#[test]
+fn test_unstable_stores() {
+    // SHX $0210,Y stores X & (high byte + 1)
+    let mut cpu = boot(&[0x9E, 0x10, 0x02]);
+    cpu.x = 0xFF;
+    cpu.y = 0x01;
+    assert_eq!(5, step(&mut cpu));
+    assert_eq!(0x03, cpu.peek(0x0211));
+
+    // On a page cross, the value also replaces the high byte of the address
+    let mut cpu = boot(&[0x9C, 0xFF, 0x02]);
+    cpu.y = 0x05;
+    cpu.x = 0x01;
+    step(&mut cpu);
+    assert_eq!(0x00, cpu.peek(0x0300));
+    assert_eq!(0x01, cpu.peek(0x0100));
+
+    // TAS sets the stack pointer to A & X as well
+    let mut cpu = boot(&[0x9B, 0x00, 0x02]);
+    cpu.a = 0xF3;
+    cpu.x = 0x3F;
+    step(&mut cpu);
+    assert_eq!(0x33, cpu.sp);
+    assert_eq!(0x03, cpu.peek(0x0200));
+
+    // LAS loads memory & SP into A, X and SP
+    let mut cpu = boot(&[0xBB, 0x00, 0x02]);
+    cpu.bus.ram[0x0200] = 0x0F;
+    step(&mut cpu);
+    assert_eq!((0x0D, 0x0D, 0x0D), (cpu.a, cpu.x, cpu.sp));
+}
+
+#[test]
+fn test_jam() {
+    // NOP; JAM
+    let mut cpu = boot(&[0xEA, 0x02, 0xEA]);
+    let jams = Rc::new(RefCell::new(Vec::new()));
+    let handler_jams = jams.clone();
+    cpu.set_jam_handler(Some(Box::new(move |opcode, pc| handler_jams.borrow_mut().push((opcode, pc)))));
+
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(CpuState::Jammed { opcode: 0x02, pc: 0x8001 }, cpu.state);
+    assert_eq!(vec![(0x02, 0x8001)], *jams.borrow());
+
+    // Time passes, but nothing is executed anymore
+    let cycles = cpu.cycles;
+    for _ in 0..100 {
+        cpu.clock();
+    }
+    assert_eq!(cycles + 100, cpu.cycles);
+    assert_eq!(0x8002, cpu.pc);
+    assert_eq!(1, jams.borrow().len());
+
+    // Until the CPU is reset
+    cpu.reset();
+    assert_eq!(CpuState::Running, cpu.state);
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(0x8001, cpu.pc);
+}
+
+#[test]
+fn test_decimal_mode() {
+    // SED; CLC; LDA #$15; ADC #$27; ADC #$58; SEC; SBC #$01
+    let program = [0xF8, 0x18, 0xA9, 0x15, 0x69, 0x27, 0x69, 0x58, 0x38, 0xE9, 0x01];
+
+    // The 2A03 ignores the D flag
+    let mut cpu = boot(&program);
+    for _ in 0..4 {
+        step(&mut cpu);
+    }
+    assert_eq!(0x3C, cpu.a);
+
+    let mut cpu = boot_variant(CpuVariant::Nmos6502, &program);
+    for _ in 0..4 {
+        step(&mut cpu);
+    }
+    assert_eq!(0x42, cpu.a);
+    assert!(!cpu.status.contains(StatusFlags::C));
+
+    // 42 + 58 = 100, the carry holds the hundreds
+    step(&mut cpu);
+    assert_eq!(0x00, cpu.a);
+    assert!(cpu.status.contains(StatusFlags::C));
+
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(0x99, cpu.a);
+    assert!(!cpu.status.contains(StatusFlags::C));
+}
+
+#[test]
+fn test_65c02_instructions() {
+    // LDA #$0F; STZ $10; TSB $10; TRB $10; LDX #$12; PHX; PLY; INC A; BRA +1; BRK; SMB3 $10;
+    // BBS3 $10, +1; BRK; RMB3 $10; LDA ($20)
+    let mut cpu = boot_variant(CpuVariant::Wdc65C02, &[
+        0xA9, 0x0F, 0x64, 0x10, 0x04, 0x10, 0x14, 0x10, 0xA2, 0x12, 0xDA, 0x7A, 0x1A, 0x80, 0x01,
+        0x00, 0xB7, 0x10, 0xBF, 0x10, 0x01, 0x00, 0x37, 0x10, 0xB2, 0x20,
+    ]);
+    cpu.bus.ram[0x10] = 0xFF;
+    cpu.bus.ram[0x20..0x22].copy_from_slice(&[0x00, 0x03]);
+    cpu.bus.ram[0x0300] = 0x5A;
+
+    step(&mut cpu);
+    assert_eq!(3, step(&mut cpu));
+    assert_eq!(0x00, cpu.bus.ram[0x10]);
+    step(&mut cpu);
+    assert_eq!(0x0F, cpu.bus.ram[0x10]);
+    assert!(cpu.status.contains(StatusFlags::Z));
+    step(&mut cpu);
+    assert_eq!(0x00, cpu.bus.ram[0x10]);
+    assert!(!cpu.status.contains(StatusFlags::Z));
+
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(4, step(&mut cpu));
+    assert_eq!(0x12, cpu.y);
+    step(&mut cpu);
+    assert_eq!(0x10, cpu.a);
+
+    assert_eq!(3, step(&mut cpu));
+    assert_eq!(0x8010, cpu.pc);
+    step(&mut cpu);
+    assert_eq!(0x08, cpu.bus.ram[0x10]);
+    step(&mut cpu);
+    assert_eq!(0x8016, cpu.pc);
+    step(&mut cpu);
+    assert_eq!(0x00, cpu.bus.ram[0x10]);
+
+    assert_eq!(5, step(&mut cpu));
+    assert_eq!(0x5A, cpu.a);
+}
+
+#[test]
+fn test_65c02_differences() {
+    // JMP ($02FF) reads the high byte from $0300 instead of $0200
+    let program = [0x6C, 0xFF, 0x02];
+    let mut cpu = boot(&program);
+    cpu.bus.ram[0x02FF] = 0x34;
+    cpu.bus.ram[0x0200] = 0x12;
+    cpu.bus.ram[0x0300] = 0x56;
+    step(&mut cpu);
+    assert_eq!(0x1234, cpu.pc);
+
+    let mut cpu = boot_variant(CpuVariant::Wdc65C02, &program);
+    cpu.bus.ram[0x02FF] = 0x34;
+    cpu.bus.ram[0x0300] = 0x56;
+    assert_eq!(6, step(&mut cpu));
+    assert_eq!(0x5634, cpu.pc);
+
+    // The undefined opcodes are NOPs, some of them take a single cycle
+    let mut cpu = boot_variant(CpuVariant::Wdc65C02, &[0x03, 0x02, 0xFF, 0xEA]);
+    assert_eq!(1, step(&mut cpu));
+    assert_eq!(2, step(&mut cpu));
+    assert_eq!(0x8003, cpu.pc);
+
+    // STP stops the CPU like a JAM
+    let mut cpu = boot_variant(CpuVariant::Wdc65C02, &[0xDB]);
+    step(&mut cpu);
+    assert_eq!(CpuState::Jammed { opcode: 0xDB, pc: 0x8000 }, cpu.state);
+
+    // WAI waits for an interrupt, which is serviced if interrupts are enabled
+    let mut cpu = boot_variant(CpuVariant::Wdc65C02, &[0x58, 0xCB, 0xEA]);
+    step(&mut cpu);
+    step(&mut cpu);
+    for _ in 0..10 {
+        cpu.clock();
+    }
+    assert_eq!(CpuState::Waiting, cpu.state);
+    cpu.set_irq_line(true);
+    cpu.clock();
+    assert_eq!(CpuState::Running, cpu.state);
+    step(&mut cpu);
+    step(&mut cpu);
+    assert_eq!(0x9000, cpu.pc);
+}
@@ -0,0 +1,120 @@
+use std::{env, fs, path::{Path, PathBuf}};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use powerglove::{cartridge::Cartridge, controller::ButtonState, nes::Nes, palette::Palette};
+
+/// The expected frame hashes, and how to reproduce each frame.
+const MANIFEST: &str = "./tests/frame_hashes.json";
+/// ROM paths in the manifest are relative to this directory.
+const ROMS_DIR: &str = "./test-roms";
+/// Set this environment variable to rewrite the manifest with the frames as they are rendered now,
+/// instead of comparing against it. Review the screenshots and the diff before committing!
+const UPDATE_VAR: &str = "UPDATE_FRAME_HASHES";
+
+/// A ROM run for a number of frames with scripted input, and the hash of the last frame.
+#[derive(Debug, Serialize, Deserialize)]
+struct Case {
+    name: String,
+    rom: String,
+    frames: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    input: Vec<InputChange>,
+    /// SHA-1 of the frame buffer's palette indices, as little-endian 16-bit words. Missing for new
+    /// cases that haven't been run in update mode yet.
+    #[serde(default)]
+    hash: Option<String>,
+}
+
+/// From the start of `frame`, counting from 0, hold down `buttons` on the controller in `port`
+/// until the next change for that port.
+#[derive(Debug, Serialize, Deserialize)]
+struct InputChange {
+    frame: u64,
+    #[serde(default, skip_serializing_if = "is_first_port")]
+    port: usize,
+    buttons: Vec<String>,
+}
+
+fn is_first_port(port: &usize) -> bool {
+    *port == 0
+}
+
+fn button(name: &str) -> ButtonState {
+    match name {
+        "A" => ButtonState::A,
+        "B" => ButtonState::B,
+        "SELECT" => ButtonState::SELECT,
+        "START" => ButtonState::START,
+        "UP" => ButtonState::UP,
+        "DOWN" => ButtonState::DOWN,
+        "LEFT" => ButtonState::LEFT,
+        "RIGHT" => ButtonState::RIGHT,
+        _ => panic!("unknown button {:?}", name),
+    }
+}
+
+/// Run a case and hash the frame buffer it ends with. The console is returned as well, so the
+/// frame can be saved when it doesn't match.
+fn run(case: &Case, rom: &Path) -> (String, Nes) {
+    let mut nes = Nes::new(Cartridge::load(rom).unwrap()).unwrap();
+
+    for frame in 0..case.frames {
+        for change in case.input.iter().filter(|change| change.frame == frame) {
+            let buttons = change.buttons.iter().fold(ButtonState::empty(), |buttons, name| buttons | button(name));
+            nes.set_buttons(change.port, buttons);
+        }
+        nes.run_frame();
+    }
+
+    let mut hasher = Sha1::new();
+    for pixel in nes.frame_buffer() {
+        hasher.update(pixel.to_le_bytes());
+    }
+    let hash = hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect();
+    (hash, nes)
+}
+
+#[test]
+fn test_frame_hashes() {
+    let json = fs::read_to_string(MANIFEST).unwrap();
+    let mut cases: Vec<Case> = serde_json::from_str(&json).unwrap();
+    let update = env::var_os(UPDATE_VAR).is_some();
+    let screenshots = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("frame_hashes");
+    fs::create_dir_all(&screenshots).unwrap();
+
+    let mut failed = Vec::new();
+    for case in cases.iter_mut() {
+        let rom = Path::new(ROMS_DIR).join(&case.rom);
+        if !rom.exists() {
+            eprintln!("{}: {} doesn't exist", case.name, rom.display());
+            failed.push(case.name.clone());
+            continue;
+        }
+
+        let (hash, nes) = run(case, &rom);
+        if case.hash.as_ref() == Some(&hash) {
+            continue;
+        }
+
+        let screenshot = screenshots.join(format!("{}.png", case.name));
+        nes.save_screenshot(&screenshot, &Palette::default()).unwrap();
+        if update {
+            eprintln!("{}: updated to {}, see {}", case.name, hash, screenshot.display());
+            case.hash = Some(hash);
+        } else {
+            eprintln!(
+                "{}: expected {}, got {}, see {}",
+                case.name,
+                case.hash.as_deref().unwrap_or("nothing"),
+                hash,
+                screenshot.display(),
+            );
+            failed.push(case.name.clone());
+        }
+    }
+
+    if update {
+        fs::write(MANIFEST, serde_json::to_string_pretty(&cases).unwrap() + "\n").unwrap();
+    }
+    assert!(failed.is_empty(), "{:?} failed, rerun with {}=1 to accept changed frames", failed, UPDATE_VAR);
+}
@@ -0,0 +1,299 @@
+use std::{cell::RefCell, rc::Rc};
+use powerglove::{
+    bus::Bus,
+    cartridge::{Cartridge, CartridgeError, Mirroring},
+    mapper::{self, nrom::Nrom, Mapper},
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+};
+
+/// Build a cartridge for `mapper`, where every 4 KiB of PRG and 1 KiB of CHR is filled with its index.
+fn cartridge(mapper: u8, prg_banks: u8, chr_banks: u8) -> Cartridge {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, prg_banks, chr_banks, mapper << 4, mapper & 0xF0];
+    rom.resize(16, 0);
+    rom.extend((0..prg_banks as usize * 0x4000).map(|i| (i / 0x1000) as u8));
+    rom.extend((0..chr_banks as usize * 0x2000).map(|i| (i / 0x400) as u8));
+    Cartridge::from_bytes(&rom).unwrap()
+}
+
+#[test]
+fn test_unsupported_mapper() {
+    assert!(matches!(mapper::from_cartridge(cartridge(0xFF, 1, 1)), Err(CartridgeError::UnsupportedMapper(0xFF))));
+}
+
+#[test]
+fn test_nrom() {
+    let mut mapper = mapper::from_cartridge(cartridge(0, 1, 1)).unwrap();
+
+    // 16 KiB of PRG-ROM is mirrored into both halves of cartridge space
+    assert_eq!(Some(0x00), mapper.cpu_read(0x8000));
+    assert_eq!(Some(0x00), mapper.cpu_read(0xC000));
+    assert_eq!(Some(0x03), mapper.cpu_read(0xFFFF));
+    assert_eq!(None, mapper.cpu_read(0x5000));
+
+    // ROM can't be written to
+    mapper.cpu_write(0x8000, 0x42);
+    assert_eq!(Some(0x00), mapper.cpu_read(0x8000));
+
+    assert_eq!(0x07, mapper.ppu_read(0x1C00));
+}
+
+/// NROM that logs the scanlines it's notified of.
+#[derive(Debug)]
+struct ScanlineLog {
+    nrom: Nrom,
+    scanlines: Rc<RefCell<Vec<u16>>>,
+}
+
+impl Mapper for ScanlineLog {
+    fn cartridge(&self) -> &Cartridge {
+        self.nrom.cartridge()
+    }
+
+    fn cpu_read(&self, address: u16) -> Option<u8> {
+        self.nrom.cpu_read(address)
+    }
+
+    fn cpu_write(&mut self, address: u16, data: u8) {
+        self.nrom.cpu_write(address, data);
+    }
+
+    fn ppu_read(&mut self, address: u16) -> u8 {
+        self.nrom.ppu_read(address)
+    }
+
+    fn ppu_write(&mut self, address: u16, data: u8) {
+        self.nrom.ppu_write(address, data);
+    }
+
+    fn scanline(&mut self, scanline: u16) {
+        self.scanlines.borrow_mut().push(scanline);
+    }
+}
+
+impl SaveState for ScanlineLog {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.nrom.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.nrom.load_state(reader)
+    }
+}
+
+#[test]
+fn test_scanline_notification() {
+    let scanlines = Rc::new(RefCell::new(Vec::new()));
+    let mut bus = Bus::new();
+    bus.mapper = Some(Box::new(ScanlineLog { nrom: Nrom::new(cartridge(0, 1, 1)), scanlines: scanlines.clone() }));
+
+    // A full frame of 262 scanlines, and the first dot of the next one
+    for _ in 0..=341 * 262 {
+        bus.clock_ppu();
+    }
+    assert_eq!((0..262).chain([0]).collect::<Vec<u16>>(), *scanlines.borrow());
+}
+
+/// Load a value into one of the MMC1 registers through its serial port.
+fn mmc1_write(mapper: &mut dyn mapper::Mapper, address: u16, value: u8) {
+    for bit in 0..5 {
+        mapper.cpu_write(address, (value >> bit) & 1);
+    }
+}
+
+#[test]
+fn test_mmc1() {
+    let mut mapper = mapper::from_cartridge(cartridge(1, 8, 4)).unwrap();
+
+    // The last bank is fixed at $C000 on power-up
+    assert_eq!(Some(0x1C), mapper.cpu_read(0xC000));
+
+    // 16 KiB banks with $C000 fixed, two 4 KiB CHR banks, horizontal mirroring
+    mmc1_write(mapper.as_mut(), 0x8000, 0b1_11_11);
+    mmc1_write(mapper.as_mut(), 0xE000, 3);
+    mmc1_write(mapper.as_mut(), 0xA000, 2);
+    mmc1_write(mapper.as_mut(), 0xC000, 5);
+    assert_eq!(Mirroring::Horizontal, mapper.mirroring());
+    assert_eq!(Some(0x0C), mapper.cpu_read(0x8000));
+    assert_eq!(Some(0x1C), mapper.cpu_read(0xC000));
+    assert_eq!(0x08, mapper.ppu_read(0x0000));
+    assert_eq!(0x14, mapper.ppu_read(0x1000));
+
+    // 32 KiB mode ignores the lowest bit of the bank number
+    mmc1_write(mapper.as_mut(), 0x8000, 0b0_00_10);
+    assert_eq!(Mirroring::Vertical, mapper.mirroring());
+    assert_eq!(Some(0x08), mapper.cpu_read(0x8000));
+    assert_eq!(Some(0x0C), mapper.cpu_read(0xC000));
+    assert_eq!(0x08, mapper.ppu_read(0x0000));
+    assert_eq!(0x0C, mapper.ppu_read(0x1000));
+
+    // A write with bit 7 set resets the shift register and fixes the last bank again
+    mapper.cpu_write(0xE000, 1);
+    mapper.cpu_write(0x8000, 0x80);
+    mmc1_write(mapper.as_mut(), 0xE000, 1);
+    assert_eq!(Some(0x04), mapper.cpu_read(0x8000));
+    assert_eq!(Some(0x1C), mapper.cpu_read(0xC000));
+}
+
+#[test]
+fn test_mmc1_ignores_consecutive_writes() {
+    let mut cart = cartridge(1, 8, 4);
+    let len = cart.prg_rom.len();
+
+    // INC $8000 resets the shift register with its dummy write of $FF. The write of $00 on the
+    // next cycle has to be ignored, or it shifts a stray bit into the register.
+    cart.prg_rom[0] = 0xFF;
+    cart.prg_rom[len - 0x100..len - 0x100 + 22].copy_from_slice(&[
+        0xEE, 0x00, 0x80, // $FF00: INC $8000
+        0xA9, 0x01,       // $FF03: LDA #$01
+        0x8D, 0x00, 0xE0, // $FF05: STA $E000
+        0x4A,             // $FF08: LSR A
+        0x8D, 0x00, 0xE0, // $FF09: STA $E000
+        0x8D, 0x00, 0xE0, // $FF0C: STA $E000
+        0x8D, 0x00, 0xE0, // $FF0F: STA $E000
+        0x8D, 0x00, 0xE0, // $FF12: STA $E000
+        0x4C,             // $FF15: JMP $FF15
+    ]);
+    cart.prg_rom[len - 0xEA..len - 0xE8].copy_from_slice(&[0x15, 0xFF]);
+    cart.prg_rom[len - 4..len - 2].copy_from_slice(&[0x00, 0xFF]);
+
+    let mut cpu = powerglove::cpu::CPU::new();
+    cpu.bus.insert_cartridge(cart).unwrap();
+    cpu.reset();
+    while cpu.pc != 0xFF15 || cpu.step > 0 {
+        cpu.clock();
+    }
+
+    // Bank 1 is now switched in at $8000
+    assert_eq!(0x04, cpu.peek(0x8000));
+}
+
+#[test]
+fn test_uxrom() {
+    let mut mapper = mapper::from_cartridge(cartridge(2, 8, 0)).unwrap();
+
+    mapper.cpu_write(0x8000, 2);
+    assert_eq!(Some(0x08), mapper.cpu_read(0x8000));
+    assert_eq!(Some(0x1C), mapper.cpu_read(0xC000));
+
+    // CHR-RAM can be written to
+    mapper.ppu_write(0x0123, 0x42);
+    assert_eq!(0x42, mapper.ppu_read(0x0123));
+}
+
+#[test]
+fn test_cnrom() {
+    let mut mapper = mapper::from_cartridge(cartridge(3, 2, 4)).unwrap();
+
+    mapper.cpu_write(0x8000, 3);
+    assert_eq!(0x18, mapper.ppu_read(0x0000));
+    assert_eq!(Some(0x04), mapper.cpu_read(0xC000));
+
+    // CHR-ROM can't be written to
+    mapper.ppu_write(0x0000, 0x42);
+    assert_eq!(0x18, mapper.ppu_read(0x0000));
+}
+
+#[test]
+fn test_axrom() {
+    let mut mapper = mapper::from_cartridge(cartridge(7, 8, 0)).unwrap();
+    assert_eq!(Mirroring::SingleScreenLower, mapper.mirroring());
+
+    mapper.cpu_write(0x8000, 0b1_0010);
+    assert_eq!(Some(0x10), mapper.cpu_read(0x8000));
+    assert_eq!(Mirroring::SingleScreenUpper, mapper.mirroring());
+}
+
+#[test]
+fn test_mmc3_banks() {
+    let mut mapper = mapper::from_cartridge(cartridge(4, 8, 16)).unwrap();
+
+    // R6 at $8000 and the second to last bank at $C000
+    mapper.cpu_write(0x8000, 6);
+    mapper.cpu_write(0x8001, 3);
+    mapper.cpu_write(0x8000, 7);
+    mapper.cpu_write(0x8001, 4);
+    assert_eq!(Some(0x06), mapper.cpu_read(0x8000));
+    assert_eq!(Some(0x08), mapper.cpu_read(0xA000));
+    assert_eq!(Some(0x1C), mapper.cpu_read(0xC000));
+    assert_eq!(Some(0x1E), mapper.cpu_read(0xE000));
+
+    // Swapping the PRG mode exchanges $8000 and $C000
+    mapper.cpu_write(0x8000, 0x40);
+    assert_eq!(Some(0x1C), mapper.cpu_read(0x8000));
+    assert_eq!(Some(0x06), mapper.cpu_read(0xC000));
+
+    // 2 KiB banks ignore the lowest bit, and are moved to $1000 with A12 inversion
+    mapper.cpu_write(0x8000, 0);
+    mapper.cpu_write(0x8001, 9);
+    mapper.cpu_write(0x8000, 2);
+    mapper.cpu_write(0x8001, 42);
+    assert_eq!(0x08, mapper.ppu_read(0x0000));
+    assert_eq!(0x09, mapper.ppu_read(0x0400));
+    assert_eq!(42, mapper.ppu_read(0x1000));
+    mapper.cpu_write(0x8000, 0x80);
+    assert_eq!(0x08, mapper.ppu_read(0x1000));
+    assert_eq!(42, mapper.ppu_read(0x0000));
+
+    mapper.cpu_write(0xA000, 1);
+    assert_eq!(Mirroring::Horizontal, mapper.mirroring());
+}
+
+/// Simulate the pattern fetches of a single scanline with the background at $0000 and sprites at $1000.
+fn mmc3_scanline(mapper: &mut dyn mapper::Mapper) {
+    for dot in 0..341 {
+        let address = if (257..=320).contains(&dot) && dot % 8 >= 4 { 0x1000 } else { 0x0000 };
+        mapper.ppu_clock(address);
+    }
+}
+
+#[test]
+fn test_mmc3_irq() {
+    let mut mapper = mapper::from_cartridge(cartridge(4, 8, 16)).unwrap();
+
+    mapper.cpu_write(0xC000, 3);
+    mapper.cpu_write(0xC001, 0);
+    mapper.cpu_write(0xE001, 0);
+
+    // Reload on the first scanline, then count down to 0 on the fourth
+    for _ in 0..3 {
+        mmc3_scanline(mapper.as_mut());
+        assert!(!mapper.irq());
+    }
+    mmc3_scanline(mapper.as_mut());
+    assert!(mapper.irq());
+
+    // Writing to $E000 acknowledges the IRQ
+    mapper.cpu_write(0xE000, 0);
+    assert!(!mapper.irq());
+}
+
+#[test]
+fn test_mmc3_irq_interrupts_cpu() {
+    let mut cart = cartridge(4, 2, 1);
+
+    // The IRQ vector points to $E000, the reset vector to $E100 where CLI is followed by an endless loop
+    let len = cart.prg_rom.len();
+    cart.prg_rom[len - 2..].copy_from_slice(&[0x00, 0xE0]);
+    cart.prg_rom[len - 4..len - 2].copy_from_slice(&[0x00, 0xE1]);
+    cart.prg_rom[len - 0x1F00..len - 0x1EFC].copy_from_slice(&[0x58, 0x4C, 0x01, 0xE1]);
+
+    let mut cpu = powerglove::cpu::CPU::new();
+    cpu.bus.insert_cartridge(cart).unwrap();
+    cpu.reset();
+    for _ in 0..20 {
+        cpu.clock();
+    }
+    assert_ne!(0xE000, cpu.pc);
+
+    // Make the counter hit zero on the very first scanline
+    cpu.write(0xC000, 0);
+    cpu.write(0xC001, 0);
+    cpu.write(0xE001, 0);
+    mmc3_scanline(cpu.bus.mapper.as_mut().unwrap().as_mut());
+
+    // The IRQ may come in too late to be polled by the current JMP, which delays it by another one
+    for _ in 0..13 {
+        cpu.clock();
+    }
+    assert_eq!(0xE000, cpu.pc & 0xFF00);
+}
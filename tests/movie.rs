@@ -0,0 +1,101 @@
+mod common;
+
+use powerglove::{
+    controller::ButtonState,
+    movie::{Movie, MovieCommand, MovieError, MovieFrame, MoviePlayer, MovieRecorder},
+    nes::Nes,
+};
+
+/// Sums up how long the A button is held at $21.
+const PROGRAM: [u8; 15] = [
+    0xA9, 0x80, 0x8D, 0x00, 0x20, // LDA #$80; STA $2000
+    0x18, 0xA5, 0x21, 0x65, 0x10, // CLC; LDA $21; ADC $10
+    0x85, 0x21, 0x4C, 0x05, 0x80, // STA $21; JMP $8005
+];
+
+/// Record 50 frames, holding A during every other frame and pressing reset halfway.
+fn record(nes: &mut Nes) -> Movie {
+    let mut recorder = MovieRecorder::new(nes, 10);
+    for frame in 0..50_u32 {
+        let buttons = if frame.is_multiple_of(2) { ButtonState::A } else { ButtonState::empty() };
+        let command = if frame == 25 { Some(MovieCommand::Reset) } else { None };
+        recorder.run_frame(nes, [buttons, ButtonState::empty()], command);
+    }
+    recorder.finish()
+}
+
+#[test]
+fn test_record_and_play() {
+    let mut nes = common::polling_console(&PROGRAM);
+    let movie = record(&mut nes);
+    let ram = nes.cpu.bus.ram;
+    assert_eq!(50, movie.frames.len());
+    assert_eq!(5, movie.checkpoints.len());
+    assert!(ram[0x21] > 0);
+
+    let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+    nes.run_frame();
+    movie.play(&mut nes).unwrap();
+    assert_eq!(ram, nes.cpu.bus.ram);
+}
+
+#[test]
+fn test_desync() {
+    let mut nes = common::polling_console(&PROGRAM);
+    let mut movie = record(&mut nes);
+
+    // Releasing A a frame early changes the sum in RAM
+    movie.frames[32].buttons[0] = ButtonState::empty();
+    let mut player = MoviePlayer::new(movie.clone(), &mut nes).unwrap();
+    for _ in 0..39 {
+        assert!(player.run_frame(&mut nes).unwrap());
+    }
+    assert!(matches!(player.run_frame(&mut nes), Err(MovieError::Desync { frame: 40, .. })));
+
+    let mut prg = common::polling_prg(&PROGRAM);
+    prg[0x4000] = 0x00;
+    let mut other = common::nrom_console(prg);
+    assert!(matches!(movie.play(&mut other), Err(MovieError::WrongCartridge)));
+    assert!(matches!(Movie::from_bytes(b"PGMX"), Err(MovieError::InvalidMagic)));
+    assert!(matches!(Movie::from_bytes(&movie.to_bytes()[..20]), Err(MovieError::Truncated)));
+}
+
+#[test]
+fn test_fm2() {
+    let fm2 = "version 3\n\
+        emuVersion 22020\n\
+        palFlag 0\n\
+        romFilename test\n\
+        romChecksum base64:AAAAAAAAAAAAAAAAAAAAAA==\n\
+        guid 00000000-0000-0000-0000-000000000000\n\
+        fourscore 0\n\
+        port0 1\n\
+        port1 1\n\
+        port2 0\n\
+        |0|.......A|........||\n\
+        |0|RLDUTSBA|R.......||\n\
+        |1|........|........||\n\
+        |0|.......A|||\n";
+
+    let movie = Movie::from_fm2(fm2).unwrap();
+    assert_eq!(None, movie.rom_crc);
+    assert_eq!(vec![
+        MovieFrame { buttons: [ButtonState::A, ButtonState::empty()], command: None },
+        MovieFrame { buttons: [ButtonState::all(), ButtonState::RIGHT], command: None },
+        MovieFrame { buttons: [ButtonState::empty(); 2], command: Some(MovieCommand::Reset) },
+        MovieFrame { buttons: [ButtonState::A, ButtonState::empty()], command: None },
+    ], movie.frames);
+
+    let mut nes = common::polling_console(&PROGRAM);
+    movie.play(&mut nes).unwrap();
+    assert_eq!(4, nes.frame_count());
+
+    assert!(matches!(
+        Movie::from_fm2("version 3\nfourscore 1\n"),
+        Err(MovieError::Fm2 { line: 2, .. })
+    ));
+    assert!(matches!(
+        Movie::from_fm2("|0|.......A|.A||\n"),
+        Err(MovieError::Fm2 { line: 1, .. })
+    ));
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::polling_console;
+use powerglove::{cartridge::Region, controller::ButtonState};
+
+#[test]
+fn test_frame_timing() {
+    let mut nes = polling_console(&[]);
+
+    // 262 scanlines of 341 dots at 3 dots per CPU cycle
+    nes.run_frame();
+    let start = nes.cycles();
+    nes.run_frame();
+    assert!((29780..=29781).contains(&(nes.cycles() - start)));
+    assert_eq!(2, nes.frame_count());
+
+    // 312 scanlines at 3.2 dots per CPU cycle
+    nes.set_region(Region::Pal);
+    nes.power_on();
+    nes.run_frame();
+    let start = nes.cycles();
+    nes.run_frame();
+    assert!((33247..=33248).contains(&(nes.cycles() - start)));
+}
+
+#[test]
+fn test_step_instruction() {
+    // NOP; LDA $0000; LDA #$02; STA $4014; NOP
+    let mut nes = polling_console(&[0xEA, 0xAD, 0x00, 0x00, 0xA9, 0x02, 0x8D, 0x14, 0x40, 0xEA]);
+
+    // The reset sequence is finished before the first instruction
+    assert_eq!(7 + 2, nes.step_instruction());
+    assert_eq!(4, nes.step_instruction());
+    assert_eq!(2, nes.step_instruction());
+    assert_eq!(4, nes.step_instruction());
+
+    // OAM DMA halts the CPU before the next instruction
+    assert_eq!(513 + 2, nes.step_instruction());
+    assert_eq!(0x800A, nes.cpu.pc);
+}
+
+#[test]
+fn test_nmi_and_input() {
+    // LDA #$80; STA $2000; JMP $8005
+    let mut nes = polling_console(&[0xA9, 0x80, 0x8D, 0x00, 0x20, 0x4C, 0x05, 0x80]);
+
+    nes.run_frame();
+    nes.run_frame();
+    assert_eq!(0, nes.cpu.bus.ram[0x10]);
+
+    nes.set_buttons(0, ButtonState::A);
+    nes.run_frame();
+    nes.run_frame();
+    assert_eq!(1, nes.cpu.bus.ram[0x10]);
+    assert_eq!(3, nes.cpu.bus.ram[0x11]);
+
+    // Resetting keeps RAM, powering on clears it
+    nes.reset();
+    assert_eq!(3, nes.cpu.bus.ram[0x11]);
+    nes.power_on();
+    assert_eq!(0, nes.cpu.bus.ram[0x11]);
+
+    // The reset vector is read at the end of the 7 cycle reset sequence
+    for _ in 0..7 {
+        nes.clock();
+    }
+    assert_eq!(0x8000, nes.cpu.pc);
+}
+
+#[test]
+fn test_audio_output() {
+    let mut nes = polling_console(&[]);
+    nes.set_sample_rate(48_000);
+    nes.run_frame();
+    nes.take_audio_samples();
+
+    for _ in 0..60 {
+        nes.run_frame();
+    }
+
+    // 60 NTSC frames take slightly less than a second
+    let samples = nes.take_audio_samples();
+    assert!((47_900..48_000).contains(&samples.len()));
+    assert!(nes.take_audio_samples().is_empty());
+}
@@ -0,0 +1,52 @@
+use std::{fs, path::PathBuf};
+use powerglove::palette::{Palette, PaletteError, EMPHASIS_BLUE, EMPHASIS_GREEN, EMPHASIS_RED};
+
+#[test]
+fn test_default_palette() {
+    let palette = Palette::default();
+    assert_eq!([84, 84, 84], palette.rgb(0x00));
+    assert_eq!([76, 154, 236], palette.rgb(0x21));
+    assert_eq!([236, 238, 236], palette.rgb(0x30));
+
+    // Every emphasis bit darkens the other two components
+    assert_eq!([236, 178, 176], palette.rgb(0x30 | EMPHASIS_RED));
+    assert_eq!([176, 238, 176], palette.rgb(0x30 | EMPHASIS_GREEN));
+    assert_eq!([131, 132, 131], palette.rgb(0x30 | EMPHASIS_RED | EMPHASIS_GREEN | EMPHASIS_BLUE));
+    assert_eq!([0, 0, 0], palette.rgb(0x0F | EMPHASIS_BLUE));
+
+    // Only the lower 9 bits are used
+    assert_eq!(palette.rgb(0x21), palette.rgb(0x221));
+}
+
+#[test]
+fn test_load() {
+    // FCEUX style, 64 colors
+    let pal: Vec<u8> = (0..64).flat_map(|index| [index, index * 2, index * 3]).collect();
+    let palette = Palette::from_bytes(&pal).unwrap();
+    assert_eq!([0x21, 0x42, 0x63], palette.rgb(0x21));
+    assert_eq!([0x21, 0x31, 0x4A], palette.rgb(0x21 | EMPHASIS_RED));
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("test.pal");
+    fs::write(&path, &pal).unwrap();
+    assert_eq!(palette, Palette::load(&path).unwrap());
+
+    // Mesen style, 512 colors with the emphasized ones given explicitly
+    let pal: Vec<u8> = (0..512_u16).flat_map(|index| [index as u8, (index >> 8) as u8, 0x80]).collect();
+    let palette = Palette::from_bytes(&pal).unwrap();
+    assert_eq!([0x21, 0x00, 0x80], palette.rgb(0x21));
+    assert_eq!([0xA1, 0x01, 0x80], palette.rgb(0x21 | EMPHASIS_GREEN | EMPHASIS_BLUE));
+
+    assert!(matches!(Palette::from_bytes(&pal[..190]), Err(PaletteError::InvalidSize(190))));
+    assert!(matches!(Palette::load("does-not-exist.pal"), Err(PaletteError::Io(_))));
+}
+
+#[test]
+fn test_conversions() {
+    let palette = Palette::default();
+    let frame = [0x21, 0x30 | EMPHASIS_RED];
+
+    assert_eq!(vec![76, 154, 236, 236, 178, 176], palette.to_rgb24(&frame));
+    assert_eq!(vec![76, 154, 236, 0xFF, 236, 178, 176, 0xFF], palette.to_rgba32(&frame));
+    assert_eq!(vec![0xFF4C9AEC, 0xFFECB2B0], palette.to_argb(&frame));
+    assert_eq!(0xFF4C9AEC, palette.argb(0x21));
+}
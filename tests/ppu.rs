@@ -0,0 +1,310 @@
+use powerglove::{
+    bus::{Bus, BusDevice},
+    cartridge::{Cartridge, Region},
+    cpu::CPU,
+    palette::{EMPHASIS_BLUE, EMPHASIS_GREEN, EMPHASIS_RED},
+    ppu::{registers::Status, FRAME_WIDTH},
+};
+
+/// Build an NROM cartridge with 8 KiB of CHR-RAM, and the given PRG-ROM at $8000.
+fn cartridge(flags6: u8, prg: &[u8]) -> Cartridge {
+    let mut rom = vec![b'N', b'E', b'S', 0x1A, 0x02, 0x00, flags6, 0x00];
+    rom.resize(16, 0);
+    rom.extend(prg);
+    rom.resize(16 + 0x8000, 0);
+    Cartridge::from_bytes(&rom).unwrap()
+}
+
+fn bus(flags6: u8) -> Bus {
+    let mut bus = Bus::new();
+    bus.insert_cartridge(cartridge(flags6, &[])).unwrap();
+    bus
+}
+
+fn write_vram(bus: &mut Bus, address: u16, data: &[u8]) {
+    let [lo, hi] = address.to_le_bytes();
+    bus.write(0x2006, hi);
+    bus.write(0x2006, lo);
+    for &byte in data {
+        bus.write(0x2007, byte);
+    }
+}
+
+fn run_frames(bus: &mut Bus, frames: u64) {
+    let target = bus.ppu.frame_count() + frames;
+    while bus.ppu.frame_count() < target {
+        bus.clock_ppu();
+    }
+}
+
+/// Fill tile 1 with color 1, put it in the top left corner of the screen, and set up the palettes.
+fn setup_background(bus: &mut Bus) {
+    write_vram(bus, 0x0010, &[0xFF; 8]);
+    write_vram(bus, 0x2000, &[0x01]);
+    write_vram(bus, 0x3F00, &[0x0F, 0x21]);
+    write_vram(bus, 0x3F11, &[0x16]);
+
+    // Reset the scroll position
+    bus.write(0x2000, 0x00);
+    bus.write(0x2005, 0x00);
+    bus.write(0x2005, 0x00);
+}
+
+#[test]
+fn test_read_buffer() {
+    let mut bus = bus(0x00);
+
+    write_vram(&mut bus, 0x2400, &[0x11, 0x22]);
+
+    // Reads outside of the palette are delayed by one read
+    bus.write(0x2006, 0x24);
+    bus.write(0x2006, 0x00);
+    bus.read(0x2007);
+    assert_eq!(0x11, bus.read(0x2007));
+    assert_eq!(0x22, bus.read(0x2007));
+
+    // Palette reads are not, and $3F10 is a mirror of $3F00
+    write_vram(&mut bus, 0x3F10, &[0x2A]);
+    bus.write(0x2006, 0x3F);
+    bus.write(0x2006, 0x00);
+    assert_eq!(0x2A, bus.read(0x2007));
+}
+
+#[test]
+fn test_increment() {
+    let mut bus = bus(0x00);
+
+    bus.write(0x2000, 0x04);
+    write_vram(&mut bus, 0x2000, &[0x11, 0x22]);
+    bus.write(0x2000, 0x00);
+
+    bus.write(0x2006, 0x20);
+    bus.write(0x2006, 0x20);
+    bus.read(0x2007);
+    assert_eq!(0x22, bus.read(0x2007));
+}
+
+#[test]
+fn test_nametable_mirroring() {
+    // Horizontal mirroring
+    let mut bus = bus(0x00);
+    write_vram(&mut bus, 0x2000, &[0x42]);
+    write_vram(&mut bus, 0x2C00, &[0x24]);
+    write_vram(&mut bus, 0x2400, &[]);
+    bus.read(0x2007);
+    assert_eq!(0x42, bus.read(0x2007));
+    write_vram(&mut bus, 0x2800, &[]);
+    bus.read(0x2007);
+    assert_eq!(0x24, bus.read(0x2007));
+
+    // Vertical mirroring
+    let mut bus = self::bus(0x01);
+    write_vram(&mut bus, 0x2000, &[0x42]);
+    write_vram(&mut bus, 0x2800, &[]);
+    bus.read(0x2007);
+    assert_eq!(0x42, bus.read(0x2007));
+    write_vram(&mut bus, 0x2400, &[]);
+    bus.read(0x2007);
+    assert_eq!(0x00, bus.read(0x2007));
+}
+
+#[test]
+fn test_vblank() {
+    let mut bus = bus(0x00);
+
+    run_frames(&mut bus, 1);
+    assert_eq!(241, bus.ppu.scanline());
+
+    // Reading the status clears the vblank flag
+    assert_eq!(0x80, bus.read(0x2002) & 0x80);
+    assert_eq!(0x00, bus.read(0x2002) & 0x80);
+
+    // No NMIs are generated unless enabled
+    assert!(!bus.nmi());
+    run_frames(&mut bus, 1);
+    assert!(!bus.nmi());
+
+    // Enabling NMIs during vblank generates one immediately
+    bus.write(0x2000, 0x80);
+    assert!(bus.nmi());
+    assert!(!bus.nmi());
+    run_frames(&mut bus, 1);
+    assert!(bus.nmi());
+}
+
+#[test]
+fn test_nmi_interrupts_cpu() {
+    // LDA #$80; STA $2000; JMP $8005, with an NMI handler at $9000
+    let mut prg = vec![0x0; 0x8000];
+    prg[..8].copy_from_slice(&[0xA9, 0x80, 0x8D, 0x00, 0x20, 0x4C, 0x05, 0x80]);
+    prg[0x1000..0x1003].copy_from_slice(&[0x4C, 0x00, 0x90]);
+    prg[0x7FFA..].copy_from_slice(&[0x00, 0x90, 0x00, 0x80, 0x00, 0x80]);
+
+    let mut cpu = CPU::new();
+    cpu.bus.insert_cartridge(cartridge(0x00, &prg)).unwrap();
+    cpu.reset();
+
+    while cpu.bus.ppu.frame_count() == 0 {
+        assert_ne!(0x9000, cpu.pc);
+        for _ in 0..3 {
+            cpu.bus.clock_ppu();
+        }
+        cpu.clock();
+    }
+
+    for _ in 0..20 {
+        cpu.clock();
+    }
+    assert_eq!(0x9000, cpu.pc & 0xFF00);
+}
+
+#[test]
+fn test_background() {
+    let mut bus = bus(0x00);
+    setup_background(&mut bus);
+
+    bus.write(0x2001, 0x0A);
+    run_frames(&mut bus, 2);
+
+    let frame = bus.ppu.frame_buffer();
+    assert_eq!(&[0x21; 8], &frame[0..8]);
+    assert_eq!(0x0F, frame[8]);
+    assert_eq!(0x21, frame[7 * FRAME_WIDTH]);
+    assert_eq!(0x0F, frame[8 * FRAME_WIDTH]);
+
+    // Hiding the left 8 pixels only shows the backdrop there
+    bus.write(0x2001, 0x08);
+    run_frames(&mut bus, 1);
+    assert_eq!(&[0x0F; 8], &bus.ppu.frame_buffer()[0..8]);
+}
+
+#[test]
+fn test_emphasis_and_greyscale() {
+    let mut bus = bus(0x00);
+    setup_background(&mut bus);
+
+    // Emphasis bits end up above the color, greyscale only keeps its brightness
+    bus.write(0x2001, 0x2B);
+    run_frames(&mut bus, 2);
+    let frame = bus.ppu.frame_buffer();
+    assert_eq!(0x20 | EMPHASIS_RED, frame[0]);
+    assert_eq!(EMPHASIS_RED, frame[8]);
+
+    bus.write(0x2001, 0xCA);
+    run_frames(&mut bus, 1);
+    assert_eq!(0x21 | EMPHASIS_GREEN | EMPHASIS_BLUE, bus.ppu.frame_buffer()[0]);
+
+    // PAL PPUs swap red and green
+    bus.ppu.set_region(Region::Pal);
+    bus.write(0x2001, 0x2A);
+    run_frames(&mut bus, 1);
+    assert_eq!(0x21 | EMPHASIS_GREEN, bus.ppu.frame_buffer()[0]);
+
+    // Palette reads are affected by greyscale too
+    bus.write(0x2001, 0x01);
+    bus.write(0x2006, 0x3F);
+    bus.write(0x2006, 0x01);
+    assert_eq!(0x20, bus.read(0x2007) & 0x3F);
+}
+
+#[test]
+fn test_odd_frame_skip() {
+    let dots = |bus: &mut Bus| {
+        run_frames(bus, 1);
+        let mut dots = 0;
+        let target = bus.ppu.frame_count() + 2;
+        while bus.ppu.frame_count() < target {
+            bus.clock_ppu();
+            dots += 1;
+        }
+        dots
+    };
+
+    let mut bus = bus(0x00);
+    assert_eq!(2 * 341 * 262, dots(&mut bus));
+
+    // Every other frame is a dot shorter, even when only sprites are rendered
+    bus.write(0x2001, 0x10);
+    assert_eq!(2 * 341 * 262 - 1, dots(&mut bus));
+}
+
+#[test]
+fn test_fine_scroll() {
+    let mut bus = bus(0x00);
+    setup_background(&mut bus);
+
+    // Scroll 3 pixels to the right, and 2 down
+    bus.write(0x2005, 0x03);
+    bus.write(0x2005, 0x02);
+    bus.write(0x2001, 0x0A);
+    run_frames(&mut bus, 2);
+
+    let frame = bus.ppu.frame_buffer();
+    assert_eq!(&[0x21; 5], &frame[0..5]);
+    assert_eq!(0x0F, frame[5]);
+    assert_eq!(0x21, frame[5 * FRAME_WIDTH]);
+    assert_eq!(0x0F, frame[6 * FRAME_WIDTH]);
+}
+
+#[test]
+fn test_sprites() {
+    let mut bus = bus(0x00);
+    setup_background(&mut bus);
+
+    // Hide all sprites below the screen, except sprite 0 which overlaps the corner tile
+    bus.ram[0x200..0x300].fill(0xFF);
+    bus.ram[0x200..0x204].copy_from_slice(&[0x04, 0x01, 0x00, 0x04]);
+    bus.write(0x2003, 0x00);
+    bus.write(0x4014, 0x02);
+    assert_eq!(513, bus.take_stall_cycles());
+
+    // Starting on an odd CPU cycle takes one more
+    bus.cpu_clock(1);
+    bus.write(0x4014, 0x02);
+    assert_eq!(514, bus.take_stall_cycles());
+    assert_eq!(0x04, bus.ppu.oam[3]);
+
+    bus.write(0x2001, 0x1E);
+    run_frames(&mut bus, 2);
+
+    // Sprites are drawn one scanline below their Y coordinate
+    let frame = bus.ppu.frame_buffer();
+    assert_eq!(0x21, frame[4 * FRAME_WIDTH + 4]);
+    assert_eq!(0x16, frame[5 * FRAME_WIDTH + 4]);
+    assert_eq!(0x16, frame[12 * FRAME_WIDTH + 11]);
+    assert_eq!(0x0F, frame[13 * FRAME_WIDTH + 4]);
+
+    let status = Status::from_bits_truncate(bus.read(0x2002));
+    assert!(status.contains(Status::SPRITE_ZERO_HIT));
+    assert!(!status.contains(Status::SPRITE_OVERFLOW));
+
+    // Putting it behind the background only shows it on transparent background pixels
+    bus.ram[0x202] = 0x20;
+    bus.write(0x4014, 0x02);
+    run_frames(&mut bus, 1);
+    let frame = bus.ppu.frame_buffer();
+    assert_eq!(0x21, frame[5 * FRAME_WIDTH + 4]);
+    assert_eq!(0x16, frame[5 * FRAME_WIDTH + 8]);
+}
+
+#[test]
+fn test_sprite_overflow() {
+    let mut bus = bus(0x00);
+    setup_background(&mut bus);
+
+    // 8 sprites on a scanline is fine
+    bus.ram[0x200..0x300].fill(0xFF);
+    for sprite in 0..8 {
+        bus.ram[0x200 + sprite * 4] = 0x40;
+    }
+    bus.write(0x4014, 0x02);
+    bus.write(0x2001, 0x18);
+    run_frames(&mut bus, 2);
+    assert_eq!(0x00, bus.read(0x2002) & 0x20);
+
+    // But 9 are not
+    bus.ram[0x220] = 0x44;
+    bus.write(0x4014, 0x02);
+    run_frames(&mut bus, 1);
+    assert_eq!(0x20, bus.read(0x2002) & 0x20);
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use std::{env, fs, path::{Path, PathBuf}};
+use common::Recorder;
+use serde::Deserialize;
+use powerglove::{bus::BusDevice, cpu::{CPU, CpuState, CpuVariant, StatusFlags}};
+
+/// Where the 6502 tests of https://github.com/SingleStepTests/ProcessorTests are expected, unless
+/// the PROCESSOR_TESTS environment variable points elsewhere.
+const TESTS_DIR: &str = "./test-roms/ProcessorTests/6502/v1";
+
+/// The JAM opcodes, whose bus activity after locking up isn't emulated.
+const SKIPPED_OPCODES: [u8; 12] = [0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2];
+
+/// One test case, the state before and after a single instruction and the bus activity in between.
+#[derive(Debug, Deserialize)]
+struct TestCase {
+    name: String,
+    initial: CpuSnapshot,
+    #[serde(rename = "final")]
+    expected: CpuSnapshot,
+    cycles: Vec<(u16, u8, String)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CpuSnapshot {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+/// The B and U flags don't exist in the status register, so they're left out of comparisons.
+fn flags(p: u8) -> u8 {
+    p & !(StatusFlags::B | StatusFlags::U).bits()
+}
+
+/// Run a single test case, returning a description of every difference with the expected outcome.
+fn run(case: &TestCase, variant: CpuVariant) -> Vec<String> {
+    let mut cpu = CPU::with_bus(Recorder::default());
+    cpu.variant = variant;
+    cpu.pc = case.initial.pc;
+    cpu.sp = case.initial.s;
+    cpu.a = case.initial.a;
+    cpu.x = case.initial.x;
+    cpu.y = case.initial.y;
+    cpu.status = StatusFlags::from_bits_truncate(case.initial.p);
+    for &(address, data) in &case.initial.ram {
+        cpu.bus.ram.write(address, data);
+    }
+
+    cpu.clock();
+    while cpu.step > 0 && cpu.state == CpuState::Running {
+        cpu.clock();
+    }
+
+    let expected = &case.expected;
+    let mut differences = Vec::new();
+    let registers = [
+        ("pc", expected.pc, cpu.pc),
+        ("s", expected.s.into(), cpu.sp.into()),
+        ("a", expected.a.into(), cpu.a.into()),
+        ("x", expected.x.into(), cpu.x.into()),
+        ("y", expected.y.into(), cpu.y.into()),
+        ("p", flags(expected.p).into(), flags(cpu.status.bits()).into()),
+    ];
+    for (name, expected, actual) in registers {
+        if expected != actual {
+            differences.push(format!("{}: expected {:04X}, got {:04X}", name, expected, actual));
+        }
+    }
+    for &(address, data) in &expected.ram {
+        let actual = cpu.peek(address);
+        if data != actual {
+            differences.push(format!("${:04X}: expected {:02X}, got {:02X}", address, data, actual));
+        }
+    }
+    let cycles: Vec<(u16, u8, String)> = cpu.bus.accesses.iter()
+        .map(|&(address, data, write)| (address, data, if write { "write" } else { "read" }.to_string()))
+        .collect();
+    if case.cycles != cycles {
+        differences.push(format!("cycles: expected {:?}, got {:?}", case.cycles, cycles));
+    }
+
+    differences
+}
+
+/// Run all test cases in a JSON file, returning the failures as `(name, differences)`.
+fn run_file(path: &Path, variant: CpuVariant) -> Vec<(String, Vec<String>)> {
+    let json = fs::read_to_string(path).unwrap();
+    let cases: Vec<TestCase> = serde_json::from_str(&json).unwrap();
+
+    cases.iter()
+        .map(|case| (case.name.clone(), run(case, variant)))
+        .filter(|(_, differences)| !differences.is_empty())
+        .collect()
+}
+
+#[test]
+fn test_single_case() {
+    // LDA ($10),Y crossing a page, which needs a dummy read from the wrong page
+    let case: TestCase = serde_json::from_str(r#"{
+        "name": "b1 10 f0",
+        "initial": {
+            "pc": 512, "s": 253, "a": 0, "x": 0, "y": 32, "p": 36,
+            "ram": [[512, 177], [513, 16], [16, 240], [17, 18], [4880, 128]]
+        },
+        "final": {
+            "pc": 514, "s": 253, "a": 128, "x": 0, "y": 32, "p": 164,
+            "ram": [[512, 177], [513, 16], [16, 240], [17, 18], [4880, 128]]
+        },
+        "cycles": [
+            [512, 177, "read"], [513, 16, "read"], [16, 240, "read"], [17, 18, "read"],
+            [4624, 0, "read"], [4880, 128, "read"]
+        ]
+    }"#).unwrap();
+
+    assert_eq!(Vec::<String>::new(), run(&case, CpuVariant::Nmos6502));
+}
+
+#[test]
+#[ignore = "needs the ProcessorTests, run with `PROCESSOR_TESTS=path/to/6502/v1 cargo test -- --ignored`"]
+fn test_processor_tests() {
+    let dir = env::var_os("PROCESSOR_TESTS").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(TESTS_DIR));
+    assert!(dir.is_dir(), "the ProcessorTests aren't in {}, point PROCESSOR_TESTS at them", dir.display());
+
+    let mut missing = Vec::new();
+    let mut failed = Vec::new();
+    for opcode in 0..=0xFF_u8 {
+        let path = dir.join(format!("{:02x}.json", opcode));
+        if SKIPPED_OPCODES.contains(&opcode) {
+            continue;
+        }
+        if !path.exists() {
+            missing.push(opcode);
+            continue;
+        }
+
+        let failures = run_file(&path, CpuVariant::Nmos6502);
+        if let Some((name, differences)) = failures.first() {
+            eprintln!("{:02X}: {} cases failed, first {}:\n  {}", opcode, failures.len(), name, differences.join("\n  "));
+            failed.push(opcode);
+        }
+    }
+
+    assert!(missing.is_empty(), "Missing tests for opcodes: {:02X?}", missing);
+    assert!(failed.is_empty(), "Failed opcodes: {:02X?}", failed);
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use powerglove::{controller::ButtonState, nes::Nes, rewind::RewindConfig};
+
+/// Build an NROM console that sums up how long the A button is held at $21.
+fn console() -> Nes {
+    common::polling_console(&[
+        0xA9, 0x80, 0x8D, 0x00, 0x20, // LDA #$80; STA $2000
+        0x18, 0xA5, 0x21, 0x65, 0x10, // CLC; LDA $21; ADC $10
+        0x85, 0x21, 0x4C, 0x05, 0x80, // STA $21; JMP $8005
+    ])
+}
+
+/// Hold A during every third frame.
+fn buttons(frame: u64) -> ButtonState {
+    if frame.is_multiple_of(3) { ButtonState::A } else { ButtonState::empty() }
+}
+
+#[test]
+fn test_rewind() {
+    let mut nes = console();
+    nes.enable_rewind(RewindConfig { interval: 4, capacity: 100, keyframe_interval: 5 });
+
+    let mut states = vec![nes.save_state()];
+    for frame in 0..40 {
+        nes.set_buttons(0, buttons(frame));
+        nes.run_frame();
+        states.push(nes.save_state());
+    }
+    assert!(nes.cpu.bus.ram[0x21] > 0);
+
+    // Step back frame by frame, both on and in between snapshots
+    for frame in (30..40).rev() {
+        assert_eq!(1, nes.rewind_frames(1));
+        assert_eq!(states[frame], nes.save_state());
+    }
+
+    assert_eq!(17, nes.rewind_frames(17));
+    assert_eq!(states[13], nes.save_state());
+
+    // Rewinding stops at the first snapshot
+    assert_eq!(13, nes.rewind_frames(100));
+    assert_eq!(states[0], nes.save_state());
+    assert_eq!(0, nes.rewind_frames(1));
+
+    // Playing on after rewinding records new history
+    for frame in 0..10 {
+        nes.set_buttons(0, buttons(frame + 1));
+        nes.run_frame();
+    }
+    let state = nes.save_state();
+    nes.run_frame();
+    assert_eq!(1, nes.rewind_frames(1));
+    assert_eq!(state, nes.save_state());
+}
+
+#[test]
+fn test_bounded_memory() {
+    let mut nes = console();
+    nes.enable_rewind(RewindConfig { interval: 2, capacity: 10, keyframe_interval: 4 });
+
+    for frame in 0..200 {
+        nes.set_buttons(0, buttons(frame));
+        nes.run_frame();
+    }
+
+    let rewind = nes.rewind_buffer().unwrap();
+    assert!((7..=10).contains(&rewind.len()));
+    // Deltas against a keyframe compress to far less than a full state each
+    assert!(rewind.memory_usage() < rewind.len() * nes.save_state().len() / 4);
+
+    // At most 10 snapshots of 2 frames each
+    let rewound = nes.rewind_frames(1000);
+    assert!((14..=20).contains(&rewound));
+
+    nes.disable_rewind();
+    assert_eq!(0, nes.rewind_frames(1));
+}
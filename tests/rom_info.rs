@@ -0,0 +1,75 @@
+use powerglove::{
+    cartridge::HeaderFormat,
+    rom_info::{HeaderAnomaly, RomHashes, RomInfo},
+};
+
+/// An iNES file with `prg_banks` of 16 KiB PRG-ROM and `chr_banks` of 8 KiB CHR-ROM, filled with
+/// their bank numbers.
+fn rom(header: [u8; 16], prg_banks: u8, chr_banks: u8) -> Vec<u8> {
+    let mut rom = header.to_vec();
+    rom[4] = prg_banks;
+    rom[5] = chr_banks;
+    for bank in 0..prg_banks {
+        rom.extend([bank; 0x4000]);
+    }
+    for bank in 0..chr_banks {
+        rom.extend([0x80 | bank; 0x2000]);
+    }
+    rom
+}
+
+#[test]
+fn test_hashes() {
+    let hashes = RomHashes::of(b"abc");
+    assert_eq!(0x352441C2, hashes.crc32);
+    assert_eq!("a9993e364706816aba3e25717850c26c9cd0d89d", hashes.sha1);
+
+    let bytes = rom(*b"NES\x1A\0\0\x11\0\0\0\0\0\0\0\0\0", 2, 1);
+    let info = RomInfo::from_bytes(&bytes).unwrap();
+    assert_eq!(HeaderFormat::INes, info.header.format);
+    assert_eq!(1, info.header.mapper);
+    assert_eq!(Some("MMC1"), info.mapper_name);
+    assert_eq!(RomHashes::of(&bytes[16..16 + 0x8000]), info.prg);
+    assert_eq!(Some(RomHashes::of(&bytes[16 + 0x8000..])), info.chr);
+    assert_eq!(RomHashes::of(&bytes[16..]), info.rom);
+    assert!(info.anomalies.is_empty());
+
+    let report = info.to_string();
+    assert!(report.contains("Mapper:      1 (MMC1), submapper 0"));
+    assert!(report.ends_with("Anomalies:   none"));
+}
+
+#[test]
+fn test_anomalies() {
+    // "DiskDude!" over bytes 7-15 turns mapper 4 into mapper 0x44 for naive parsers
+    let mut bytes = rom(*b"NES\x1A\0\0\x40DiskDude!", 1, 0);
+    bytes.extend([0xFF; 100]);
+    let info = RomInfo::from_bytes(&bytes).unwrap();
+    assert_eq!(4, info.header.mapper);
+    assert_eq!(None, info.chr);
+    assert_eq!(vec![
+        HeaderAnomaly::DirtyHeader { signature: "DiskDude!".to_string() },
+        HeaderAnomaly::TrailingData(100),
+    ], info.anomalies);
+
+    let bytes = rom(*b"NES\x1A\0\0\x50\0\0\0\0\0\0\0\0\0", 2, 1);
+    let info = RomInfo::from_bytes(&bytes[..0x5000]).unwrap();
+    assert_eq!(vec![
+        HeaderAnomaly::Truncated { expected: 16 + 0xA000, found: 0x5000 },
+        HeaderAnomaly::UnsupportedMapper(5),
+    ], info.anomalies);
+    assert_eq!(RomHashes::of(&bytes[16..0x5000]), info.prg);
+    assert_eq!(Some(RomHashes::of(&[])), info.chr);
+
+    let info = RomInfo::from_bytes(&rom(*b"NES\x1A\0\0\0\0\0\0\0\0\0\0\0\0", 0, 1)).unwrap();
+    assert_eq!(vec![HeaderAnomaly::NoPrgRom], info.anomalies);
+    assert!(info.to_string().ends_with("Anomalies:\n  - header specifies no PRG-ROM"));
+
+    // NES 2.0 exponent sizes of 2^63 bytes each for PRG-ROM and CHR-ROM
+    let mut bytes = rom(*b"NES\x1A\0\0\0\x08\0\xFF\0\0\0\0\0\0", 0, 0);
+    bytes[4..6].copy_from_slice(&[0xFC, 0xFC]);
+    let info = RomInfo::from_bytes(&bytes).unwrap();
+    assert_eq!(vec![HeaderAnomaly::RomSizeOverflow], info.anomalies);
+
+    assert!(RomInfo::from_bytes(b"NES\x1A\x01").is_err());
+}
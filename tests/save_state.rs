@@ -0,0 +1,73 @@
+mod common;
+
+use powerglove::save_state::{SaveStateError, STATE_HEADER_SIZE};
+
+/// Enable NMIs and rendering, then keep changing the background color and a counter in RAM.
+const PROGRAM: [u8; 30] = [
+    0xA9, 0x80, 0x8D, 0x00, 0x20, // LDA #$80; STA $2000
+    0xA9, 0x1E, 0x8D, 0x01, 0x20, // LDA #$1E; STA $2001
+    0xA9, 0x3F, 0x8D, 0x06, 0x20, // LDA #$3F; STA $2006
+    0xA9, 0x00, 0x8D, 0x06, 0x20, // LDA #$00; STA $2006
+    0xE6, 0x20, 0xA5, 0x20,       // INC $20; LDA $20
+    0x8D, 0x07, 0x20,             // STA $2007
+    0x4C, 0x0A, 0x80,             // JMP $800A
+];
+
+#[test]
+fn test_round_trip() {
+    let mut nes = common::polling_console(&PROGRAM);
+    nes.run_frame();
+    nes.run_frame();
+    // Save in the middle of an instruction
+    for _ in 0..1234 {
+        nes.clock();
+    }
+    let state = nes.save_state();
+
+    for _ in 0..3 {
+        nes.run_frame();
+    }
+    let frame = nes.frame_buffer().to_vec();
+    let ram = nes.cpu.bus.ram;
+    let cycles = nes.cycles();
+    let later = nes.save_state();
+
+    nes.load_state(&state).unwrap();
+    assert_eq!(state, nes.save_state());
+    for _ in 0..3 {
+        nes.run_frame();
+    }
+    assert_eq!(frame, nes.frame_buffer());
+    assert_eq!(ram, nes.cpu.bus.ram);
+    assert_eq!(cycles, nes.cycles());
+    assert_eq!(later, nes.save_state());
+}
+
+#[test]
+fn test_invalid_states() {
+    let mut nes = common::polling_console(&PROGRAM);
+    nes.run_frame();
+    let state = nes.save_state();
+
+    let mut bad_magic = state.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(Err(SaveStateError::InvalidMagic), nes.load_state(&bad_magic));
+
+    let mut bad_version = state.clone();
+    bad_version[4] = 0xFF;
+    assert_eq!(Err(SaveStateError::UnsupportedVersion(0xFF)), nes.load_state(&bad_version));
+
+    let mut corrupted = state.clone();
+    corrupted[STATE_HEADER_SIZE + 100] ^= 0x01;
+    assert_eq!(Err(SaveStateError::ChecksumMismatch), nes.load_state(&corrupted));
+
+    assert_eq!(Err(SaveStateError::Truncated), nes.load_state(&state[..state.len() - 1]));
+
+    // The same program with different unused PRG-ROM is a different game
+    let mut prg = common::polling_prg(&PROGRAM);
+    prg[0x4000] = 0x00;
+    let mut other = common::nrom_console(prg);
+    assert_eq!(Err(SaveStateError::WrongCartridge), other.load_state(&state));
+
+    assert_eq!(Ok(()), nes.load_state(&state));
+}
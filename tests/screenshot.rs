@@ -0,0 +1,65 @@
+mod common;
+
+use std::{fs, path::PathBuf};
+use powerglove::{
+    nes::Nes,
+    palette::Palette,
+    screenshot::{ImageFormat, ScreenshotError},
+};
+
+/// An NROM console that sets the backdrop color to $21 and points the VRAM address away from the
+/// palette, so with rendering disabled the whole frame shows the backdrop.
+fn console() -> Nes {
+    let mut nes = common::nrom_console(common::prg(&[
+        0xA9, 0x3F, 0x8D, 0x06, 0x20, // LDA #$3F; STA $2006
+        0xA9, 0x00, 0x8D, 0x06, 0x20, // LDA #$00; STA $2006
+        0xA9, 0x21, 0x8D, 0x07, 0x20, // LDA #$21; STA $2007
+        0xA9, 0x00, 0x8D, 0x06, 0x20, // LDA #$00; STA $2006
+        0x8D, 0x06, 0x20,             // STA $2006
+        0x4C, 0x17, 0x80,             // JMP $8017
+    ]));
+    nes.run_frame();
+    nes.run_frame();
+    nes
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name)
+}
+
+#[test]
+fn test_ppm() {
+    let nes = console();
+    assert!(nes.frame_buffer().iter().all(|&color| color == 0x21));
+
+    let path = temp_path("screenshot.ppm");
+    nes.save_screenshot(&path, &Palette::default()).unwrap();
+    let image = fs::read(&path).unwrap();
+    let header = b"P6\n256 240\n255\n";
+    assert_eq!(header, &image[..header.len()]);
+    assert_eq!(256 * 240 * 3, image.len() - header.len());
+    assert!(image[header.len()..].chunks(3).all(|pixel| pixel == [76, 154, 236]));
+}
+
+#[test]
+fn test_png() {
+    let nes = console();
+    let palette = Palette::from_bytes(&[0x55; 192]).unwrap();
+    let path = temp_path("screenshot.png");
+    nes.save_screenshot(&path, &palette).unwrap();
+
+    let decoder = png::Decoder::new(fs::File::open(&path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((256, 240), (info.width, info.height));
+    assert_eq!(png::ColorType::Rgb, info.color_type);
+    assert_eq!(nes.frame_rgb(&palette), pixels[..info.buffer_size()]);
+
+    assert_eq!(Some(ImageFormat::Png), ImageFormat::from_path("SHOT.PNG"));
+    assert_eq!(None, ImageFormat::from_path("shot.bmp"));
+    assert!(matches!(
+        nes.save_screenshot(temp_path("screenshot.bmp"), &palette),
+        Err(ScreenshotError::UnknownFormat),
+    ));
+}
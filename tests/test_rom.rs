@@ -0,0 +1,118 @@
+mod common;
+
+use std::{fs, path::{Path, PathBuf}};
+use powerglove::{cartridge::Cartridge, test_rom::{TestOutcome, TestRunner}};
+
+/// Where blargg's test suites are expected, e.g. `test-roms/blargg/instr_test-v5/rom_singles/01-basics.nes`.
+const BLARGG_DIR: &str = "./test-roms/blargg";
+
+/// Build the iNES image of an NROM test ROM that follows the $6000 protocol. It asks to be reset
+/// on the first boot, then prints `message` and reports `code`.
+fn protocol_image(code: u8, message: &str) -> Vec<u8> {
+    let mut prg = common::prg(&[
+        0xAD, 0x00, 0x60,       // $8000: LDA $6000
+        0xC9, 0x81,             // $8003: CMP #$81
+        0xF0, 0x1E,             // $8005: BEQ $8025
+        0xA9, 0xDE,             // $8007: LDA #$DE
+        0x8D, 0x01, 0x60,       // $8009: STA $6001
+        0xA9, 0xB0,             // $800C: LDA #$B0
+        0x8D, 0x02, 0x60,       // $800E: STA $6002
+        0xA9, 0x61,             // $8011: LDA #$61
+        0x8D, 0x03, 0x60,       // $8013: STA $6003
+        0xA9, 0x80,             // $8016: LDA #$80
+        0x8D, 0x00, 0x60,       // $8018: STA $6000
+        0xA9, 0x81,             // $801B: LDA #$81
+        0x8D, 0x00, 0x60,       // $801D: STA $6000
+        0x4C, 0x20, 0x80,       // $8020: JMP $8020
+        0xEA, 0xEA,             // $8023: NOP; NOP
+        0xA2, 0x00,             // $8025: LDX #$00
+        0xBD, 0x00, 0x81,       // $8027: LDA $8100,X
+        0x9D, 0x04, 0x60,       // $802A: STA $6004,X
+        0xF0, 0x04,             // $802D: BEQ $8033
+        0xE8,                   // $802F: INX
+        0x4C, 0x27, 0x80,       // $8030: JMP $8027
+        0xA9, code,             // $8033: LDA #code
+        0x8D, 0x00, 0x60,       // $8035: STA $6000
+        0x4C, 0x38, 0x80,       // $8038: JMP $8038
+    ]);
+    prg[0x100..0x100 + message.len()].copy_from_slice(message.as_bytes());
+    prg[0x100 + message.len()] = 0x00;
+    common::nrom(prg)
+}
+
+/// `protocol_image` loaded as a cartridge.
+fn protocol_rom(code: u8, message: &str) -> Cartridge {
+    Cartridge::from_bytes(&protocol_image(code, message)).unwrap()
+}
+
+#[test]
+fn test_protocol() {
+    let mut runner = TestRunner::new(protocol_rom(0, "All tests passed\n")).unwrap();
+    let report = runner.run();
+    assert!(report.passed());
+    assert_eq!("All tests passed\n", report.message);
+
+    // The reset request is honoured after a delay
+    assert!(report.frames > 5);
+
+    let report = TestRunner::new(protocol_rom(3, "Failed #3")).unwrap().run();
+    assert_eq!(TestOutcome::Failed(3), report.outcome);
+    assert_eq!("Failed #3", report.message);
+}
+
+#[test]
+fn test_timeout() {
+    // Without the signature, the status isn't trusted
+    let mut cartridge = protocol_rom(0, "");
+    cartridge.prg_rom[0x08] = 0x00;
+
+    let mut runner = TestRunner::new(cartridge).unwrap();
+    runner.set_max_frames(20);
+    let report = runner.run();
+    assert_eq!(TestOutcome::TimedOut, report.outcome);
+    assert_eq!(20, report.frames);
+}
+
+/// Run every ROM found under `dir`, collecting a description of each one that didn't pass.
+fn run_suite(dir: &Path, failures: &mut Vec<String>) {
+    let mut entries: Vec<_> = fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            run_suite(&path, failures);
+        } else if path.extension().is_some_and(|extension| extension == "nes") {
+            let report = TestRunner::load(&path).unwrap().run();
+            if !report.passed() {
+                failures.push(format!("{}: {:?}\n{}", path.display(), report.outcome, report.message));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_suite() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("test_rom_suite");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("singles")).unwrap();
+    fs::write(dir.join("01-passes.nes"), protocol_image(0, "Passed\n")).unwrap();
+    fs::write(dir.join("singles/02-fails.nes"), protocol_image(2, "Failed #2")).unwrap();
+    fs::write(dir.join("readme.txt"), "Not a ROM").unwrap();
+
+    // ROMs in subdirectories are found too, and everything that isn't a ROM is left alone
+    let mut failures = Vec::new();
+    run_suite(&dir, &mut failures);
+    assert_eq!(1, failures.len());
+    assert!(failures[0].contains("02-fails.nes: Failed(2)\nFailed #2"), "{}", failures[0]);
+}
+
+#[test]
+#[ignore = "needs blargg's test ROMs in test-roms/blargg, run with `cargo test -- --ignored` once they're there"]
+fn test_blargg_suites() {
+    let dir = Path::new(BLARGG_DIR);
+    assert!(dir.is_dir(), "blargg's test ROMs aren't in {}", dir.display());
+
+    let mut failures = Vec::new();
+    run_suite(dir, &mut failures);
+    assert!(failures.is_empty(), "{} test ROMs failed:\n{}", failures.len(), failures.join("\n"));
+}